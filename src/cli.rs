@@ -0,0 +1,396 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum, ValueHint::FilePath};
+use fiux::{
+    ArrowCompression, AvroCodec, Binary, CborTags, Codec, ColumnType, CsvDialect, LateKeys,
+    LogFormat, ParquetCompression, QuoteStyle, Ragged, SchemaFormat, Terminator,
+};
+
+static LONG_ABT: &str = r#"
+fiux: The fastest streaming-first file conveter.
+
+  • Supports JSON, NDJSON, TOML, YAML, CSV, TSV, PSV and more!
+
+  • Formats are detected automatically based on file extension, except for custom 
+    delimter CSV formats (e.g. TSV, PSV, etc.), which are detected with `--input-delimiter <DELIMITER>` and `--output-delimiter <DELIMITER>`.
+
+  • if there are any bugs or any features you want, open an issue at: `https://github.com/Tahaa-Dev/fiux`.
+
+
+╭────────────────·Examples·────────────────╮
+│                                      ••• │
+│ fiux convert data.json out.csv           │
+│ fiux validate broken.ndjson --verbose    │
+│ fiux convert big.csv big.json --verbose  │
+│                                          │
+╰──────────────────────────────────────────╯
+"#;
+
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about = "The fastest utility for converting between file formats.",
+    long_about = LONG_ABT
+)]
+pub struct Args {
+    #[command(subcommand)]
+    pub cmd: Commands,
+
+    /// Argument for setting a Markdown (MD) file to export error logs to.
+    #[arg(short, long, value_hint = FilePath, global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Argument for the format of error logs, `json` / `ndjson` write one structured event per
+    /// error plus a final summary event (defaults to `markdown` for log files and `text` for
+    /// stderr).
+    #[arg(long, value_enum, value_name = "FORMAT", global = true)]
+    pub log_format: Option<LogFormat>,
+}
+
+/// fiux subcommands
+#[derive(Subcommand)]
+// parsed once per run, boxing `Convert`'s flags wouldn't save anything
+#[allow(clippy::large_enum_variant)]
+pub enum Commands {
+    /// Convert command that takes two positional arguments for input and output, takes one
+    Convert {
+        /// Argument for input file path, `-` reads from stdin
+        #[arg(required = true, value_hint = FilePath)]
+        input: PathBuf,
+
+        /// Argument for output file path, `-` writes to stdout (requires `--to`)
+        #[arg(short, long, required = true, value_hint = FilePath)]
+        output: PathBuf,
+
+        /// Argument for specifying the input format (e.g. json, csv), `auto` detects it from the
+        /// input's content and falls back to its extension.
+        #[arg(long, value_name = "FORMAT", default_value = "auto")]
+        from: String,
+
+        /// Argument for specifying the output format (e.g. json, csv) instead of detecting it
+        /// from the output file's extension.
+        #[arg(long, value_name = "FORMAT")]
+        to: Option<String>,
+
+        /// Argument to append into output file instead of overwriting it (WARNING: This
+        /// can lead to unexpected output on some formats)
+        #[arg(short, long)]
+        append: bool,
+
+        /// Argument for inferring the types of CSV values (integers, floats, dates and
+        /// datetimes) instead of writing them as strings, leading-zero values stay strings.
+        #[arg(short, long)]
+        parse_numbers: bool,
+
+        /// Argument for the types of CSV columns (e.g. `id=int,zip=string`), overriding the
+        /// inferred ones. Types are auto, string, int, float, bool, date and datetime.
+        #[arg(long, value_name = "COL=TYPE", value_delimiter = ',', value_parser = parse_type)]
+        types: Vec<(String, ColumnType)>,
+
+        /// Argument for specifying delimiters for CSV / CSV-like input formats (e.g. TSV, PSV, etc.).
+        /// This flag makes fiux ignore the extension and instead treat the file as a CSV
+        /// with the specified delimiter instead of commas.
+        #[arg(long)]
+        input_delimiter: Option<char>,
+
+        /// Argument for specifying delimiters for CSV / CSV-like output formats (e.g. TSV, PSV, etc.).
+        /// This flag makes fiux ignore the extension and instead treat the file as a CSV
+        /// with the specified delimiter instead of commas.
+        #[arg(long)]
+        output_delimiter: Option<char>,
+
+        #[command(flatten)]
+        input_dialect: InputDialect,
+
+        #[command(flatten)]
+        output_dialect: OutputDialect,
+
+        /// Argument for choosing how CSV output handles keys that first appear after the header
+        /// row was derived when converting from JSON / NDJSON / TOML, `union` holds every record
+        /// in memory until the end of the input while `drop` / `error` stream them.
+        #[arg(long, value_enum, default_value_t = LateKeys::Union)]
+        late_keys: LateKeys,
+
+        /// Argument for compressing the output with a codec, inferred from compound output
+        /// extensions (e.g. `out.ndjson.gz`) when not set.
+        #[arg(long, value_enum, value_name = "CODEC")]
+        compress: Option<Codec>,
+
+        /// Argument for the compression level of the output (0-9, 1-9 for bzip2, 1-22 for zstd,
+        /// negative zstd levels are its faster modes).
+        #[arg(long, allow_negative_numbers = true)]
+        level: Option<i32>,
+
+        /// Argument for a file to write records that failed to decode or encode into (with their
+        /// line numbers) instead of only logging them.
+        #[arg(long, value_name = "PATH", value_hint = FilePath)]
+        reject_file: Option<PathBuf>,
+
+        /// Argument for aborting the conversion once this many records failed, fiux exits with
+        /// code 2 when it finishes after skipping invalid records.
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        max_errors: Option<u64>,
+
+        /// Argument for aborting the conversion on the first invalid record without leaving any
+        /// partial output behind.
+        #[arg(long, conflicts_with = "max_errors")]
+        strict: bool,
+
+        /// Argument for the XML element that holds a record (e.g. `item`), the children of the
+        /// root element are records when not set.
+        #[arg(long, value_name = "TAG")]
+        xml_record: Option<String>,
+
+        /// Argument for the name of the root element of XML output.
+        #[arg(long, value_name = "NAME", default_value = "root")]
+        xml_root: String,
+
+        /// Argument for the name of the element that wraps every record in XML output.
+        #[arg(long, value_name = "NAME", default_value = "row")]
+        xml_row: String,
+
+        /// Argument for how MessagePack binary blobs / ext data and CBOR byte strings are written
+        /// into records, as base64 strings or arrays of byte values.
+        #[arg(long, value_enum, default_value_t = Binary::Base64)]
+        binary: Binary,
+
+        /// Argument for converting CBOR tags with a known meaning (e.g. epoch datetimes,
+        /// bignums) into plain values or keeping every tag as a `{"$tag", "$value"}` object.
+        #[arg(long, value_enum, default_value_t = CborTags::Convert)]
+        cbor_tags: CborTags,
+
+        /// Argument for the maximum number of rows in a row group of Parquet output.
+        #[arg(long, value_name = "N", default_value_t = 1024 * 1024, value_parser = clap::value_parser!(u64).range(1..))]
+        row_group_size: u64,
+
+        /// Argument for the compression codec of the column chunks in Parquet output.
+        #[arg(long, value_enum, default_value_t = ParquetCompression::Snappy)]
+        parquet_compression: ParquetCompression,
+
+        /// Argument for the maximum number of rows in a record batch of Arrow output.
+        #[arg(long, value_name = "N", default_value_t = 64 * 1024, value_parser = clap::value_parser!(u64).range(1..))]
+        batch_size: u64,
+
+        /// Argument for the compression codec of the record batch buffers in Arrow output.
+        #[arg(long, value_enum, default_value_t = ArrowCompression::Uncompressed)]
+        arrow_compression: ArrowCompression,
+
+        /// Argument for the Avro schema (`.avsc`) file of Avro output, records that don't match it
+        /// are skipped.
+        #[arg(long, value_name = "PATH", value_hint = FilePath)]
+        avro_schema: Option<PathBuf>,
+
+        /// Argument for the number of records the schema of Avro output is inferred from when
+        /// `--avro-schema` isn't set.
+        #[arg(long, value_name = "N", default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+        avro_infer: u64,
+
+        /// Argument for the compression codec of the blocks in Avro output.
+        #[arg(long, value_enum, default_value_t = AvroCodec::Null)]
+        avro_codec: AvroCodec,
+
+        /// Argument for an expression records have to match to be written (e.g. `age >= 18 and
+        /// email is not null`), fields are dotted key paths for records and headers for tables.
+        #[arg(long = "where", value_name = "EXPR")]
+        filter: Option<String>,
+
+        /// Argument for the fields to keep (e.g. `id,address.city`), as dotted key paths for
+        /// records and headers for tables.
+        #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+        select: Vec<String>,
+
+        /// Argument for the fields to drop, applied after `--select`.
+        #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+        exclude: Vec<String>,
+
+        /// Argument for renaming fields (e.g. `address.city=city`), applied after `--select` and
+        /// `--exclude`.
+        #[arg(long, value_name = "OLD=NEW", value_delimiter = ',', value_parser = parse_rename)]
+        rename: Vec<(String, String)>,
+    },
+
+    /// Validate command for file format validation with one positional argument for the file
+    Validate {
+        /// path to the file to be validated
+        #[arg(required = true, value_hint = FilePath)]
+        input: PathBuf,
+
+        /// Argument for specifying delimiters for CSV / CSV-like input formats (e.g. TSV, PSV, etc.).
+        /// This flag makes fiux ignore the extension and instead treat the file as a CSV
+        /// with the specified delimiter instead of commas.
+        #[arg(short, long)]
+        delimiter: Option<char>,
+
+        #[command(flatten)]
+        input_dialect: InputDialect,
+
+        /// Argument for a JSON Schema file every record is checked against (e.g. from `fiux
+        /// schema --format json-schema`), CSV rows are checked as objects.
+        #[arg(long, value_name = "PATH", value_hint = FilePath)]
+        schema: Option<PathBuf>,
+    },
+
+    /// Schema command that profiles the fields of a file (types, nullability, min / max,
+    /// distinct counts and examples)
+    Schema {
+        /// Argument for input file path, `-` reads from stdin
+        #[arg(required = true, value_hint = FilePath)]
+        input: PathBuf,
+
+        /// Argument for the file to write the report into instead of stdout.
+        #[arg(short, long, value_hint = FilePath)]
+        output: Option<PathBuf>,
+
+        /// Argument for specifying the input format (e.g. json, csv), `auto` detects it from the
+        /// input's content and falls back to its extension.
+        #[arg(long, value_name = "FORMAT", default_value = "auto")]
+        from: String,
+
+        /// Argument for the format of the report, a table of fields or a JSON Schema document.
+        #[arg(long, value_enum, default_value_t = SchemaFormat::Table)]
+        format: SchemaFormat,
+
+        /// Argument for specifying delimiters for CSV / CSV-like input formats (e.g. TSV, PSV, etc.).
+        /// This flag makes fiux ignore the extension and instead treat the file as a CSV
+        /// with the specified delimiter instead of commas.
+        #[arg(short, long)]
+        delimiter: Option<char>,
+
+        #[command(flatten)]
+        input_dialect: InputDialect,
+
+        /// Argument for the types of CSV columns (e.g. `id=int,zip=string`), overriding the
+        /// inferred ones.
+        #[arg(long, value_name = "COL=TYPE", value_delimiter = ',', value_parser = parse_type)]
+        types: Vec<(String, ColumnType)>,
+
+        /// Argument for the XML element that holds a record (e.g. `item`), the children of the
+        /// root element are records when not set.
+        #[arg(long, value_name = "TAG")]
+        xml_record: Option<String>,
+    },
+}
+
+/// CSV dialect flags for input, shared by `convert`, `validate` and `schema`
+#[derive(clap::Args)]
+#[command(next_help_heading = "CSV input")]
+pub struct InputDialect {
+    /// Argument for the character that quotes fields in CSV input.
+    #[arg(long, value_name = "CHAR", default_value_t = '"')]
+    input_quote: char,
+
+    /// Argument for the character that escapes quotes in CSV input (e.g. `\\`) instead of
+    /// doubled quotes.
+    #[arg(long, value_name = "CHAR")]
+    input_escape: Option<char>,
+
+    /// Argument for reading doubled quotes in CSV input as two quotes instead of an escaped one.
+    #[arg(long)]
+    input_no_double_quote: bool,
+
+    /// Argument for skipping lines that start with a character (e.g. `#`) in CSV input.
+    #[arg(long, value_name = "CHAR")]
+    comment: Option<char>,
+
+    /// Argument for trimming whitespace around fields and headers in CSV input.
+    #[arg(long)]
+    trim: bool,
+
+    /// Argument for reading the first row of CSV input as a record, headers are `col1` to `colN`
+    /// unless `--header-names` is set.
+    #[arg(long)]
+    no_header: bool,
+
+    /// Argument for the headers of CSV input without a header row (e.g. `id,name,email`).
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', requires = "no_header")]
+    header_names: Vec<String>,
+
+    /// Argument for the record terminator of CSV input, any of `\r\n`, `\n` and `\r` by
+    /// default.
+    #[arg(long, value_enum, value_name = "TERMINATOR")]
+    input_terminator: Option<Terminator>,
+
+    /// Argument for handling CSV rows with more or fewer fields than the header row, every
+    /// policy but `error` pads short rows with empty fields (empty strings, not nulls).
+    #[arg(long, value_enum, default_value_t = Ragged::Error)]
+    ragged: Ragged,
+}
+
+impl InputDialect {
+    pub fn dialect(&self) -> CsvDialect {
+        CsvDialect {
+            quote: self.input_quote,
+            escape: self.input_escape,
+            double_quote: !self.input_no_double_quote,
+            comment: self.comment,
+            trim: self.trim,
+            has_headers: !self.no_header,
+            header_names: self.header_names.clone(),
+            terminator: self.input_terminator,
+            ragged: self.ragged,
+            ..CsvDialect::default()
+        }
+    }
+}
+
+/// CSV dialect flags for output
+#[derive(clap::Args)]
+#[command(next_help_heading = "CSV output")]
+pub struct OutputDialect {
+    /// Argument for the character that quotes fields in CSV output.
+    #[arg(long, value_name = "CHAR", default_value_t = '"')]
+    output_quote: char,
+
+    /// Argument for the character that escapes quotes in CSV output (e.g. `\\`) instead of
+    /// doubling them.
+    #[arg(long, value_name = "CHAR")]
+    output_escape: Option<char>,
+
+    /// Argument for the record terminator of CSV output, `\n` by default.
+    #[arg(long, value_enum, value_name = "TERMINATOR")]
+    output_terminator: Option<Terminator>,
+
+    /// Argument for which fields get quoted in CSV output.
+    #[arg(long, value_enum, default_value_t = QuoteStyle::Necessary)]
+    quote_style: QuoteStyle,
+
+    /// Argument for writing CSV output without a header row.
+    #[arg(long)]
+    output_no_header: bool,
+}
+
+impl OutputDialect {
+    pub fn dialect(&self) -> CsvDialect {
+        CsvDialect {
+            quote: self.output_quote,
+            escape: self.output_escape,
+            // escaped quotes aren't doubled
+            double_quote: self.output_escape.is_none(),
+            has_headers: !self.output_no_header,
+            terminator: self.output_terminator,
+            quote_style: self.quote_style,
+            ..CsvDialect::default()
+        }
+    }
+}
+
+/// Parses an `old=new` pair for `--rename`.
+fn parse_rename(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+            Ok((old.to_string(), new.to_string()))
+        }
+        _ => Err(format!("`{}` isn't an `OLD=NEW` pair", s)),
+    }
+}
+
+/// Parses a `column=type` pair for `--types`.
+fn parse_type(s: &str) -> Result<(String, ColumnType), String> {
+    match s.split_once('=') {
+        Some((col, ty)) if !col.is_empty() => {
+            Ok((col.to_string(), ColumnType::from_str(ty, true)?))
+        }
+        _ => Err(format!("`{}` isn't a `COL=TYPE` pair", s)),
+    }
+}
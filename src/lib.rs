@@ -0,0 +1,537 @@
+/*!
+**fiux: The *fastest* multi-format file converter CLI tool**
+
+fiux provides intuitive file conversion similar to `mlr`'s `cat` verb but with a few advantages over it:
+
+1. Auto format detection through content sniffing and extension
+2. Better error-handling with recoverable errors, logging options, comprehensive logs and a `validate` command for debugging
+3. 5× - 6× faster than `mlr`'s `cat` verb
+
+---
+
+## Subcommands & flags
+
+### 1. Convert:
+
+##### Description
+
+- Convert from input file to output file
+- If any item in the input file is invalid, the item is skipped and fiux logs an error message
+- Only panics on read / write failure
+##### Usage
+
+Basic usage:
+
+```sh
+fiux convert <INPUT> -o <OUTPUT>
+```
+
+##### Arguments
+
+1. Input: Input file to be converted, will panic if it doesn't exist or if its extension is not supported, `-` reads from stdin.
+2. Output: `--output` / `-o` flag, file to write output to, will panic only if its extension is not supported, will create the file if it doesn't exist, `-` writes to stdout.
+
+##### Flags (options)
+
+1. `--append` / `-a`: fiux overwrites existing data in the output file by default, this flag makes it append to it instead. **WARNING:** This flag can lead to corrupted output with some formats like JSON.
+2. `--parse-numbers` / `-p` / `--types <COL=TYPE>`: Infer the types of CSV values when converting them into JSON / NDJSON / TOML / YAML instead of writing them as strings. Integers bigger than 64 bits are kept exact (as numbers in JSON, strings in TOML), numbers with leading zeros (e.g. ZIP codes) stay strings and ISO dates / datetimes become native TOML datetimes, `true` / `false` / `null` are always typed. `--types id=int,zip=string` sets the types of columns by their output header (`auto`, `string`, `int`, `float`, `bool`, `date` or `datetime`) even without `-p`, records with values that aren't of their column's type are skipped.
+3. `--input-delimiter` / `--output-delimiter`: Flags that make fiux ignore file extension and treat them as CSV with the specified delimiter
+4. `--from` / `--to`: Flags for setting the input / output format (e.g. `json`, `csv`), `--to` is required when writing to stdout. `--from` defaults to `auto` which inspects the input's first bytes (leading `{` / `[`, one JSON value per line, TOML `key = value` / `[table]` lines, leading XML tags, MessagePack map / array markers, CBOR map markers or the self-described CBOR tag, the Parquet magic `PAR1`, the Avro magic `Obj\x01`, the Arrow IPC magic `ARROW1` or stream marker `0xFFFFFFFF`, YAML `key: value` lines and delimiter frequency for CSV / TSV / PSV), the extension is trusted when the content is consistent with it and the detected format is reported along with why it was picked.
+5. CSV dialect flags for input (`convert` and `validate`):
+    - `--input-quote <CHAR>` / `--input-escape <CHAR>` / `--input-no-double-quote`: Quote character (`"` by default), escape character for quotes inside quoted fields and whether doubled quotes are an escaped quote.
+    - `--comment <CHAR>`: Skip lines that start with a character (e.g. `#`).
+    - `--trim`: Trim whitespace around fields and headers.
+    - `--no-header` / `--header-names <NAMES>`: Read the first row as a record, headers are `col1` to `colN` unless names are given (e.g. `--header-names id,name`).
+    - `--input-terminator crlf|lf|cr`: Record terminator, any of `\r\n`, `\n` and `\r` by default.
+    - `--ragged error|pad|truncate|extra-column`: Policy for rows with more or fewer fields than the header row. `error` (default) skips them as invalid records, the other policies pad short rows with empty fields (empty strings rather than nulls, except for outputs like Avro and Parquet that write empty fields as nulls) and differ on long rows: `pad` skips them, `truncate` drops their extra fields and `extra-column` collects them into an `_extra` column as a JSON array (e.g. `["6","7"]`, `[]` for rows without extra fields) that JSON, YAML, TOML, CBOR and MessagePack outputs write as a real array. `validate` reports every row whose field count the policy doesn't allow.
+6. CSV dialect flags for output:
+    - `--output-quote <CHAR>` / `--output-escape <CHAR>`: Quote character and escape character for quotes (quotes are doubled by default).
+    - `--quote-style always|necessary|never|non-numeric`: Which fields get quoted (`necessary` by default).
+    - `--output-terminator crlf|lf|cr`: Record terminator (`\n` by default).
+    - `--output-no-header`: Don't write a header row.
+7. `--late-keys union|drop|error`: Policy for keys that first appear after the header row when converting JSON / NDJSON / TOML into CSV, nested objects and arrays are flattened into dotted columns (e.g. `address.city`, `tags.0`). Whole documents are split into rows first: arrays into their elements and objects whose only key holds an array of objects (e.g. a TOML file of `[[Rows]]` tables) into that array's objects, other documents are a single row.
+    - `union` (default): buffers all records until the end of the input and uses the union of their keys as headers.
+    - `drop`: streams records with the first record's keys as headers, dropping unknown keys.
+    - `error`: streams records with the first record's keys as headers, skipping records with unknown keys.
+8. `--compress` / `--level`: Compress the output with `gzip`, `zstd`, `bzip2` or `xz` at an optional level (0-9, 1-9 for bzip2, 1-22 for zstd with negative levels down to -131072 selecting its faster modes), the codec is inferred from compound output extensions (e.g. `out.ndjson.gz`) when the flag isn't set. Compressed inputs are always detected from their magic bytes and decompressed transparently.
+9. `--reject-file`: File to quarantine records that fail to decode into, every NDJSON line / CSV row that is skipped is written with its original bytes as a `<line>\t<raw record>` line so it can be fixed and replayed (e.g. with `cut -f2-`). Records that the output format fails to encode (e.g. fields that don't match `--types` or records that don't match an Avro / Parquet schema) are written too: CSV rows in the input's dialect and other records as JSON lines after their record number.
+10. `--max-errors <N>`: Abort the conversion once `N` records failed instead of skipping any number of them.
+11. `--strict`: Abort the conversion on the first invalid record without leaving any partial output, files are written into a temporary file that only replaces the output once the conversion succeeds.
+12. `--xml-record <TAG>`: XML element that holds a record (e.g. `item`), matched by its qualified or local name at any depth, the children of the root element are records when it isn't set. Attributes become `@attr` keys, text next to attributes or children becomes a `#text` key and repeated children become arrays.
+13. `--xml-root` / `--xml-row`: Names of the root element and of the element that wraps every record in XML output (`root` / `row` by default), the same `@attr` / `#text` conventions map keys back into attributes and text. Characters that element / attribute names can't hold are replaced with `_`, including the `:` of namespace prefixes since namespace declarations aren't kept (e.g. `dc:title` becomes `dc_title`). Records with control characters XML doesn't allow or with keys that become the same attribute name are skipped as invalid records.
+14. `--where <EXPR>`: Only write records that match an expression, e.g. `age >= 18 and (email is not null or name contains "x")`. Fields are dotted key paths (`address.city`, backticks quote names with spaces) or table headers and are compared with `==`, `!=`, `<`, `<=`, `>`, `>=`, `contains` (substrings or array elements), `=~` / `!~` (regexes) and `is null` / `is not null`, combined with `and`, `or`, `not` and parentheses. Strings are compared as numbers against numbers, so table fields work with numeric comparisons, and missing fields are null.
+15. `--select` / `--exclude` / `--rename`: Keep only some fields (`--select id,address.city`), drop fields (`--exclude notes`) or rename them (`--rename address.city=city`), applied in that order after `--where`. Records use dotted key paths into nested objects while tables use their headers and reorder their columns in `--select`'s order, unknown columns in tables are an error.
+16. `--binary base64|array`: How MessagePack binary blobs and CBOR byte strings are written into records, as standard base64 strings (default) or arrays of byte values. Ext values become `{"$ext": {"type": <type>, "data": <bytes>}}` objects, which MessagePack output writes back as ext values.
+17. `--cbor-tags convert|preserve`: CBOR tags with a known meaning are converted into plain values by default: date / time strings (tags 0 and 1004) stay strings, epoch datetimes (tag 1) become RFC 3339 strings in UTC, bignums (tags 2 and 3) become numbers or decimal strings when they don't fit into 64 bits and decimal fractions / bigfloats (tags 4 and 5) become floats. Other tags, and every tag with `preserve`, become `{"$tag": <tag>, "$value": <value>}` objects which CBOR output writes back as tags. CBOR output of CSV tables writes bignums for integers bigger than 64 bits and tags datetimes / dates.
+18. `--row-group-size <N>`: Maximum number of rows in a row group of Parquet output (1,048,576 by default), row groups are written one at a time so memory use is bounded by their size. The column types are inferred from the first row group (or its first 65,536 records): CSV fields are typed like `-p` / `--types` type them (integers, floats, booleans, dates and UTC timestamps, other columns are strings) and JSON objects by their values, later records that don't fit the inferred types or have keys that weren't in the first row group are skipped.
+19. `--parquet-compression uncompressed|snappy|gzip|zstd`: Compression codec of the column chunks in Parquet output (`snappy` by default).
+20. `--avro-schema <PATH>`: Avro schema (`.avsc` JSON) that records are written with, records that don't match it are skipped with the path of the first mismatching field. Logical types (decimals, dates, times, timestamps, UUIDs) are converted from their JSON text forms.
+21. `--avro-infer <N>`: Number of leading records the Avro schema is inferred from when `--avro-schema` isn't set (1,000 by default), objects become records, fields missing from some records or holding nulls become nullable unions. Later records with keys the inferred schema doesn't have are skipped, while records are written without the keys a given `--avro-schema` doesn't have. CSV columns are always nullable with empty fields written as nulls, and integers beyond the 64-bit range are written as strings.
+22. `--avro-codec null|deflate|snappy`: Compression codec of the blocks in Avro output (`null` by default).
+23. `--batch-size <N>`: Maximum number of rows in a record batch of Arrow output (65,536 by default), CSV rows are collected into typed columns and written one batch at a time. The column types are inferred from the first batch like Parquet's, later records that don't fit them or have keys that weren't in the first batch are skipped.
+24. `--arrow-compression uncompressed|lz4|zstd`: Compression codec of the record batch buffers in Arrow output (`uncompressed` by default, which every Arrow reader supports).
+
+##### Exit codes
+
+- `0`: Every record was converted.
+- `1`: The conversion failed or was aborted (read / write failures, `--max-errors`, `--strict`).
+- `2`: The conversion finished but skipped invalid records.
+
+---
+
+### 2. Validate
+
+##### Description
+
+- Test if input file is valid
+- Logs a detailed error message if an item is invalid then continues to validate the rest of the items
+- Only panics on read failure
+##### Usage
+
+Basic usage:
+
+```sh
+fiux validate <INPUT>
+```
+
+##### Arguments
+
+Input: File to be validated, will panic if it doesn't exist
+
+##### Flags (options)
+
+1. `--delimiter` / `-d`: Flag that makes fiux ignore file extension and treat the file as a CSV with the specified delimiter, the CSV input dialect flags of `convert` (e.g. `--input-quote`, `--comment`, `--no-header`) work the same way
+2. `--schema <PATH>`: Check every record (JSON values, NDJSON lines, CSV rows as objects, TOML / YAML documents and XML records) against a JSON Schema, e.g. one written by `fiux schema --format json-schema`. The draft 2020-12 keywords `type`, `enum`, `const`, `required`, `properties`, `additionalProperties`, `items`, `pattern`, `minimum` / `maximum` (and their exclusive variants), `minLength` / `maxLength` and `minItems` / `maxItems` are checked, others (e.g. `format`) are ignored. Every violation is reported with its record number and JSON pointer (e.g. `/address/zip`). CSV fields are typed like `--parse-numbers` types them, empty fields are nulls and fields whose property doesn't allow their type are checked as text.
+
+### 3. Schema
+
+##### Description
+
+- Profiles every field / column of a file before converting it, streaming it through the same decoders as `convert`
+- Reports the inferred type(s) of every field, how often it was present and null, its min / max (left blank for fields with both numbers and strings), a distinct-count estimate (exact up to 10,000 values, `~` marks estimates) and example values
+- Nested fields are reported by dotted key paths (e.g. `address.city`) and array elements under `[]` (e.g. `tags[]`)
+##### Usage
+
+Basic usage:
+
+```sh
+fiux schema <INPUT>
+```
+
+##### Arguments
+
+Input: File to be profiled, `-` reads from stdin
+
+##### Flags (options)
+
+1. `--format table|json-schema`: Write a table of fields (default) or a JSON Schema (draft 2020-12) document with types, `required` fields, `minimum` / `maximum`, `minLength` / `maxLength`, `format` for dates / datetimes and `examples`.
+2. `--output` / `-o`: File to write the report into instead of stdout.
+3. `--from`, `--delimiter` / `-d`, the CSV input dialect flags, `--types` and `--xml-record` work like they do for `convert`, CSV values are always typed like `--parse-numbers` types them.
+
+### 4. `--log-file` / `-l` global flag
+
+Flag for specifying a file to write logs to instead of printing them to stderr, preferably a Markdown file.
+
+##### Usage
+
+```sh
+fiux validate <BROKEN_FILE> -l err.md
+```
+
+### 5. `--log-format` global flag
+
+Format of error logs: `text` (default for stderr), `markdown` (default for log files), `json` (an array of events) or `ndjson` (one event per line).
+
+Structured formats write one `error` event per error with the file, record index, line, column, byte offset, error kind and context chain when they're known, and a final `summary` event with the error / skipped record counts and the elapsed time.
+
+##### Usage
+
+```sh
+fiux --log-format ndjson validate <BROKEN_FILE> 2> errors.ndjson
+```
+
+---
+
+## Library usage
+
+fiux can also be embedded as a library, conversions go through the [`Decoder`] / [`Encoder`] traits which are implemented for every format in [`formats`], or through [`convert()`] which picks them from format names:
+
+```rust
+use fiux::{Options, convert};
+
+let input = "{\"name\": \"Alice\", \"address\": {\"city\": \"NYC\"}}\n";
+let mut output = Vec::new();
+
+convert(input.as_bytes(), "ndjson", &mut output, "csv", &Options::default())?;
+
+assert_eq!(String::from_utf8_lossy(&output), "name,address.city\nAlice,NYC\n");
+# Ok::<(), resext::ErrCtx<std::io::Error>>(())
+```
+
+---
+
+## Supported formats
+
+JSON, NDJSON, TOML, YAML (`.yaml` / `.yml`, multi-document streams are read as a sequence of values), XML (records are streamed one element at a time), MessagePack (`.msgpack` / `.mpk`, streams of concatenated values), CBOR (`.cbor`, CBOR sequences of concatenated items), Parquet (`.parquet`, read into memory as a whole since its metadata is at the end of the file, rows are streamed one batch at a time), Avro (`.avro` object container files with an embedded schema and `deflate` / `snappy` blocks, logical types are read as their JSON text forms), Arrow IPC files (`.arrow` / `.feather` for Feather v2, read into memory as a whole since their footer is at the end of the file) and streams (`.arrows`, read one record batch at a time, also from stdin) and CSV (or CSV-like formats through delimiter flags).
+
+Top-level JSON arrays are read one element at a time, so converting them runs in constant memory no matter how large the array is.
+
+Any of them can be compressed with gzip (`.gz`), zstd (`.zst`), bzip2 (`.bz2`) or xz (`.xz`).
+
+---
+
+## Examples
+
+```sh
+# Convert with input delimiter
+fiux convert input.psv --input-delimiter '|' -o output.csv
+
+# Convert with output delimiter
+fiux convert input.csv -o output.ssv --output-delimiter ';'
+
+# Convert with append and parse numbers in output
+fiux convert input.csv -o output.ndjson -a -p
+
+# Convert JSON into CSV, streaming with the first record's keys as headers
+fiux convert api_dump.json -o table.csv --late-keys drop
+
+# Convert in a pipeline through stdin and stdout
+curl -s https://example.com/data.json | fiux convert - --from json -o - --to csv | less
+
+# Convert a compressed archive into a compressed table
+fiux convert archive.ndjson.gz -o table.csv.zst
+
+# Convert while keeping invalid records in a side file
+fiux convert events.ndjson -o events.csv --reject-file rejects.tsv
+
+# Convert in a nightly job, failing on the first invalid record
+fiux convert events.ndjson -o events.csv --strict
+
+# Convert only a few columns of a wide table, renaming one of them
+fiux convert wide.csv -o narrow.csv --select id,email,created_at --rename created_at=created
+
+# Convert only the records that match an expression
+fiux convert users.ndjson -o adults.csv --where "age >= 18 and email =~ '@example\.com$'"
+
+# Convert with log file
+fiux convert broken.ndjson -o output.toml -l err.md
+
+# Convert the `<item>` elements of an XML feed into NDJSON
+fiux convert feed.xml -o items.ndjson --xml-record item
+
+# Convert a MessagePack stream into NDJSON with binary blobs as byte arrays
+fiux convert events.msgpack -o events.ndjson --binary array
+
+# Convert CBOR sequences from IoT devices, keeping their tags as `$tag` objects
+fiux convert readings.cbor -o readings.ndjson --cbor-tags preserve
+
+# Convert a typed CSV table into Parquet with zstd-compressed row groups of 100,000 rows
+fiux convert sales.csv -o sales.parquet -p --row-group-size 100000 --parquet-compression zstd
+
+# Write Avro with a given schema and snappy-compressed blocks
+fiux convert sales.csv -o sales.avro --avro-schema sale.avsc --avro-codec snappy
+
+# Hand a typed CSV table to a notebook as a zstd-compressed Feather file
+fiux convert sales.csv -o sales.feather -p --arrow-compression zstd
+
+# Convert a headerless export with single quotes, backslash escapes and `#` comments
+fiux convert export.csv -o export.ndjson --input-quote "'" --input-escape '\' --comment '#' --trim --no-header --header-names id,name,note
+
+# Profile a vendor export before converting it
+fiux schema vendor.csv
+fiux schema vendor.ndjson --format json-schema -o vendor.schema.json
+
+# Validate with log file
+fiux validate broken.json -l err.md
+
+# Validate with delimiter
+fiux validate input.psv -d '|'
+
+# Validate every record against a JSON Schema
+fiux validate vendor.ndjson --schema vendor.schema.json
+```
+*/
+
+mod utils;
+
+use std::{
+    io::{Error, Read, Write},
+    path::Path,
+};
+
+use resext::{CtxResult, ResExt};
+use utils::*;
+
+pub use utils::{
+    codecs::{Decoder, Encoder, check_format},
+    compression::{Codec, Compressor, compress, decompress, split_ext},
+    infer::ColumnType,
+    log::{
+        LogFormat, Summary, flush_logger, log_fatal, log_info, set_log_file, set_log_format,
+        set_log_input,
+    },
+    options::{
+        ArrowCompression, AvroCodec, Binary, CborTags, CsvDialect, LateKeys, Options,
+        ParquetCompression, QuoteStyle, Ragged, Terminator,
+    },
+    schema::SchemaFormat,
+    sniffer::{Detection, detect_format},
+    utilities::{DataTypes, WriterStreams},
+};
+
+/// Unit structs for every supported format, implementing [`Decoder`] and [`Encoder`].
+pub mod formats {
+    pub use crate::utils::codecs::{
+        Arrow, ArrowStream, Avro, Cbor, Csv, Json, Msgpack, Ndjson, Parquet, Toml, Xml, Yaml,
+    };
+}
+
+/// Converts `reader` from the `from` format into the `to` format and writes it into `writer`.
+///
+/// Formats are named after their extensions (e.g. `"json"`, `"csv"`, `"yml"`), `"auto"` detects
+/// the input format from its content (see [`detect_format()`]). Invalid records are logged and
+/// skipped while read / write failures return an error.
+///
+/// Compressed input is detected from its magic bytes and decompressed transparently unless
+/// [`Options::decompress`] is `false`, the output is compressed with [`Options::compress`] if it's
+/// set.
+///
+/// Returns the number of invalid records that were skipped, the conversion is aborted with an
+/// error once [`Options::max_errors`] records were skipped.
+pub fn convert<R: Read, W: Write>(
+    reader: R,
+    from: &str,
+    writer: W,
+    to: &str,
+    options: &Options,
+) -> CtxResult<usize, Error> {
+    let conv = Conversion::new(options.max_errors);
+
+    let reader: Box<dyn Read + '_> =
+        if options.decompress { decompress(reader)?.1 } else { Box::new(reader) };
+
+    match options.compress {
+        Some(codec) => {
+            let mut writer = compress(writer, codec, options.level)?;
+            convert_detected(reader, from, &mut writer, to, options, &conv)?;
+            writer.finish().context("Failed to finish compressed output")?;
+        }
+        None => convert_detected(reader, from, writer, to, options, &conv)?,
+    }
+
+    Ok(conv.skipped())
+}
+
+/// Converts `reader`, detecting its format first if `from` is `"auto"`.
+#[inline]
+fn convert_detected<R: Read, W: Write>(
+    reader: R,
+    from: &str,
+    writer: W,
+    to: &str,
+    options: &Options,
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    if from == "auto" {
+        let (detection, reader) = detect_format(reader, None)?;
+
+        let options = Options {
+            input_delimiter: detection.delimiter.unwrap_or(options.input_delimiter),
+            ..options.clone()
+        };
+
+        convert_from(reader, &detection.format, writer, to, &options, conv)
+    } else {
+        convert_from(reader, from, writer, to, options, conv)
+    }
+}
+
+#[inline]
+fn convert_from<R: Read, W: Write>(
+    reader: R,
+    from: &str,
+    writer: W,
+    to: &str,
+    options: &Options,
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    match from {
+        "json" => {
+            let data = Json.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "toml" => {
+            let data = Toml.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "csv" => {
+            let data = Csv.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "ndjson" => {
+            let data = Ndjson.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "yaml" | "yml" => {
+            let data = Yaml.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "xml" => {
+            let data = Xml.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "msgpack" | "mpk" => {
+            let data = Msgpack.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "cbor" => {
+            let data = Cbor.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "parquet" => {
+            let data = Parquet.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "avro" => {
+            let data = Avro.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "arrow" | "feather" => {
+            let data = Arrow.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        "arrows" => {
+            let data = ArrowStream.decode(reader, options).context("Deserialization failed")?;
+            encode_to(data, writer, to, options, conv)
+        }
+        _ => log_invalid_ext(from, false),
+    }
+}
+
+/// Profiles `reader` in the `from` format and writes a report of its fields into `writer`, with
+/// their types, nullability, min / max, distinct-count estimates and examples as a table or a
+/// JSON Schema document.
+///
+/// Formats are named like in [`convert()`], invalid records are logged and skipped and their
+/// number is returned.
+pub fn schema<R: Read, W: Write>(
+    reader: R,
+    from: &str,
+    writer: W,
+    format: SchemaFormat,
+    options: &Options,
+) -> CtxResult<usize, Error> {
+    let conv = Conversion::new(options.max_errors);
+
+    let reader: Box<dyn Read + '_> =
+        if options.decompress { decompress(reader)?.1 } else { Box::new(reader) };
+
+    if from == "auto" {
+        let (detection, reader) = detect_format(reader, None)?;
+
+        let options = Options {
+            input_delimiter: detection.delimiter.unwrap_or(options.input_delimiter),
+            ..options.clone()
+        };
+
+        schema_from(reader, &detection.format, writer, format, &options, &conv)?;
+    } else {
+        schema_from(reader, from, writer, format, options, &conv)?;
+    }
+
+    Ok(conv.skipped())
+}
+
+#[inline]
+fn schema_from<R: Read, W: Write>(
+    reader: R,
+    from: &str,
+    writer: W,
+    format: SchemaFormat,
+    options: &Options,
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let profile = match from {
+        "json" => {
+            profile(Json.decode(reader, options).context("Deserialization failed")?, options, conv)
+        }
+        "toml" => {
+            profile(Toml.decode(reader, options).context("Deserialization failed")?, options, conv)
+        }
+        "csv" => {
+            profile(Csv.decode(reader, options).context("Deserialization failed")?, options, conv)
+        }
+        "ndjson" => profile(
+            Ndjson.decode(reader, options).context("Deserialization failed")?,
+            options,
+            conv,
+        ),
+        "yaml" | "yml" => {
+            profile(Yaml.decode(reader, options).context("Deserialization failed")?, options, conv)
+        }
+        "xml" => {
+            profile(Xml.decode(reader, options).context("Deserialization failed")?, options, conv)
+        }
+        "msgpack" | "mpk" => profile(
+            Msgpack.decode(reader, options).context("Deserialization failed")?,
+            options,
+            conv,
+        ),
+        "cbor" => {
+            profile(Cbor.decode(reader, options).context("Deserialization failed")?, options, conv)
+        }
+        "parquet" => profile(
+            Parquet.decode(reader, options).context("Deserialization failed")?,
+            options,
+            conv,
+        ),
+        "avro" => {
+            profile(Avro.decode(reader, options).context("Deserialization failed")?, options, conv)
+        }
+        "arrow" | "feather" => {
+            profile(Arrow.decode(reader, options).context("Deserialization failed")?, options, conv)
+        }
+        "arrows" => profile(
+            ArrowStream.decode(reader, options).context("Deserialization failed")?,
+            options,
+            conv,
+        ),
+        _ => return log_invalid_ext(from, false),
+    }
+    .context("Failed to profile input")?;
+
+    write_schema(&profile, writer, format)
+}
+
+/// Validates the file at `path` as the `format` format, every invalid record is logged and an
+/// error is returned if there was at least one.
+///
+/// Records are also checked against the JSON Schema at [`Options::schema`] if it's set, every
+/// violation is logged with its record number and JSON pointer.
+pub fn validate(path: &Path, format: &str, options: &Options) -> CtxResult<(), Error> {
+    let schema = options.schema.as_deref().map(load_schema).transpose()?;
+
+    let syntax = match format {
+        "json" => json_validator::validate_json(path),
+        "toml" => toml_validator::validate_toml(path),
+        "csv" => csv_validator::validate_csv(path, options.input_delimiter, &options.input_dialect),
+        "ndjson" => ndjson_validator::validate_ndjson(path),
+        "yaml" | "yml" => yaml_validator::validate_yaml(path),
+        "xml" => xml_validator::validate_xml(path),
+        "msgpack" | "mpk" => msgpack_validator::validate_msgpack(path),
+        "cbor" => cbor_validator::validate_cbor(path),
+        "parquet" => parquet_validator::validate_parquet(path),
+        "avro" => avro_validator::validate_avro(path),
+        "arrow" | "feather" => arrow_validator::validate_arrow(path, false),
+        "arrows" => arrow_validator::validate_arrow(path, true),
+        _ => return log_invalid_ext(format, false),
+    };
+
+    match schema {
+        Some(schema) => {
+            let records = schema_validator::validate_schema(path, format, &schema, options);
+            syntax.and(records)
+        }
+        None => syntax,
+    }
+}
@@ -1,118 +1,33 @@
-/*!
-**fiux: The *fastest* multi-format file converter CLI tool**
+//! CLI for fiux, a thin wrapper around the library's [`fiux::convert`], [`fiux::validate`] and
+//! [`fiux::schema`].
 
-fiux provides intuitive file conversion similar to `mlr`'s `cat` verb but with a few advantages over it:
+mod cli;
 
-1. Auto format detection through extension
-2. Better error-handling with recoverable errors, logging options, comprehensive logs and a `validate` command for debugging
-3. 5× - 6× faster than `mlr`'s `cat` verb
-
----
-
-## Subcommands & flags
-
-### 1. Convert:
-
-##### Description
-
-- Convert from input file to output file
-- If any item in the input file is invalid, the item is skipped and fiux logs an error message
-- Only panics on read / write failure
-##### Usage
-
-Basic usage:
-
-```sh
-fiux convert <INPUT> -o <OUTPUT>
-```
-
-##### Arguments
-
-1. Input: Input file to be converted, will panic if it doesn't exist or if its extension is not supported.
-2. Output: `--output` / `-o` flag, file to write output to, will panic only if its extension is not supported, will create the file if it doesn't exist.
-
-##### Flags (options)
-
-1. `--append` / `-a`: fiux overwrites existing data in the output file by default, this flag makes it append to it instead. **WARNING:** This flag can lead to corrupted output with some formats like JSON.
-2. `--parse-numbers` / `-p`: Flag to make fiux parse numbers in output when converted from CSV.
-3. `--input-delimiter` / `--output-delimiter`: Flags that make fiux ignore file extension and treat them as CSV with the specified delimiter
-
----
-
-### 2. Validate
-
-##### Description
-
-- Test if input file is valid
-- Logs a detailed error message if an item is invalid then continues to validate the rest of the items
-- Only panics on read failure
-##### Usage
-
-Basic usage:
-
-```sh
-fiux validate <INPUT>
-```
-
-##### Arguments
-
-Input: File to be validated, will panic if it doesn't exist
-
-##### Flags (options)
-
-`--delimiter` / `-d`: Flag that makes fiux ignore file extension and treat the file as a CSV with the specified delimiter
-
-### 3. `--log-file` / `-l` global flag
-
-Flag for specifying a file to write logs to instead of printing them to stderr, preferably a Markdown file.
-
-##### Usage
-
-```sh
-fiux validate <BROKEN_FILE> -l err.md
-```
-
----
-
-## Examples
-
-```sh
-# Convert with input delimiter
-fiux convert input.psv --input-delimiter '|' -o output.csv
-
-# Convert with output delimiter
-fiux convert input.csv -o output.ssv --output-delimiter ';'
-
-# Convert with append and parse numbers in output
-fiux convert input.csv -o output.ndjson -a -p
-
-# Convert with log file
-fiux convert broken.ndjson -o output.toml -l err.md
-
-# Validate with log file
-fiux validate broken.json -l err.md
-
-# Validate with delimiter
-fiux validate input.psv -d '|'
-```
-*/
-
-mod utils;
 use clap::Parser;
+use cli::*;
+use fiux::{
+    Options, Summary, check_format, decompress, detect_format, flush_logger, log_fatal, log_info,
+    set_log_file, set_log_format, set_log_input, split_ext,
+};
 use owo_colors::OwoColorize;
 use resext::*;
-use std::fs::OpenOptions;
-use std::io::{Error, ErrorKind as EK};
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind as EK, Read, Write};
 use std::path::Path;
 use std::process::exit;
-use std::sync::LazyLock;
-use utils::*;
-
-pub(crate) static ARGS: LazyLock<Args> = LazyLock::new(Args::parse);
 
 #[inline]
 fn run() -> CtxResult<(), Error> {
-    let args = &*ARGS;
+    let args = Args::parse();
+
+    if let Some(path) = &args.log_file {
+        set_log_file(path)?;
+    }
+
+    if let Some(format) = args.log_format {
+        set_log_format(format)?;
+    }
 
     match &args.cmd {
         Commands::Convert {
@@ -120,187 +35,382 @@ fn run() -> CtxResult<(), Error> {
             output,
             append,
             parse_numbers,
+            types,
             input_delimiter,
             output_delimiter,
+            input_dialect,
+            output_dialect,
+            late_keys,
+            from,
+            to,
+            compress,
+            level,
+            reject_file,
+            max_errors,
+            strict,
+            xml_record,
+            xml_root,
+            xml_row,
+            binary,
+            cbor_tags,
+            row_group_size,
+            parquet_compression,
+            batch_size,
+            arrow_compression,
+            avro_schema,
+            avro_infer,
+            avro_codec,
+            filter,
+            select,
+            exclude,
+            rename,
         } => {
-            // Check if input exists
-            throw_err_if!(
-                !Path::new(&input).exists(),
-                || format!(
-                    "{} {} {} {}",
-                    "FATAL:".red().bold(),
-                    "Input file:",
-                    input.to_str().unwrap_or("input_file").on_bright_red(),
-                    "doesn't exist"
-                ),
-                1
-            );
-
-            let output_file = OpenOptions::new()
-                .create(true)
-                .write(true)
-                .append(*append)
-                .open(output)
-                .context("Failed to open output file")?;
-
-            let o_d: char;
+            let is_stdout = output.as_os_str() == "-";
 
             let now = std::time::Instant::now();
 
-            let output_ext;
-            if let Some(ch) = output_delimiter {
-                output_ext = std::borrow::Cow::Borrowed("csv");
-                o_d = *ch;
+            let (input_ext, input_delimiter, input_stream) =
+                open_input(input, from, *input_delimiter)?;
+
+            let output_ext = if output_delimiter.is_some() {
+                Cow::Borrowed("csv")
             } else {
-                output_ext = output
-                    .extension()
-                    .ok_or_else(|| Error::new(EK::InvalidFilename, "Output file has no extension"))
-                    .context("Failed to get output file's extension")?
-                    .to_string_lossy();
-                o_d = ',';
-            }
+                format_of(output, to.as_deref(), true)?
+            };
 
-            if let Some(ch) = input_delimiter {
-                let data = csv_decoder::csv_decoder(csv_reader::csv_reader(input, *ch))
-                    .context("Deserialization failed")?;
+            let compress = if is_stdout { *compress } else { compress.or(split_ext(output).1) };
+
+            if level.is_some() && compress.is_none() {
+                return Err(Error::new(
+                    EK::InvalidInput,
+                    "`--level` requires `--compress <CODEC>` or a compressed output extension",
+                ))
+                .context("Failed to set the compression level");
+            }
 
-                match_output(data, output_file, &output_ext, *parse_numbers, o_d)?;
+            // unsupported formats are rejected before the output is opened and truncated
+            check_format(&input_ext, false)?;
+            check_format(&output_ext, true)?;
+
+            // strict conversions don't leave partial output behind, files are written into a
+            // temporary file that replaces the output once the conversion succeeds while stdout
+            // and appended output are held in memory until then
+            let tmp = output.with_file_name(format!(
+                ".{}.fiux-tmp",
+                output.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            let mut held: Vec<u8> = Vec::new();
+
+            let output_stream: Box<dyn Write> = if *strict && (is_stdout || *append) {
+                Box::new(&mut held)
+            } else if is_stdout {
+                Box::new(std::io::stdout())
             } else {
-                let input_ext: &str = &input
-                    .extension()
-                    .ok_or_else(|| Error::new(EK::InvalidFilename, "Input file has no extension"))
-                    .context("Failed to get input file's extension")?
-                    .to_string_lossy();
-
-                match input_ext {
-                    "json" => {
-                        let data = json_decoder::json_decoder(json_reader::json_reader(input))
-                            .context("Deserialization failed")?;
-
-                        match_output(data, output_file, &output_ext, *parse_numbers, o_d)?;
-                    }
-                    "toml" => {
-                        let data = toml_decoder::toml_decoder(toml_reader::toml_reader(input))
-                            .context("Deserialization failed")?;
-                        match_output(data, output_file, &output_ext, *parse_numbers, o_d)?;
-                    }
-                    "csv" => {
-                        let data = csv_decoder::csv_decoder(csv_reader::csv_reader(input, ','))
-                            .context("Deserialization failed")?;
-                        match_output(data, output_file, &output_ext, *parse_numbers, o_d)?;
-                    }
-                    "ndjson" => {
-                        let data =
-                            ndjson_decoder::ndjson_decoder(ndjson_reader::ndjson_reader(input))
-                                .context("Deserialization failed")?;
-                        match_output(data, output_file, &output_ext, *parse_numbers, o_d)?;
+                Box::new(
+                    OpenOptions::new()
+                        .create(true)
+                        .write(true)
+                        .append(*append)
+                        .truncate(!*append)
+                        .open(if *strict { &tmp } else { output })
+                        .context("Failed to open output file")?,
+                )
+            };
+
+            let options = Options {
+                parse_numbers: *parse_numbers,
+                types: types.clone(),
+                input_delimiter: input_delimiter.unwrap_or(','),
+                output_delimiter: output_delimiter.unwrap_or(','),
+                input_dialect: input_dialect.dialect(),
+                output_dialect: output_dialect.dialect(),
+                late_keys: *late_keys,
+                // `open_input` already decompressed the input to detect its format
+                decompress: false,
+                compress,
+                level: *level,
+                reject_file: reject_file.clone(),
+                max_errors: if *strict { Some(1) } else { max_errors.map(|n| n as usize) },
+                xml_record: xml_record.clone(),
+                xml_root: xml_root.clone(),
+                xml_row: xml_row.clone(),
+                binary: *binary,
+                cbor_tags: *cbor_tags,
+                row_group_size: *row_group_size as usize,
+                parquet_compression: *parquet_compression,
+                batch_size: *batch_size as usize,
+                arrow_compression: *arrow_compression,
+                avro_schema: avro_schema.clone(),
+                avro_infer: *avro_infer as usize,
+                avro_codec: *avro_codec,
+                filter: filter.clone(),
+                select: select.clone(),
+                exclude: exclude.clone(),
+                rename: rename.clone(),
+                schema: None,
+            };
+
+            let skipped =
+                match fiux::convert(input_stream, &input_ext, output_stream, &output_ext, &options)
+                {
+                    Ok(skipped) => skipped,
+                    Err(e) => {
+                        if *strict && !is_stdout && !*append {
+                            let _ = std::fs::remove_file(&tmp);
+                        }
+                        return Err(e);
                     }
-                    _ => log_invalid_ext(input_ext, false)?,
                 };
+
+            if *strict {
+                if is_stdout {
+                    std::io::stdout()
+                        .write_all(&held)
+                        .context("Failed to write output into stdout")?;
+                } else if *append {
+                    OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(output)
+                        .context("Failed to open output file")?
+                        .write_all(&held)
+                        .context("Failed to append output into output file")?;
+                } else {
+                    std::fs::rename(&tmp, output)
+                        .context("Failed to replace output file with converted output")?;
+                }
             }
 
-            flush_logger(&format!("Finished in: {:?}", now.elapsed().bright_green()))?;
+            let elapsed = now.elapsed();
+            let summary = Summary { success: true, skipped, elapsed };
+
+            if skipped > 0 {
+                flush_logger(
+                    &format!(
+                        "Finished in: {:?} ({} invalid record(s) skipped)",
+                        elapsed.bright_green(),
+                        skipped.yellow()
+                    ),
+                    &summary,
+                )?;
+                exit(2);
+            }
+
+            flush_logger(&format!("Finished in: {:?}", elapsed.bright_green()), &summary)?;
 
             Ok(())
         }
 
-        Commands::Validate { input, delimiter } => {
-            throw_err_if!(
-                !Path::new(&input).exists(),
-                || format!(
-                    "{} {} {} {}",
-                    "FATAL:".red().bold(),
-                    "Input file:",
-                    input.to_str().unwrap_or("input_file").on_bright_red(),
-                    "doesn't exist"
-                ),
-                1
-            );
-
-            let i_d: char;
-
-            let temp_ext;
-            if let Some(ch) = delimiter {
-                temp_ext = std::borrow::Cow::Borrowed("csv");
-                i_d = *ch;
-            } else {
-                temp_ext = input
-                    .extension()
-                    .ok_or_else(|| Error::new(EK::InvalidFilename, "Output file has no extension"))
-                    .context("Failed to get output file's extension")?
-                    .to_string_lossy();
-                i_d = ',';
-            }
+        Commands::Validate { input, delimiter, input_dialect, schema } => {
+            let mut delimiter = *delimiter;
+            let now = std::time::Instant::now();
+
+            set_log_input(&input.to_string_lossy());
+
+            check_input(input)?;
 
-            let input_ext: &str = &temp_ext;
+            let input_ext = if delimiter.is_some() {
+                Cow::Borrowed("csv")
+            } else {
+                let file = File::open(input).with_context(|| {
+                    format!("Failed to open input file: {}", input.to_string_lossy())
+                })?;
+                let (_, file) = decompress(file)?;
+                let (detection, _) = detect_format(file, split_ext(input).0.as_deref())?;
+
+                log_info(&format!(
+                    "Detected input format: {} ({})",
+                    detection.format.bright_green(),
+                    detection.reason
+                ))?;
+
+                delimiter = delimiter.or(detection.delimiter);
+                Cow::Owned(detection.format)
+            };
 
-            let res = match input_ext {
-                "json" => json_validator::validate_json(input),
-                "toml" => toml_validator::validate_toml(input),
-                "csv" => csv_validator::validate_csv(input, i_d),
-                "ndjson" => ndjson_validator::validate_ndjson(input),
-                _ => log_invalid_ext(input_ext, false),
+            let options = Options {
+                input_delimiter: delimiter.unwrap_or(','),
+                input_dialect: input_dialect.dialect(),
+                schema: schema.clone(),
+                ..Options::default()
             };
 
-            match res {
+            match fiux::validate(input, &input_ext, &options) {
                 Ok(_) => {
                     let msg = format!("Input file: {} is valid", input.display().bright_green());
-                    flush_logger(&msg)?;
+                    flush_logger(
+                        &msg,
+                        &Summary { success: true, skipped: 0, elapsed: now.elapsed() },
+                    )?;
                     Ok(())
                 }
                 Err(e) => {
-                    flush_logger(&e.red().bold().to_string())?;
+                    flush_logger(
+                        &e.red().bold().to_string(),
+                        &Summary { success: false, skipped: 0, elapsed: now.elapsed() },
+                    )?;
                     exit(1);
                 }
             }
         }
+
+        Commands::Schema {
+            input,
+            output,
+            from,
+            format,
+            delimiter,
+            input_dialect,
+            types,
+            xml_record,
+        } => {
+            let now = std::time::Instant::now();
+
+            let (input_ext, delimiter, input_stream) = open_input(input, from, *delimiter)?;
+
+            let output_stream: Box<dyn Write> = match output {
+                Some(path) if path.as_os_str() != "-" => {
+                    Box::new(File::create(path).context("Failed to open output file")?)
+                }
+                _ => Box::new(std::io::stdout()),
+            };
+
+            let options = Options {
+                types: types.clone(),
+                input_delimiter: delimiter.unwrap_or(','),
+                input_dialect: input_dialect.dialect(),
+                xml_record: xml_record.clone(),
+                decompress: false,
+                ..Options::default()
+            };
+
+            let skipped = fiux::schema(input_stream, &input_ext, output_stream, *format, &options)?;
+
+            let summary = Summary { success: true, skipped, elapsed: now.elapsed() };
+
+            if skipped > 0 {
+                flush_logger(
+                    &format!(
+                        "Profiled in: {:?} ({} invalid record(s) skipped)",
+                        summary.elapsed.bright_green(),
+                        skipped.yellow()
+                    ),
+                    &summary,
+                )?;
+                exit(2);
+            }
+
+            flush_logger(&format!("Profiled in: {:?}", summary.elapsed.bright_green()), &summary)?;
+
+            Ok(())
+        }
+    }
+}
+
+/// Format, delimiter and stream of an opened input.
+type Input<'a> = (Cow<'a, str>, Option<char>, Box<dyn Read>);
+
+/// Opens the input of `convert` / `schema` (stdin for `-`), decompressing it and detecting its
+/// format unless it's set with `--from` or a delimiter. Returns the format and the delimiter
+/// along with the stream.
+fn open_input<'a>(
+    input: &Path,
+    from: &'a str,
+    delimiter: Option<char>,
+) -> CtxResult<Input<'a>, Error> {
+    let is_stdin = input.as_os_str() == "-";
+
+    set_log_input(&if is_stdin { Cow::Borrowed("<stdin>") } else { input.to_string_lossy() });
+
+    if !is_stdin {
+        check_input(input)?;
+    }
+
+    let input_stream: Box<dyn Read> =
+        if is_stdin {
+            Box::new(std::io::stdin())
+        } else {
+            Box::new(File::open(input).with_context(|| {
+                format!("Failed to open input file: {}", input.to_string_lossy())
+            })?)
+        };
+
+    let (codec, input_stream) = decompress(input_stream)?;
+
+    if let Some(codec) = codec {
+        log_info(&format!("Detected input compression: {}", codec.name().bright_green()))?;
+    }
+
+    if delimiter.is_some() {
+        return Ok((Cow::Borrowed("csv"), delimiter, input_stream));
+    } else if from != "auto" {
+        return Ok((Cow::Borrowed(from), delimiter, input_stream));
     }
+
+    let ext = if is_stdin { None } else { split_ext(input).0 };
+    let (detection, stream) = detect_format(input_stream, ext.as_deref())?;
+
+    log_info(&format!(
+        "Detected input format: {} ({})",
+        detection.format.bright_green(),
+        detection.reason
+    ))?;
+
+    Ok((Cow::Owned(detection.format), delimiter.or(detection.delimiter), Box::new(stream)))
+}
+
+/// Fails if the input file doesn't exist, the error goes through `log_fatal` like every other
+/// fatal error so it's also logged in the JSON log formats.
+fn check_input(input: &Path) -> CtxResult<(), Error> {
+    if input.exists() {
+        return Ok(());
+    }
+
+    Err(Error::new(EK::NotFound, format!("Input file: {} doesn't exist", input.to_string_lossy())))
+        .context("Failed to open input file")
 }
 
 fn main() {
+    let now = std::time::Instant::now();
+
     match run() {
         Ok(_) => {}
         Err(e) => {
-            eprintln!("{} {}", "FATAL:".red().bold(), e);
+            log_fatal(&e, now.elapsed())
+                .unwrap_or_else(|err| eprintln!("{} {}\n{}", "FATAL:".red().bold(), e, err));
             exit(1);
         }
     }
 }
 
+/// Gets the format of an input / output from the `--from` / `--to` flags, falling back to its
+/// extension, `-` (stdin / stdout) has no extension so it requires the flag.
+///
+/// Compression extensions are skipped so `out.ndjson.gz` is NDJSON.
 #[inline]
-fn match_output(
-    data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
-    output_file: std::fs::File,
-    output_ext: &str,
-    parse_numbers: bool,
-    o_d: char,
-) -> CtxResult<(), Error> {
-    match output_ext {
-        "json" => write_json::write_json(data, output_file, parse_numbers)
-            .context("Serialization failed")?,
-        "toml" => toml_writer::toml_writer(data, output_file, parse_numbers)
-            .context("Serialization failed")?,
-        "csv" => csv_writer::csv_writer(data, output_file, o_d).context("Serialization failed")?,
-        "ndjson" => ndjson_writer::ndjson_writer(data, output_file, parse_numbers)
-            .context("Serialization failed")?,
-        _ => log_invalid_ext(output_ext, true)?,
-    };
-
-    Ok(())
-}
+fn format_of<'a>(
+    path: &'a Path,
+    explicit: Option<&'a str>,
+    is_output: bool,
+) -> CtxResult<Cow<'a, str>, Error> {
+    let (s, flag) = if is_output { ("Output", "--to") } else { ("Input", "--from") };
+
+    if let Some(fmt) = explicit {
+        return Ok(Cow::Borrowed(fmt));
+    }
 
-#[inline]
-fn log_invalid_ext(input_ext: &str, is_output: bool) -> CtxResult<(), Error> {
-    let s = if is_output { "Out" } else { "In" };
-    let repo_link = "https://github.com/Tahaa-Dev/fiux";
-
-    Err(Error::new(
-        EK::InvalidFilename,
-        format!(
-            "{}put extension: [{}] is not supported currently\nOpen an issue at: {}",
-            s,
-            input_ext.red().bold(),
-            repo_link.bright_blue().italic(),
-        ),
-    ))?
+    if path.as_os_str() == "-" {
+        return Err(Error::new(
+            EK::InvalidInput,
+            format!("{} format can't be detected for `-`, use `{} <FORMAT>`", s, flag),
+        ))
+        .with_context(|| format!("Failed to get {} format", s.to_lowercase()));
+    }
+
+    Ok(Cow::Owned(
+        split_ext(path)
+            .0
+            .ok_or_else(|| Error::new(EK::InvalidFilename, format!("{} file has no extension", s)))
+            .with_context(|| format!("Failed to get {} file's extension", s.to_lowercase()))?,
+    ))
 }
@@ -0,0 +1,597 @@
+use std::io::{Error, Read, Write};
+
+use owo_colors::OwoColorize;
+use resext::{CtxResult, ResExt};
+
+use crate::utils::*;
+
+/// Trait for formats that can be read into fiux's record stream.
+pub trait Decoder {
+    /// Decodes `reader` into a stream of records, every item is either a decoded record or a
+    /// recoverable error for a single invalid record.
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>;
+}
+
+/// Trait for formats that fiux's record stream can be written into.
+pub trait Encoder {
+    /// Encodes every record in `data` into `writer`, invalid records are logged and don't stop
+    /// the conversion until [`Options::max_errors`] of them were skipped.
+    ///
+    /// Unlike [`crate::convert()`], `data` is written as it is: records that failed to decode
+    /// reach the encoder as errors, nothing is written into [`Options::reject_file`] and
+    /// [`Options::filter`], [`Options::select`], [`Options::exclude`] and [`Options::rename`]
+    /// aren't applied.
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error>;
+}
+
+/// Encoders that share the error budget and reject file of the conversion they're part of,
+/// [`Encoder::encode`] runs them in a conversion of their own.
+pub(crate) trait EncodeWith {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error>;
+}
+
+/// JSON format, a stream of whitespace separated JSON values.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+/// NDJSON format, one JSON value per line.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ndjson;
+
+/// TOML format, documents are read into memory as a whole.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Toml;
+
+/// CSV format, or CSV-like formats through `Options::input_delimiter` / `Options::output_delimiter`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Csv;
+
+/// YAML format, multi-document streams are read as a sequence of values.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Yaml;
+
+/// XML format, records are the elements selected with `Options::xml_record`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Xml;
+
+/// MessagePack format, a stream of concatenated MessagePack values.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Msgpack;
+
+/// CBOR format, a CBOR sequence of concatenated data items.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cbor;
+
+/// Parquet format, files are read into memory as a whole and written one row group at a time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Parquet;
+
+/// Avro format, object container files with their schema embedded in them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Avro;
+
+/// Arrow IPC file format (Feather v2), files are read into memory as a whole and written one
+/// record batch at a time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Arrow;
+
+/// Arrow IPC stream format, record batches are read and written one at a time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ArrowStream;
+
+impl Decoder for Json {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        _options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        json_decoder::json_decoder(json_reader::json_reader(reader)?)
+    }
+}
+
+impl Decoder for Ndjson {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        _options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        ndjson_decoder::ndjson_decoder(ndjson_reader::ndjson_reader(reader))
+    }
+}
+
+impl Decoder for Toml {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        _options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        toml_decoder::toml_decoder(toml_reader::toml_reader(reader)?)
+    }
+}
+
+impl Decoder for Csv {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        csv_decoder::csv_decoder(
+            csv_reader::csv_reader(
+                reader,
+                options.input_delimiter,
+                &options.input_dialect,
+                options.reject_file.is_some(),
+            )?,
+            options.input_delimiter as u8,
+            options.input_dialect.clone(),
+        )
+    }
+}
+
+impl Decoder for Yaml {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        _options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        yaml_decoder::yaml_decoder(yaml_reader::yaml_reader(reader))
+    }
+}
+
+impl Decoder for Xml {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        xml_decoder::xml_decoder(xml_reader::xml_reader(reader), options.xml_record.clone())
+    }
+}
+
+impl Decoder for Msgpack {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        msgpack_decoder::msgpack_decoder(msgpack_reader::msgpack_reader(reader), options.binary)
+    }
+}
+
+impl Decoder for Cbor {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        cbor_decoder::cbor_decoder(
+            cbor_reader::cbor_reader(reader),
+            options.binary,
+            options.cbor_tags,
+        )
+    }
+}
+
+impl Decoder for Parquet {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        _options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        let reader =
+            parquet_reader::parquet_reader(reader).context("Failed to read Parquet file")?;
+
+        parquet_decoder::parquet_decoder(reader)
+    }
+}
+
+impl Decoder for Avro {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        let reader = avro_reader::avro_reader(reader).context("Failed to read Avro file header")?;
+
+        avro_decoder::avro_decoder(reader, options.binary)
+    }
+}
+
+impl Decoder for Arrow {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        _options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        let reader =
+            arrow_reader::arrow_file_reader(reader).context("Failed to read Arrow IPC file")?;
+
+        arrow_decoder::arrow_decoder(reader)
+    }
+}
+
+impl Decoder for ArrowStream {
+    fn decode<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        _options: &Options,
+    ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+    {
+        let reader = arrow_reader::arrow_stream_reader(reader)
+            .context("Failed to read Arrow IPC stream schema")?;
+
+        arrow_decoder::arrow_decoder(reader)
+    }
+}
+
+impl EncodeWith for Json {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        write_json::write_json(data, writer, options.parse_numbers, &options.types, conv)
+    }
+}
+
+impl EncodeWith for Ndjson {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        ndjson_writer::ndjson_writer(data, writer, options.parse_numbers, &options.types, conv)
+    }
+}
+
+impl EncodeWith for Toml {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        toml_writer::toml_writer(data, writer, options.parse_numbers, &options.types, conv)
+    }
+}
+
+impl EncodeWith for Csv {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        csv_writer::csv_writer(
+            data,
+            writer,
+            options.output_delimiter,
+            &options.output_dialect,
+            options.late_keys,
+            conv,
+        )
+    }
+}
+
+impl EncodeWith for Yaml {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        yaml_writer::yaml_writer(data, writer, options.parse_numbers, &options.types, conv)
+    }
+}
+
+impl EncodeWith for Xml {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        xml_writer::xml_writer(data, writer, &options.xml_root, &options.xml_row, conv)
+    }
+}
+
+impl EncodeWith for Msgpack {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        msgpack_writer::msgpack_writer(data, writer, options.parse_numbers, &options.types, conv)
+    }
+}
+
+impl EncodeWith for Cbor {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        cbor_writer::cbor_writer(data, writer, options.parse_numbers, &options.types, conv)
+    }
+}
+
+impl EncodeWith for Parquet {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        parquet_writer::parquet_writer(data, writer, options, &options.types, conv)
+    }
+}
+
+impl EncodeWith for Avro {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        avro_writer::avro_writer(data, writer, options, &options.types, conv)
+    }
+}
+
+impl EncodeWith for Arrow {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        arrow_writer::arrow_writer(data, writer, options, &options.types, false, conv)
+    }
+}
+
+impl EncodeWith for ArrowStream {
+    fn encode_with<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+        conv: &Conversion,
+    ) -> CtxResult<(), Error> {
+        arrow_writer::arrow_writer(data, writer, options, &options.types, true, conv)
+    }
+}
+
+impl Encoder for Json {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Ndjson {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Toml {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Csv {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Yaml {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Xml {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Msgpack {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Cbor {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Parquet {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Avro {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for Arrow {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+impl Encoder for ArrowStream {
+    fn encode<W: Write>(
+        &self,
+        data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+        writer: W,
+        options: &Options,
+    ) -> CtxResult<(), Error> {
+        self.encode_with(data, writer, options, &Conversion::new(options.max_errors))
+    }
+}
+
+/// Encodes `data` with the encoder for the `to` format, after quarantining its invalid records
+/// and applying the filter and projection.
+#[inline]
+pub(crate) fn encode_to<W: Write>(
+    data: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+    writer: W,
+    to: &str,
+    options: &Options,
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let data = quarantine(data, options, conv)?;
+    let data = filter(data, options)?;
+    let data = project(data, options)?;
+
+    let res = match to {
+        "json" => Json.encode_with(data, writer, options, conv),
+        "toml" => Toml.encode_with(data, writer, options, conv),
+        "csv" => Csv.encode_with(data, writer, options, conv),
+        "ndjson" => Ndjson.encode_with(data, writer, options, conv),
+        "yaml" | "yml" => Yaml.encode_with(data, writer, options, conv),
+        "xml" => Xml.encode_with(data, writer, options, conv),
+        "msgpack" | "mpk" => Msgpack.encode_with(data, writer, options, conv),
+        "cbor" => Cbor.encode_with(data, writer, options, conv),
+        "parquet" => Parquet.encode_with(data, writer, options, conv),
+        "avro" => Avro.encode_with(data, writer, options, conv),
+        "arrow" | "feather" => Arrow.encode_with(data, writer, options, conv),
+        "arrows" => ArrowStream.encode_with(data, writer, options, conv),
+        _ => log_invalid_ext(to, true),
+    };
+
+    conv.close_rejects();
+    res.context("Serialization failed")
+}
+
+/// Names of the supported formats, every format can be read and written.
+pub(crate) const FORMATS: &[&str] = &[
+    "json", "ndjson", "toml", "csv", "yaml", "yml", "xml", "msgpack", "mpk", "cbor", "parquet",
+    "avro", "arrow", "feather", "arrows",
+];
+
+/// Checks that `format` is supported before any input is read or output is written, returning the
+/// same error as an unsupported format in [`crate::convert()`].
+#[inline]
+pub fn check_format(format: &str, is_output: bool) -> CtxResult<(), Error> {
+    if FORMATS.contains(&format) { Ok(()) } else { log_invalid_ext(format, is_output) }
+}
+
+#[inline]
+pub(crate) fn log_invalid_ext(ext: &str, is_output: bool) -> CtxResult<(), Error> {
+    let s = if is_output { "Out" } else { "In" };
+    let repo_link = "https://github.com/Tahaa-Dev/fiux";
+
+    Err(Error::new(
+        std::io::ErrorKind::InvalidFilename,
+        format!(
+            "{}put extension: [{}] is not supported currently\nOpen an issue at: {}",
+            s,
+            ext.red().bold(),
+            repo_link.bright_blue().italic(),
+        ),
+    ))?
+}
@@ -0,0 +1,261 @@
+use std::{
+    fs::File,
+    io::{Cursor, Error, ErrorKind as EK, Read, Write},
+    path::Path,
+};
+
+use clap::ValueEnum;
+use resext::{CtxResult, ResExt};
+
+/// Compression codecs for inputs and outputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Codec {
+    /// gzip (`.gz`)
+    #[value(alias = "gz")]
+    Gzip,
+
+    /// Zstandard (`.zst`)
+    #[value(alias = "zst")]
+    Zstd,
+
+    /// bzip2 (`.bz2`)
+    #[value(alias = "bz2")]
+    Bzip2,
+
+    /// xz / LZMA2 (`.xz`)
+    Xz,
+}
+
+impl Codec {
+    /// Gets the codec for a compression extension (e.g. `gz` in `data.ndjson.gz`).
+    pub fn from_ext(ext: &str) -> Option<Codec> {
+        match ext {
+            "gz" | "gzip" => Some(Codec::Gzip),
+            "zst" | "zstd" => Some(Codec::Zstd),
+            "bz2" => Some(Codec::Bzip2),
+            "xz" => Some(Codec::Xz),
+            _ => None,
+        }
+    }
+
+    /// Gets the codec from the magic bytes at the start of a compressed stream.
+    pub fn from_magic(bytes: &[u8]) -> Option<Codec> {
+        if bytes.starts_with(&[0x1F, 0x8B]) {
+            Some(Codec::Gzip)
+        } else if bytes.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            Some(Codec::Zstd)
+        } else if bytes.starts_with(b"BZh") {
+            Some(Codec::Bzip2)
+        } else if bytes.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Codec::Xz)
+        } else {
+            None
+        }
+    }
+
+    /// Name of the codec for messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+            Codec::Bzip2 => "bzip2",
+            Codec::Xz => "xz",
+        }
+    }
+}
+
+/// Splits a path's extensions into its format extension and compression codec, so
+/// `data.ndjson.gz` gives `(Some("ndjson"), Some(Codec::Gzip))` and `data.csv` gives
+/// `(Some("csv"), None)`.
+pub fn split_ext(path: &Path) -> (Option<String>, Option<Codec>) {
+    let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+
+    match ext.as_deref().and_then(Codec::from_ext) {
+        Some(codec) => {
+            let inner = path
+                .file_stem()
+                .map(Path::new)
+                .and_then(Path::extension)
+                .map(|e| e.to_string_lossy().to_lowercase());
+
+            (inner, Some(codec))
+        }
+        None => (ext, None),
+    }
+}
+
+/// Detects compressed input from its magic bytes and wraps it with the right decompressor, input
+/// that isn't compressed is passed through as-is.
+pub fn decompress<'a, R: Read + 'a>(
+    mut reader: R,
+) -> CtxResult<(Option<Codec>, Box<dyn Read + 'a>), Error> {
+    let mut magic = [0u8; 6];
+    let mut len = 0;
+
+    while len < magic.len() {
+        match reader.read(&mut magic[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == EK::Interrupted => continue,
+            Err(e) => return Err(e).context("Failed to read input for compression detection"),
+        }
+    }
+
+    let codec = Codec::from_magic(&magic[..len]);
+    let reader = Cursor::new(magic[..len].to_vec()).chain(reader);
+
+    let decompressed: Box<dyn Read + 'a> = match codec {
+        None => Box::new(reader),
+        Some(Codec::Gzip) => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        Some(Codec::Zstd) => Box::new(
+            zstd::stream::read::Decoder::new(reader)
+                .context("Failed to initialize zstd decompressor")?,
+        ),
+        Some(Codec::Bzip2) => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+        Some(Codec::Xz) => Box::new(liblzma::read::XzDecoder::new_multi_decoder(reader)),
+    };
+
+    Ok((codec, decompressed))
+}
+
+/// Opens the file at `path`, decompressing it if it's compressed.
+#[inline]
+pub(crate) fn open_decompressed(path: &Path) -> CtxResult<Box<dyn Read>, Error> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open input file: {}", path.to_string_lossy()))?;
+
+    decompress(file).map(|(_, reader)| reader)
+}
+
+/// Compressing writer returned by [`compress()`], [`Compressor::finish()`] writes the end of the
+/// compressed stream and returns errors that dropping it would swallow.
+pub struct Compressor<W: Write>(Encoder<W>);
+
+enum Encoder<W: Write> {
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    Bzip2(bzip2::write::BzEncoder<W>),
+    Xz(liblzma::write::XzEncoder<W>),
+}
+
+impl<W: Write> Compressor<W> {
+    /// Finishes the compressed stream and flushes the inner writer.
+    pub fn finish(self) -> Result<W, Error> {
+        let mut writer = match self.0 {
+            Encoder::Gzip(e) => e.finish()?,
+            Encoder::Zstd(e) => e.finish()?,
+            Encoder::Bzip2(e) => e.finish()?,
+            Encoder::Xz(e) => e.finish()?,
+        };
+
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for Compressor<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        match &mut self.0 {
+            Encoder::Gzip(e) => e.write(buf),
+            Encoder::Zstd(e) => e.write(buf),
+            Encoder::Bzip2(e) => e.write(buf),
+            Encoder::Xz(e) => e.write(buf),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<(), Error> {
+        match &mut self.0 {
+            Encoder::Gzip(e) => e.flush(),
+            Encoder::Zstd(e) => e.flush(),
+            Encoder::Bzip2(e) => e.flush(),
+            Encoder::Xz(e) => e.flush(),
+        }
+    }
+}
+
+/// Wraps `writer` with a compressor for `codec`, `level` defaults to the codec's default level.
+///
+/// The compressed stream has to be finished with [`Compressor::finish()`].
+pub fn compress<W: Write>(
+    writer: W,
+    codec: Codec,
+    level: Option<i32>,
+) -> CtxResult<Compressor<W>, Error> {
+    let range = match codec {
+        Codec::Zstd => zstd::compression_level_range(),
+        Codec::Bzip2 => 1..=9,
+        _ => 0..=9,
+    };
+
+    if let Some(level) = level
+        && !range.contains(&level)
+    {
+        return Err(Error::new(EK::InvalidInput, "Invalid compression level")).with_context(|| {
+            format!(
+                "Compression level: {} is out of range for {} ({}..={})",
+                level,
+                codec.name(),
+                range.start(),
+                range.end()
+            )
+        });
+    }
+
+    Ok(Compressor(match codec {
+        Codec::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+            writer,
+            level.map_or(flate2::Compression::default(), |l| flate2::Compression::new(l as u32)),
+        )),
+        Codec::Zstd => Encoder::Zstd(
+            zstd::stream::write::Encoder::new(writer, level.unwrap_or(0))
+                .context("Failed to initialize zstd compressor")?,
+        ),
+        Codec::Bzip2 => Encoder::Bzip2(bzip2::write::BzEncoder::new(
+            writer,
+            level.map_or(bzip2::Compression::default(), |l| bzip2::Compression::new(l as u32)),
+        )),
+        Codec::Xz => Encoder::Xz(liblzma::write::XzEncoder::new(writer, level.unwrap_or(6) as u32)),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_compound_ext() {
+        assert_eq!(
+            split_ext(Path::new("data.ndjson.gz")),
+            (Some("ndjson".to_string()), Some(Codec::Gzip))
+        );
+        assert_eq!(
+            split_ext(Path::new("data.csv.zst")),
+            (Some("csv".to_string()), Some(Codec::Zstd))
+        );
+        assert_eq!(split_ext(Path::new("data.json")), (Some("json".to_string()), None));
+        assert_eq!(split_ext(Path::new("archive.xz")), (None, Some(Codec::Xz)));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for codec in [Codec::Gzip, Codec::Zstd, Codec::Bzip2, Codec::Xz] {
+            let mut compressed = Vec::new();
+            let mut wtr = compress(&mut compressed, codec, None).unwrap();
+            wtr.write_all(b"a,b\n1,2\n").unwrap();
+            wtr.finish().unwrap();
+
+            assert_eq!(Codec::from_magic(&compressed), Some(codec));
+
+            let (detected, mut rdr) = decompress(compressed.as_slice()).unwrap();
+            let mut out = String::new();
+            rdr.read_to_string(&mut out).unwrap();
+
+            assert_eq!(detected, Some(codec));
+            assert_eq!(out, "a,b\n1,2\n");
+        }
+
+        assert!(compress(Vec::new(), Codec::Bzip2, Some(0)).is_err());
+    }
+}
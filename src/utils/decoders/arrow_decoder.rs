@@ -0,0 +1,107 @@
+use std::{io::Error, iter::from_fn};
+
+use arrow_array::RecordBatch;
+use arrow_json::{WriterBuilder, writer::JsonArray};
+use arrow_schema::ArrowError;
+use resext::{CtxResult, ResExt};
+use serde_json::Value;
+
+use crate::utils::{DataTypes, WriterStreams, arrow_reader::arrow_error};
+
+/// Converts the rows of a record batch into JSON objects, nulls are kept as `null` fields. Dates
+/// and timestamps become RFC 3339 strings, structs objects and lists arrays.
+pub(crate) fn batch_to_json(batch: &RecordBatch) -> Result<Vec<Value>, ArrowError> {
+    let mut buf = Vec::new();
+
+    let mut writer = WriterBuilder::new().with_explicit_nulls(true).build::<_, JsonArray>(&mut buf);
+    writer.write(batch)?;
+    writer.finish()?;
+    drop(writer);
+
+    // an empty batch writes nothing at all
+    if buf.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_slice(&buf).map_err(|e| ArrowError::JsonError(e.to_string()))
+}
+
+/// Yields every row of every record batch as an object, the rows end at the first batch that
+/// fails to decode which is converted with `error`.
+pub(crate) fn batch_rows(
+    mut batches: impl Iterator<Item = Result<RecordBatch, ArrowError>>,
+    error: impl Fn(ArrowError) -> Error,
+) -> impl Iterator<Item = Result<Value, Error>> {
+    let mut rows = Vec::new().into_iter();
+    let mut failed = false;
+
+    from_fn(move || {
+        loop {
+            if let Some(row) = rows.next() {
+                return Some(Ok(row));
+            }
+            if failed {
+                return None;
+            }
+
+            match batches.next()?.and_then(|batch| batch_to_json(&batch)) {
+                Ok(batch) => rows = batch.into_iter(),
+                Err(e) => {
+                    failed = true;
+                    return Some(Err(error(e)));
+                }
+            }
+        }
+    })
+}
+
+/// Every row of an Arrow IPC file or stream is yielded as an object, the stream ends at the first
+/// record batch that fails to decode.
+#[inline]
+pub(crate) fn arrow_decoder(
+    batches: impl Iterator<Item = Result<RecordBatch, ArrowError>>,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
+    let iter = batch_rows(batches, arrow_error).enumerate().map(|(rec_no, rec)| {
+        rec.map(DataTypes::Json)
+            .context("Failed to deserialize file")
+            .with_context(|| format!("Invalid Arrow data in input file after row: {}", rec_no))
+    });
+
+    Ok(WriterStreams::Values { iter })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, Int64Array, TimestampMicrosecondArray};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_batch_rows() {
+        let at: ArrayRef = Arc::new(
+            TimestampMicrosecondArray::from(vec![Some(1_714_518_001_000_000), None])
+                .with_timezone("Europe/Berlin"),
+        );
+        let id: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        let batch = RecordBatch::try_from_iter([("id", id), ("at", at)]).unwrap();
+
+        let rows: Vec<Value> =
+            batch_rows([Ok(batch)].into_iter(), arrow_error).map(Result::unwrap).collect();
+        assert_eq!(
+            rows,
+            [json!({"id": 1, "at": "2024-05-01T01:00:01+02:00"}), json!({"id": 2, "at": null})]
+        );
+
+        let failed = ArrowError::IpcError("bad".into());
+        let mut rows = batch_rows(
+            [Err(failed), Ok(RecordBatch::new_empty(Arc::new(arrow_schema::Schema::empty())))]
+                .into_iter(),
+            arrow_error,
+        );
+        assert!(rows.next().unwrap().unwrap_err().to_string().contains("Invalid Arrow"));
+        assert!(rows.next().is_none());
+    }
+}
@@ -0,0 +1,231 @@
+use std::io::{BufRead, Error};
+
+use apache_avro::{
+    BigDecimal, Schema,
+    schema::{Names, UnionSchema},
+    types::Value as Avro,
+};
+use resext::{CtxResult, ResExt};
+use serde_json::{Map, Value};
+use toml::value::{Datetime, Offset, Time};
+
+use crate::utils::{
+    Binary, DataTypes, WriterStreams, avro_reader::avro_error, avro_reader::avro_names,
+    bytes_to_json, date_from_days, epoch_datetime,
+};
+
+/// Formats a timestamp of `ticks` units of `1 / per_sec` seconds, in UTC or local without an
+/// offset.
+fn timestamp(ticks: i64, per_sec: i64, utc: bool) -> Value {
+    let secs = ticks.div_euclid(per_sec);
+    let nanos = (ticks.rem_euclid(per_sec) * (1_000_000_000 / per_sec)) as u32;
+
+    match epoch_datetime(secs, nanos, utc.then_some(Offset::Z)) {
+        Some(dt) => Value::String(dt.to_string()),
+        None => Value::from(ticks),
+    }
+}
+
+/// Formats a time of day of `ticks` units of `1 / per_sec` seconds.
+fn time(ticks: i64, per_sec: i64) -> Value {
+    let secs = ticks.div_euclid(per_sec);
+    let time = Time {
+        hour: (secs / 3600 % 24) as u8,
+        minute: (secs % 3600 / 60) as u8,
+        second: (secs % 60) as u8,
+        nanosecond: (ticks.rem_euclid(per_sec) * (1_000_000_000 / per_sec)) as u32,
+    };
+
+    Value::String(Datetime { date: None, time: Some(time), offset: None }.to_string())
+}
+
+/// Formats an unscaled decimal integer with `scale` digits after the decimal point.
+fn decimal_text(unscaled: String, scale: usize) -> String {
+    let (sign, digits) = match unscaled.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", unscaled.as_str()),
+    };
+
+    if scale == 0 {
+        return unscaled;
+    }
+
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale);
+
+    format!("{}{}.{}", sign, int, frac)
+}
+
+/// Schema of the branch at `index` of a union.
+fn branch(union: &UnionSchema, index: u32) -> &Schema {
+    union.variants().get(index as usize).unwrap_or(&Schema::Null)
+}
+
+/// Converts an Avro value into a JSON value along with its schema. Bytes and fixed values become
+/// `binary`'s representation, enums their symbol and unions the value of their branch. Dates,
+/// times and timestamps become RFC 3339 strings (timestamps in UTC, local timestamps without an
+/// offset) and decimals exact decimal strings.
+pub(crate) fn avro_to_json(value: Avro, schema: &Schema, names: &Names, binary: Binary) -> Value {
+    let schema = match schema {
+        Schema::Ref { name } => names.get(name).unwrap_or(&Schema::Null),
+        schema => schema,
+    };
+
+    match value {
+        Avro::Null => Value::Null,
+        Avro::Boolean(b) => Value::Bool(b),
+        Avro::Int(i) => Value::from(i),
+        Avro::Long(i) => Value::from(i),
+        // going through the shortest representation keeps `0.1f32` as `0.1`
+        Avro::Float(f) => {
+            f.to_string().parse::<f64>().ok().and_then(serde_json::Number::from_f64).into()
+        }
+        Avro::Double(f) => serde_json::Number::from_f64(f).into(),
+        Avro::Bytes(data) | Avro::Fixed(_, data) => bytes_to_json(data, binary),
+        Avro::String(s) | Avro::Enum(_, s) => Value::String(s),
+        Avro::Uuid(uuid) => Value::String(uuid.to_string()),
+        Avro::Union(index, value) => {
+            let schema = match schema {
+                Schema::Union(union) => branch(union, index),
+                _ => &Schema::Null,
+            };
+            avro_to_json(*value, schema, names, binary)
+        }
+        Avro::Array(items) => {
+            let schema = match schema {
+                Schema::Array(array) => &*array.items,
+                _ => &Schema::Null,
+            };
+            Value::Array(
+                items.into_iter().map(|v| avro_to_json(v, schema, names, binary)).collect(),
+            )
+        }
+        Avro::Map(entries) => {
+            let schema = match schema {
+                Schema::Map(map) => &*map.types,
+                _ => &Schema::Null,
+            };
+
+            let obj: Map<String, Value> = entries
+                .into_iter()
+                .map(|(k, v)| (k, avro_to_json(v, schema, names, binary)))
+                .collect();
+            Value::Object(obj)
+        }
+        Avro::Record(fields) => {
+            let mut obj = Map::with_capacity(fields.len());
+
+            for (i, (name, value)) in fields.into_iter().enumerate() {
+                let schema = match schema {
+                    Schema::Record(record) => {
+                        record.fields.get(i).map_or(&Schema::Null, |field| &field.schema)
+                    }
+                    _ => &Schema::Null,
+                };
+
+                obj.insert(name, avro_to_json(value, schema, names, binary));
+            }
+
+            Value::Object(obj)
+        }
+        Avro::Date(days) => match date_from_days(days.into()) {
+            Some(date) => {
+                Value::String(Datetime { date: Some(date), time: None, offset: None }.to_string())
+            }
+            None => Value::from(days),
+        },
+        Avro::TimeMillis(ms) => time(ms.into(), 1000),
+        Avro::TimeMicros(us) => time(us, 1_000_000),
+        Avro::TimestampMillis(ms) => timestamp(ms, 1000, true),
+        Avro::TimestampMicros(us) => timestamp(us, 1_000_000, true),
+        Avro::TimestampNanos(ns) => timestamp(ns, 1_000_000_000, true),
+        Avro::LocalTimestampMillis(ms) => timestamp(ms, 1000, false),
+        Avro::LocalTimestampMicros(us) => timestamp(us, 1_000_000, false),
+        Avro::LocalTimestampNanos(ns) => timestamp(ns, 1_000_000_000, false),
+        Avro::Decimal(decimal) => {
+            let scale = match schema {
+                Schema::Decimal(decimal) => decimal.scale,
+                _ => 0,
+            };
+            let unscaled = BigDecimal::new(decimal.into(), 0).to_string();
+            Value::String(decimal_text(unscaled, scale))
+        }
+        Avro::BigDecimal(decimal) => Value::String(decimal.to_string()),
+        Avro::Duration(duration) => serde_json::json!({
+            "months": u32::from(duration.months()),
+            "days": u32::from(duration.days()),
+            "millis": u32::from(duration.millis()),
+        }),
+    }
+}
+
+/// Every value of an Avro object container file is yielded as its own value, with the schema
+/// embedded in the file. The stream ends at the first block that fails to decode.
+#[inline]
+pub(crate) fn avro_decoder<R: BufRead>(
+    reader: apache_avro::Reader<'static, R>,
+    binary: Binary,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
+    let schema = reader.writer_schema().clone();
+    let names = avro_names(&schema).context("Failed to resolve the schema of the input file")?;
+
+    let mut failed = false;
+
+    let values = reader.map_while(move |value| {
+        if failed {
+            return None;
+        }
+        failed = value.is_err();
+
+        Some(value.map_err(avro_error))
+    });
+
+    let iter = values.enumerate().map(move |(rec_no, rec)| {
+        rec.map(|value| DataTypes::Json(avro_to_json(value, &schema, &names, binary)))
+            .context("Failed to deserialize file")
+            .with_context(|| format!("Invalid Avro data in input file at record: {}", rec_no + 1))
+    });
+
+    Ok(WriterStreams::Values { iter })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_avro_logical_types() {
+        let schema = Schema::parse_str(
+            r#"{"type": "record", "name": "R", "fields": [
+                {"name": "price", "type": {"type": "bytes", "logicalType": "decimal", "precision": 4, "scale": 2}},
+                {"name": "day", "type": {"type": "int", "logicalType": "date"}},
+                {"name": "at", "type": ["null", {"type": "long", "logicalType": "timestamp-millis"}]},
+                {"name": "local", "type": {"type": "long", "logicalType": "local-timestamp-micros"}},
+                {"name": "blob", "type": "bytes"}
+            ]}"#,
+        )
+        .unwrap();
+        let names = avro_names(&schema).unwrap();
+
+        let value = Avro::Record(vec![
+            ("price".into(), Avro::Decimal(vec![0xff, 0xfb].into())),
+            ("day".into(), Avro::Date(19_844)),
+            ("at".into(), Avro::Union(1, Box::new(Avro::TimestampMillis(-500)))),
+            ("local".into(), Avro::LocalTimestampMicros(1_000_001)),
+            ("blob".into(), Avro::Bytes(vec![0, 255])),
+        ]);
+
+        assert_eq!(
+            avro_to_json(value, &schema, &names, Binary::Array),
+            serde_json::json!({
+                "price": "-0.05",
+                "day": "2024-05-01",
+                "at": "1969-12-31T23:59:59.5Z",
+                "local": "1970-01-01T00:00:01.000001",
+                "blob": [0, 255]
+            })
+        );
+        assert_eq!(decimal_text("12345".into(), 0), "12345");
+        assert_eq!(decimal_text("-7".into(), 3), "-0.007");
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    io::{BufRead, Error},
+    iter::from_fn,
+};
+
+use resext::{CtxResult, ResExt};
+use serde_json::{Map, Value};
+use toml::value::Offset;
+
+use crate::utils::{
+    Binary, CborTags, DataTypes, WriterStreams, bytes_to_json,
+    cbor_reader::{CborReader, Item},
+    epoch_datetime,
+};
+
+/// Wraps a tagged value as a `{"$tag": <tag>, "$value": <value>}` object.
+#[inline]
+fn tagged(tag: u64, value: Value) -> Value {
+    serde_json::json!({ "$tag": tag, "$value": value })
+}
+
+/// Formats big-endian unsigned `bytes` as a decimal number, plus one for negative bignums.
+fn decimal(bytes: &[u8], plus_one: bool) -> String {
+    // little-endian limbs of 9 decimal digits
+    let mut limbs: Vec<u64> = vec![0];
+
+    for &b in bytes {
+        let mut carry = b as u64;
+        for limb in limbs.iter_mut() {
+            let v = *limb * 256 + carry;
+            *limb = v % 1_000_000_000;
+            carry = v / 1_000_000_000;
+        }
+        while carry > 0 {
+            limbs.push(carry % 1_000_000_000);
+            carry /= 1_000_000_000;
+        }
+    }
+
+    if plus_one {
+        for limb in limbs.iter_mut() {
+            *limb += 1;
+            if *limb < 1_000_000_000 {
+                break;
+            }
+            *limb = 0;
+        }
+        if limbs.last() == Some(&0) {
+            limbs.push(1);
+        }
+    }
+
+    let mut s = limbs.last().map_or_else(String::new, u64::to_string);
+    for limb in limbs.iter().rev().skip(1) {
+        s.push_str(&format!("{:09}", limb));
+    }
+
+    s
+}
+
+/// Converts a bignum (tags 2 and 3), numbers that don't fit into 64 bits become decimal strings.
+fn bignum(bytes: &[u8], negative: bool) -> Value {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let bytes = &bytes[start..];
+
+    if bytes.len() <= 8 {
+        let mut buf = [0u8; 8];
+        buf[8 - bytes.len()..].copy_from_slice(bytes);
+        let n = u64::from_be_bytes(buf);
+
+        match (negative, i64::try_from(n)) {
+            (false, _) => return Value::from(n),
+            (true, Ok(n)) => return Value::from(-1 - n),
+            (true, Err(_)) => {}
+        }
+    }
+
+    let digits = decimal(bytes, negative);
+    Value::String(if negative { format!("-{}", digits) } else { digits })
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 datetime in UTC, `None` outside of the
+/// years 0 to 9999.
+fn epoch(secs: i64, nanosecond: u32) -> Option<String> {
+    epoch_datetime(secs, nanosecond, Some(Offset::Z)).map(|dt| dt.to_string())
+}
+
+/// Converts a tagged item with a known meaning, `None` if the tag is unknown or its content isn't
+/// what the tag expects.
+fn convert_tag(tag: u64, item: &Item) -> Option<Value> {
+    let int = |item: &Item| match item {
+        Item::Uint(n) => i64::try_from(*n).ok(),
+        Item::Nint(n) => i64::try_from(*n).ok().map(|n| -1 - n),
+        _ => None,
+    };
+
+    match (tag, item) {
+        // date / time strings, RFC 3339 and RFC 8943 full dates
+        (0 | 1004, Item::Text(s)) => Some(Value::String(s.clone())),
+        (1, Item::Float(f)) if f.is_finite() => {
+            let secs = f.floor();
+            let nanos = ((f - secs) * 1e9).round().min(999_999_999.0) as u32;
+            epoch(secs as i64, nanos).map(Value::String)
+        }
+        (1, item) => epoch(int(item)?, 0).map(Value::String),
+        (2 | 3, Item::Bytes(bytes)) => Some(bignum(bytes, tag == 3)),
+        // decimal fractions and bigfloats, `[exponent, mantissa]`
+        (4 | 5, Item::Array(pair)) if pair.len() == 2 => {
+            let (exp, mant) = (int(&pair[0])?, int(&pair[1])?);
+            let value = if tag == 4 {
+                format!("{}e{}", mant, exp).parse::<f64>().ok()?
+            } else {
+                mant as f64 * 2f64.powi(i32::try_from(exp).ok()?)
+            };
+            serde_json::Number::from_f64(value).map(Value::Number)
+        }
+        _ => None,
+    }
+}
+
+/// Converts a CBOR item into a JSON value. Byte strings become `binary`'s representation, map keys
+/// that aren't text become their JSON text and integers that don't fit into 64 bits become
+/// decimal strings. Known tags are converted with `CborTags::Convert`, other tags become
+/// `{"$tag": <tag>, "$value": <value>}` objects.
+pub(crate) fn cbor_to_json(item: Item, binary: Binary, tags: CborTags) -> Value {
+    match item {
+        Item::Uint(n) => Value::from(n),
+        Item::Nint(n) => match i64::try_from(n) {
+            Ok(n) => Value::from(-1 - n),
+            Err(_) => Value::String(format!("-{}", n as u128 + 1)),
+        },
+        Item::Bytes(data) => bytes_to_json(data, binary),
+        Item::Text(s) => Value::String(s),
+        Item::Array(items) => {
+            Value::Array(items.into_iter().map(|i| cbor_to_json(i, binary, tags)).collect())
+        }
+        Item::Map(pairs) => {
+            let mut obj = Map::with_capacity(pairs.len());
+
+            for (k, v) in pairs {
+                let key = match cbor_to_json(k, binary, tags) {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                };
+
+                obj.insert(key, cbor_to_json(v, binary, tags));
+            }
+
+            Value::Object(obj)
+        }
+        // self-described CBOR marks the start of a file, it carries no data
+        Item::Tag(55_799, item) => cbor_to_json(*item, binary, tags),
+        Item::Tag(tag, item) => match tags {
+            CborTags::Convert => convert_tag(tag, &item)
+                .unwrap_or_else(|| tagged(tag, cbor_to_json(*item, binary, tags))),
+            CborTags::Preserve => tagged(tag, cbor_to_json(*item, binary, tags)),
+        },
+        Item::Bool(b) => Value::Bool(b),
+        Item::Null | Item::Undefined => Value::Null,
+        Item::Simple(n) => serde_json::json!({ "$simple": n }),
+        Item::Float(f) => serde_json::Number::from_f64(f).into(),
+    }
+}
+
+/// Every top-level item of a CBOR sequence is yielded as its own value, the stream ends at the
+/// first malformed item.
+#[inline]
+pub(crate) fn cbor_decoder<R: BufRead>(
+    mut reader: CborReader<R>,
+    binary: Binary,
+    tags: CborTags,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
+    let mut failed = false;
+
+    let items = from_fn(move || {
+        if failed {
+            return None;
+        }
+
+        let next = reader.read_item()?;
+        failed = next.is_err();
+
+        Some(next)
+    });
+
+    let iter = items.enumerate().map(move |(rec_no, rec)| {
+        rec.map(|item| DataTypes::Json(cbor_to_json(item, binary, tags)))
+            .context("Failed to deserialize file")
+            .with_context(|| format!("Invalid CBOR data in input file at item: {}", rec_no + 1))
+    });
+
+    Ok(WriterStreams::Values { iter })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cbor_tags() {
+        let convert = |item| cbor_to_json(item, Binary::Base64, CborTags::Convert);
+        let tag = |tag, item| Item::Tag(tag, Box::new(item));
+
+        assert_eq!(convert(tag(1, Item::Uint(1_700_000_000))), "2023-11-14T22:13:20Z");
+        assert_eq!(convert(tag(1, Item::Float(-0.5))), "1969-12-31T23:59:59.5Z");
+        assert_eq!(
+            convert(tag(0, Item::Text("2024-05-01T12:30:00Z".into()))),
+            "2024-05-01T12:30:00Z"
+        );
+        assert_eq!(convert(tag(2, Item::Bytes(vec![1, 0]))), 256);
+        assert_eq!(convert(tag(3, Item::Bytes(vec![0xff; 8]))), "-18446744073709551616");
+        assert_eq!(convert(tag(2, Item::Bytes(vec![1; 12]))), "310698676526526814092329217");
+        assert_eq!(convert(tag(4, Item::Array(vec![Item::Nint(1), Item::Uint(27315)]))), 273.15);
+        assert_eq!(convert(Item::Nint(u64::MAX)), "-18446744073709551616");
+        assert_eq!(
+            convert(tag(32, Item::Text("https://a.b".into()))),
+            serde_json::json!({"$tag": 32, "$value": "https://a.b"})
+        );
+
+        let preserved = cbor_to_json(tag(1, Item::Uint(0)), Binary::Array, CborTags::Preserve);
+        assert_eq!(preserved, serde_json::json!({"$tag": 1, "$value": 0}));
+    }
+}
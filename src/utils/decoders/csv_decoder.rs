@@ -1,29 +1,98 @@
 use std::{
-    fs::File,
-    io::{BufReader, Error, ErrorKind as EK},
+    io::{BufReader, Error, ErrorKind as EK, Read},
+    iter::from_fn,
 };
 
+use csv::ByteRecord;
 use resext::{CtxResult, ResExt};
 
-use crate::utils::{DataTypes, WriterStreams};
+use crate::utils::{
+    CsvDialect, DataTypes, Position, Ragged, WriterStreams,
+    csv_reader::{Recorder, fit_row},
+    raw_row, read_error, record_error,
+};
 
 #[inline]
-pub(crate) fn csv_decoder(
-    mut reader: csv::Reader<BufReader<File>>,
+pub(crate) fn csv_decoder<R: Read>(
+    mut reader: csv::Reader<Recorder<BufReader<R>>>,
+    delimiter: u8,
+    dialect: CsvDialect,
 ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
-    let headers = reader
+    // without a header row this peeks at the first row, which is still read as a record
+    let first = reader
         .headers()
-        .map_err(|_| Error::new(EK::InvalidData, "Input CSV file headers are missing"))
-        .context("Failed to read input file headers")?
-        .iter()
-        .map(|s| s.to_string())
-        .collect::<Vec<String>>();
+        .map_err(|e| match e.is_io_error() {
+            true => read_error(e),
+            false => Error::new(EK::InvalidData, "Input CSV file headers are missing"),
+        })
+        .context("Failed to read input file headers")?;
+
+    let mut headers = if dialect.has_headers {
+        first.iter().map(|s| s.to_string()).collect::<Vec<String>>()
+    } else if !dialect.header_names.is_empty() {
+        dialect.header_names
+    } else {
+        (1..=first.len()).map(|n| format!("col{}", n)).collect()
+    };
+
+    let quote = dialect.quote as u8;
+    let ragged = dialect.ragged;
+
+    let len = headers.len();
+
+    if ragged == Ragged::ExtraColumn {
+        headers.push("_extra".to_string());
+    }
+
+    let seen = reader.get_ref().seen.clone();
+
+    // the reader's position after a row is where the row's original bytes end
+    let rows = from_fn(move || {
+        let mut rec = ByteRecord::new();
+        let read = reader.read_byte_record(&mut rec);
+        let end = reader.position().byte();
 
-    let iter = reader.into_byte_records().enumerate().map(move |(line_no, rec)| {
+        match read {
+            Ok(true) => Some(Ok((rec, end))),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    });
+
+    let iter = rows.enumerate().map(move |(rec_no, rec)| {
+        // the reader is flexible so rows with the wrong number of fields are still read and can
+        // be kept in the reject file
         let record = rec
-            .map_err(|_| Error::new(EK::InvalidData, "Invalid CSV record"))
+            .map_err(|e| match e.is_io_error() {
+                // e.g. truncated compressed input, the rest of the input can't be read
+                true => read_error(e),
+                false => Error::new(EK::InvalidData, "Invalid CSV record"),
+            })
+            .and_then(|(mut rec, end)| {
+                let fitted = fit_row(&mut rec, len, ragged);
+
+                let start = rec.position().map(|p| p.byte());
+                let raw = match (&fitted, &seen, start) {
+                    (Err(_), Some(seen), Some(start)) => seen.borrow().raw(start, end),
+                    _ => None,
+                };
+                if let Some(seen) = &seen {
+                    seen.borrow_mut().consume(end);
+                }
+
+                let Err(msg) = fitted else { return Ok(rec) };
+
+                let pos = Position {
+                    record: Some(rec_no + 1),
+                    line: rec.position().map(|p| p.line() as usize),
+                    offset: start,
+                    ..Position::default()
+                };
+                let raw = raw.unwrap_or_else(|| raw_row(&rec, delimiter, quote));
+                Err(record_error(format!("Invalid CSV record: {}", msg), pos, raw))
+            })
             .context("Failed to deserialize file")
-            .with_context(|| format!("Invalid CSV data in input file at line: {}", line_no + 1));
+            .with_context(|| format!("Invalid CSV data in input file at record: {}", rec_no + 1));
 
         match record {
             Ok(ok) => Ok(DataTypes::Csv(ok)),
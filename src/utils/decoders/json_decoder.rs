@@ -1,26 +1,63 @@
-use std::{
-    fs::File,
-    io::{BufReader, Error, ErrorKind as EK},
-};
+use std::io::{Error, Read};
 
 use resext::{CtxResult, ResExt};
 
-use crate::utils::{DataTypes, WriterStreams};
+use crate::utils::{
+    DataTypes, WriterStreams,
+    json_reader::{JsonInput, element_error, json_error},
+};
+
+/// Values of either JSON input, so both share one iterator type.
+enum JsonValues<A, S> {
+    Array(A),
+    Stream(S),
+}
+
+impl<T, A: Iterator<Item = T>, S: Iterator<Item = T>> Iterator for JsonValues<A, S> {
+    type Item = T;
 
+    fn next(&mut self) -> Option<T> {
+        match self {
+            JsonValues::Array(iter) => iter.next(),
+            JsonValues::Stream(iter) => iter.next(),
+        }
+    }
+}
+
+/// Top-level arrays are decoded one element at a time as a stream of records (like NDJSON) so
+/// they never have to fit in memory, other input is decoded as a stream of whole values.
 #[inline]
-pub(crate) fn json_decoder(
-    reader: serde_json::Deserializer<serde_json::de::IoRead<BufReader<File>>>,
+pub(crate) fn json_decoder<R: Read>(
+    input: JsonInput<R>,
 ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
-    let iter = reader.into_iter::<serde_json::Value>().map(move |obj| {
-        let obj = obj
-            .map_err(|_| Error::new(EK::InvalidData, "Invalid JSON"))
-            .context("Failed to deserialize file")
-            .context("Invalid JSON data in input file");
-        match obj {
-            Ok(ok) => Ok(DataTypes::Json(ok)),
-            Err(err) => Err(err),
+    match input {
+        JsonInput::Array(elements) => {
+            let values = elements.enumerate().map(|(idx, el)| {
+                el.and_then(|el| {
+                    serde_json::from_slice(&el.bytes).map_err(|e| element_error(e, &el, idx + 1))
+                })
+                .map(DataTypes::Json)
+                .context("Failed to deserialize array element")
+                .context("Invalid JSON data in input file")
+            });
+
+            Ok(WriterStreams::Ndjson { values: JsonValues::Array(values) })
         }
-    });
 
-    Ok(WriterStreams::Values { iter })
+        JsonInput::Values((reader, state)) => {
+            let iter =
+                reader.into_iter::<serde_json::Value>().enumerate().map(move |(idx, obj)| {
+                    let obj = obj
+                        .map_err(|e| json_error(e, &state, Some(idx + 1)))
+                        .context("Failed to deserialize file")
+                        .context("Invalid JSON data in input file");
+                    match obj {
+                        Ok(ok) => Ok(DataTypes::Json(ok)),
+                        Err(err) => Err(err),
+                    }
+                });
+
+            Ok(WriterStreams::Values { iter: JsonValues::Stream(iter) })
+        }
+    }
 }
@@ -1,4 +1,11 @@
+pub(crate) mod arrow_decoder;
+pub(crate) mod avro_decoder;
+pub(crate) mod cbor_decoder;
 pub(crate) mod csv_decoder;
 pub(crate) mod json_decoder;
+pub(crate) mod msgpack_decoder;
 pub(crate) mod ndjson_decoder;
+pub(crate) mod parquet_decoder;
 pub(crate) mod toml_decoder;
+pub(crate) mod xml_decoder;
+pub(crate) mod yaml_decoder;
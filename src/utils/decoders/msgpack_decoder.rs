@@ -0,0 +1,141 @@
+use std::{
+    io::{BufRead, Error},
+    iter::from_fn,
+};
+
+use resext::{CtxResult, ResExt};
+use serde_json::{Map, Value};
+
+use crate::utils::{
+    Binary, DataTypes, WriterStreams, bytes_to_json, msgpack_reader::MsgpackReader,
+};
+
+/// Converts a MessagePack value into a JSON value, map keys that aren't strings become their JSON
+/// text and ext values become `{"$ext": {"type": <type>, "data": <bytes>}}` objects.
+pub(crate) fn msgpack_to_json(value: rmpv::Value, binary: Binary) -> Value {
+    match value {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(b) => Value::Bool(b),
+        rmpv::Value::Integer(i) => match i.as_i64() {
+            Some(i) => Value::from(i),
+            None => i.as_u64().map_or(Value::Null, Value::from),
+        },
+        // going through the shortest representation keeps `0.1f32` as `0.1`
+        rmpv::Value::F32(f) => {
+            f.to_string().parse::<f64>().ok().and_then(serde_json::Number::from_f64).into()
+        }
+        rmpv::Value::F64(f) => serde_json::Number::from_f64(f).into(),
+        rmpv::Value::String(s) => Value::String(match s.as_str() {
+            Some(s) => s.to_string(),
+            None => String::from_utf8_lossy(s.as_bytes()).into_owned(),
+        }),
+        rmpv::Value::Binary(data) => bytes_to_json(data, binary),
+        rmpv::Value::Array(items) => {
+            Value::Array(items.into_iter().map(|v| msgpack_to_json(v, binary)).collect())
+        }
+        rmpv::Value::Map(pairs) => {
+            let mut obj = Map::with_capacity(pairs.len());
+
+            for (k, v) in pairs {
+                let key = match msgpack_to_json(k, binary) {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                };
+
+                obj.insert(key, msgpack_to_json(v, binary));
+            }
+
+            Value::Object(obj)
+        }
+        rmpv::Value::Ext(ty, data) => serde_json::json!({
+            "$ext": { "type": ty, "data": bytes_to_json(data, binary) }
+        }),
+    }
+}
+
+/// Every top-level value of a stream of concatenated MessagePack values is yielded as its own
+/// value, the stream ends at the first invalid or truncated value.
+#[inline]
+pub(crate) fn msgpack_decoder<R: BufRead>(
+    mut reader: MsgpackReader<R>,
+    binary: Binary,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
+    let mut failed = false;
+
+    let values = from_fn(move || {
+        if failed {
+            return None;
+        }
+
+        let next = reader.read_value()?;
+        failed = next.is_err();
+
+        Some(next)
+    });
+
+    let iter = values.enumerate().map(move |(rec_no, rec)| {
+        rec.map(|v| DataTypes::Json(msgpack_to_json(v, binary)))
+            .context("Failed to deserialize file")
+            .with_context(|| {
+                format!("Invalid MessagePack data in input file at value: {}", rec_no + 1)
+            })
+    });
+
+    Ok(WriterStreams::Values { iter })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::msgpack_reader::msgpack_reader;
+
+    #[test]
+    fn test_msgpack_values() {
+        use rmpv::Value as V;
+
+        let mut input = Vec::new();
+        let record = V::Map(vec![
+            (V::from("id"), V::from(7)),
+            (V::from("ratio"), V::F32(0.1)),
+            (V::from("blob"), V::Binary(vec![0, 255, 1])),
+            (V::from(1), V::Ext(-1, vec![0, 0, 0, 1])),
+        ]);
+        rmpv::encode::write_value(&mut input, &record).unwrap();
+        rmpv::encode::write_value(&mut input, &V::from("x")).unwrap();
+
+        let decode = |input: &[u8], binary| {
+            let mut reader = msgpack_reader(input);
+            from_fn(move || reader.read_value())
+                .map(|v| v.map(|v| msgpack_to_json(v, binary)))
+                .collect::<Vec<_>>()
+        };
+
+        let values: Vec<Value> =
+            decode(&input, Binary::Base64).into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            values,
+            [
+                serde_json::json!({
+                    "id": 7,
+                    "ratio": 0.1,
+                    "blob": "AP8B",
+                    "1": {"$ext": {"type": -1, "data": "AAAAAQ=="}}
+                }),
+                Value::from("x"),
+            ]
+        );
+
+        let values = decode(&input, Binary::Array);
+        assert_eq!(values[0].as_ref().unwrap()["blob"], serde_json::json!([0, 255, 1]));
+
+        // a truncated value is an error at the offset it started at
+        let values = decode(&input[..input.len() - 1], Binary::Base64);
+        let err = values.last().unwrap().as_ref().unwrap_err();
+        assert!(err.to_string().starts_with("Invalid MessagePack"), "{}", err);
+
+        // the reserved marker is an error while the same byte inside a string isn't
+        let values = decode(&[0x92, 0xa1, 0xc1, 0xc1], Binary::Base64);
+        let err = values[0].as_ref().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid MessagePack: reserved marker 0xc1");
+    }
+}
@@ -1,28 +1,44 @@
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{BufRead, BufReader, Error, Read},
     iter::from_fn,
 };
 
 use resext::{CtxResult, ResExt};
 
-use crate::utils::{DataTypes, WriterStreams};
+use crate::utils::{DataTypes, Position, WriterStreams, read_error, record_error};
 
-pub(crate) fn ndjson_decoder(
-    mut reader: BufReader<File>,
+pub(crate) fn ndjson_decoder<R: Read>(
+    mut reader: BufReader<R>,
 ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
     let mut buf = Vec::new();
     let mut line_no = 0usize;
+    let mut rec_no = 0usize;
+    let mut offset = 0u64;
+    let mut failed = false;
 
     let iter = from_fn(move || {
         loop {
+            if failed {
+                return None;
+            }
+
             line_no += 1;
             buf.clear();
-            let bytes = reader.read_until(b'\n', &mut buf).dyn_expect(
-                || format!("Failed to read line: {} in input file", line_no),
-                1,
-                true,
-            );
+            let bytes = match reader
+                .read_until(b'\n', &mut buf)
+                .map_err(read_error)
+                .with_context(|| format!("Failed to read line: {} in input file", line_no))
+            {
+                Ok(n) => n,
+                Err(e) => {
+                    // reading failures aren't recoverable so they end the conversion
+                    failed = true;
+                    return Some(Err(e));
+                }
+            };
+
+            let start = offset;
+            offset += bytes as u64;
 
             if bytes == 0 {
                 return None;
@@ -31,12 +47,23 @@ pub(crate) fn ndjson_decoder(
                     buf.pop();
                 }
 
-                if buf.is_empty() {
+                if buf.trim_ascii().is_empty() {
                     continue;
                 };
 
+                rec_no += 1;
+
                 let ndjson_obj = serde_json::from_slice(buf.as_slice())
-                    .map_err(|_| Error::new(std::io::ErrorKind::InvalidData, "Invalid NDJSON"))
+                    .map_err(|e| {
+                        // serde_json's position is relative to the line
+                        let pos = Position {
+                            record: Some(rec_no),
+                            line: Some(line_no),
+                            column: (e.column() != 0).then_some(e.column()),
+                            offset: Some(start),
+                        };
+                        record_error(format!("Invalid NDJSON: {}", e), pos, buf.clone())
+                    })
                     .context("Failed to deserialize file")
                     .with_context(|| {
                         format!("Invalid NDJSON values in input file at line: {}", line_no)
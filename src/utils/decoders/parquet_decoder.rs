@@ -0,0 +1,23 @@
+use std::io::Error;
+
+use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
+use resext::{CtxResult, ResExt};
+
+use crate::utils::{
+    DataTypes, WriterStreams, arrow_decoder::batch_rows, parquet_reader::parquet_error,
+};
+
+/// Every row of every row group is yielded as an object, the stream ends at the first row group
+/// that fails to decode.
+#[inline]
+pub(crate) fn parquet_decoder(
+    reader: ParquetRecordBatchReader,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
+    let iter = batch_rows(reader, parquet_error).enumerate().map(|(rec_no, rec)| {
+        rec.map(DataTypes::Json)
+            .context("Failed to deserialize file")
+            .with_context(|| format!("Invalid Parquet data in input file after row: {}", rec_no))
+    });
+
+    Ok(WriterStreams::Values { iter })
+}
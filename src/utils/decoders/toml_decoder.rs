@@ -2,7 +2,7 @@ use std::io::Error;
 
 use resext::{CtxResult, ResExt};
 
-use crate::utils::{DataTypes, WriterStreams};
+use crate::utils::{DataTypes, WriterStreams, toml_reader::toml_error};
 
 #[inline]
 pub(crate) fn toml_decoder(
@@ -10,7 +10,7 @@ pub(crate) fn toml_decoder(
 ) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
     let iter = [content].into_iter().map(move |c| {
         let toml_val = toml::from_slice(c.as_slice())
-            .map_err(|_| Error::new(std::io::ErrorKind::InvalidData, "Invalid TOML"))
+            .map_err(|e| toml_error(e, c.as_slice()))
             .context("Failed to deserialize file")
             .context("Invalid TOML values in input file");
 
@@ -0,0 +1,270 @@
+use std::io::{BufRead, Error};
+
+use quick_xml::{
+    Reader, XmlVersion,
+    events::{BytesStart, Event},
+};
+use resext::{CtxResult, ResExt};
+use serde_json::{Map, Value};
+
+use crate::utils::{
+    DataTypes, WriterStreams,
+    xml_reader::{resolve_ref, xml_error},
+};
+
+/// Element that is being read, its children and text are collected until its end tag.
+struct Open {
+    name: String,
+    map: Map<String, Value>,
+    text: String,
+}
+
+impl Open {
+    fn new(start: &BytesStart, offset: u64) -> Result<Self, Error> {
+        let mut map = Map::new();
+
+        for attr in start.attributes() {
+            let attr = attr.map_err(|e| xml_error(e, offset))?;
+            let value =
+                attr.normalized_value(XmlVersion::Implicit1_0).map_err(|e| xml_error(e, offset))?;
+
+            map.insert(format!("@{}", attr.key.as_ref()), Value::String(value.into_owned()));
+        }
+
+        Ok(Open { name: start.name().as_ref().to_string(), map, text: String::new() })
+    }
+
+    /// Converts the element into its value, elements with only text become strings and empty
+    /// elements become `null`.
+    fn into_value(self) -> Value {
+        let text = self.text.trim();
+
+        if self.map.is_empty() {
+            return if text.is_empty() { Value::Null } else { Value::String(text.to_string()) };
+        }
+
+        let mut map = self.map;
+        if !text.is_empty() {
+            map.insert("#text".to_string(), Value::String(text.to_string()));
+        }
+
+        Value::Object(map)
+    }
+}
+
+/// Adds a child to its parent, repeated children are collected into an array.
+fn insert_child(map: &mut Map<String, Value>, name: String, value: Value) {
+    match map.get_mut(&name) {
+        Some(Value::Array(arr)) => arr.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+        None => {
+            map.insert(name, value);
+        }
+    }
+}
+
+/// Whether `start` is a record element, records are the elements named `record` (qualified or
+/// local name) that aren't inside another record, or the children of the root element.
+fn is_record(record: Option<&str>, depth: usize, start: &BytesStart) -> bool {
+    match record {
+        Some(tag) => start.name().as_ref() == tag || start.local_name().as_ref() == tag,
+        None => depth == 1,
+    }
+}
+
+/// Reads the record elements of an XML document one at a time.
+struct XmlRecords<R: BufRead> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    record: Option<String>,
+    depth: usize,
+    stack: Vec<Open>,
+    done: bool,
+}
+
+impl<R: BufRead> XmlRecords<R> {
+    /// Closes the innermost open element, returns the record if it was one.
+    fn close(&mut self) -> Option<Value> {
+        let open = self.stack.pop()?;
+        let name = open.name.clone();
+        let value = open.into_value();
+
+        match self.stack.last_mut() {
+            Some(parent) => {
+                insert_child(&mut parent.map, name, value);
+                None
+            }
+            None => Some(match value {
+                Value::Object(_) => value,
+                Value::Null => Value::Object(Map::new()),
+                other => Value::Object(Map::from_iter([("#text".to_string(), other)])),
+            }),
+        }
+    }
+
+    fn read_next(&mut self) -> Option<Result<Value, Error>> {
+        loop {
+            self.buf.clear();
+            let offset = self.reader.buffer_position();
+
+            let event = match self.reader.read_event_into(&mut self.buf) {
+                Ok(event) => event,
+                Err(e) => return Some(Err(xml_error(e, self.reader.error_position()))),
+            };
+
+            let empty = matches!(event, Event::Empty(_));
+
+            match event {
+                Event::Start(ref start) | Event::Empty(ref start)
+                    if !self.stack.is_empty()
+                        || is_record(self.record.as_deref(), self.depth, start) =>
+                {
+                    let open = match Open::new(start, offset) {
+                        Ok(open) => open,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    self.stack.push(open);
+
+                    if empty {
+                        if let Some(record) = self.close() {
+                            return Some(Ok(record));
+                        }
+                    } else {
+                        self.depth += 1;
+                    }
+                }
+                Event::Start(_) => self.depth += 1,
+                Event::End(_) => {
+                    self.depth = self.depth.saturating_sub(1);
+
+                    if let Some(record) = self.close() {
+                        return Some(Ok(record));
+                    }
+                }
+                Event::Text(text) => {
+                    if let Some(open) = self.stack.last_mut() {
+                        open.text.push_str(&text.xml10_content());
+                    }
+                }
+                Event::CData(cdata) => {
+                    if let Some(open) = self.stack.last_mut() {
+                        open.text.push_str(&cdata.xml10_content());
+                    }
+                }
+                Event::GeneralRef(r) => {
+                    if let Some(open) = self.stack.last_mut() {
+                        open.text.push_str(&resolve_ref(&r));
+                    }
+                }
+                Event::Eof => {
+                    if self.depth > 0 {
+                        return Some(Err(xml_error(
+                            "unexpected end of input, some elements were never closed",
+                            self.reader.buffer_position(),
+                        )));
+                    }
+
+                    return None;
+                }
+                // declarations, comments, processing instructions and doctypes hold no data
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for XmlRecords<R> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let next = self.read_next();
+
+        // XML can't be recovered after a syntax error
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+
+        next
+    }
+}
+
+/// Every record element (`record`, or the children of the root element if it's `None`) is yielded
+/// as an object, attributes become `@name` keys, text next to attributes or children becomes a
+/// `#text` key and repeated children become arrays.
+#[inline]
+pub(crate) fn xml_decoder<R: BufRead>(
+    reader: Reader<R>,
+    record: Option<String>,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error> {
+    let records =
+        XmlRecords { reader, buf: Vec::new(), record, depth: 0, stack: Vec::new(), done: false };
+
+    let values = records.enumerate().map(|(rec_no, rec)| {
+        rec.map(DataTypes::Json)
+            .context("Failed to deserialize file")
+            .with_context(|| format!("Invalid XML data in input file at record: {}", rec_no + 1))
+    });
+
+    Ok(WriterStreams::Ndjson { values })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::xml_reader::xml_reader;
+
+    fn records(xml: &str, record: Option<&str>) -> Vec<Value> {
+        let records = XmlRecords {
+            reader: xml_reader(xml.as_bytes()),
+            buf: Vec::new(),
+            record: record.map(str::to_string),
+            depth: 0,
+            stack: Vec::new(),
+            done: false,
+        };
+
+        records.map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn test_xml_records() {
+        let xml = r#"<?xml version="1.0"?>
+<feed>
+  <item id="1">
+    <name>A &amp; B</name>
+    <tag>x</tag>
+    <tag>y</tag>
+    <price currency="EUR">9.5</price>
+    <empty/>
+  </item>
+  <item id="2"/>
+</feed>"#;
+
+        let expected = serde_json::json!([
+            {
+                "@id": "1",
+                "name": "A & B",
+                "tag": ["x", "y"],
+                "price": {"@currency": "EUR", "#text": "9.5"},
+                "empty": null
+            },
+            {"@id": "2"}
+        ]);
+
+        assert_eq!(Value::Array(records(xml, None)), expected);
+
+        // nested record elements are found at any depth
+        let nested = "<a><b><rec><v>1</v></rec></b><rec><v>2</v></rec></a>";
+        assert_eq!(
+            Value::Array(records(nested, Some("rec"))),
+            serde_json::json!([{"v": "1"}, {"v": "2"}])
+        );
+    }
+}
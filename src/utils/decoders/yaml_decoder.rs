@@ -0,0 +1,38 @@
+use std::io::{Error, ErrorKind as EK};
+
+use resext::{CtxResult, ResExt};
+use serde::Deserialize;
+
+use crate::utils::{DataTypes, WriterStreams};
+
+/// Every document in a (multi-document) YAML stream is yielded as its own value, YAML can't be
+/// recovered after a syntax error so the stream ends at the first invalid document.
+#[inline]
+pub(crate) fn yaml_decoder<'a>(
+    reader: serde_norway::Deserializer<'a>,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error> {
+    let mut failed = false;
+
+    let iter = reader.enumerate().map_while(move |(doc_no, doc)| {
+        if failed {
+            return None;
+        }
+
+        let yaml_val = serde_json::Value::deserialize(doc)
+            .map_err(|e| Error::new(EK::InvalidData, format!("Invalid YAML: {}", e)))
+            .context("Failed to deserialize file")
+            .with_context(|| {
+                format!("Invalid YAML values in input file at document: {}", doc_no + 1)
+            });
+
+        match yaml_val {
+            Ok(ok) => Some(Ok(DataTypes::Json(ok))),
+            Err(err) => {
+                failed = true;
+                Some(Err(err))
+            }
+        }
+    });
+
+    Ok(WriterStreams::Values { iter })
+}
@@ -0,0 +1,552 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    io::{Error, ErrorKind as EK},
+};
+
+use regex::Regex;
+use resext::{CtxResult, ResExt};
+
+use crate::utils::{DataTypes, Options, WriterStreams};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Field(String),
+    Str(String),
+    Num(f64),
+    Word(&'static str),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+const WORDS: [&str; 8] = ["and", "or", "not", "is", "null", "true", "false", "contains"];
+const OPS: [&str; 9] = ["==", "!=", "<=", ">=", "=~", "!~", "<", ">", "="];
+
+/// Splits an expression into tokens, every token keeps the column it starts at for errors.
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let err = |msg: String, col: usize| {
+        Error::new(EK::InvalidInput, format!("{} at column {}", msg, col + 1))
+    };
+
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let c = chars[idx];
+        let start = idx;
+
+        if c.is_whitespace() {
+            idx += 1;
+            continue;
+        }
+
+        let token = match c {
+            '(' => {
+                idx += 1;
+                Token::Open
+            }
+            ')' => {
+                idx += 1;
+                Token::Close
+            }
+            '"' | '\'' | '`' => {
+                let mut s = String::new();
+                idx += 1;
+
+                loop {
+                    match chars.get(idx) {
+                        None => return Err(err("unterminated string".to_string(), start)),
+                        Some(q) if *q == c => break,
+                        Some('\\') => {
+                            idx += 1;
+                            s.push(match chars.get(idx) {
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some(other) => *other,
+                                None => return Err(err("unterminated string".to_string(), start)),
+                            });
+                        }
+                        Some(other) => s.push(*other),
+                    }
+                    idx += 1;
+                }
+                idx += 1;
+
+                // backticks quote field names with spaces or other special characters
+                if c == '`' { Token::Field(s) } else { Token::Str(s) }
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.get(idx + 1).is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                idx += 1;
+                while chars.get(idx).is_some_and(|n| n.is_ascii_alphanumeric() || *n == '.') {
+                    idx += 1;
+                }
+
+                let s: String = chars[start..idx].iter().collect();
+                Token::Num(s.parse().map_err(|_| err(format!("invalid number `{}`", s), start))?)
+            }
+            c if c.is_alphabetic() || matches!(c, '_' | '@' | '#') => {
+                while chars.get(idx).is_some_and(|n| {
+                    n.is_alphanumeric() || matches!(n, '_' | '@' | '#' | '.' | '-' | ':')
+                }) {
+                    idx += 1;
+                }
+
+                let s: String = chars[start..idx].iter().collect();
+
+                match WORDS.iter().find(|w| **w == s) {
+                    Some(word) => Token::Word(word),
+                    None => Token::Field(s),
+                }
+            }
+            _ => {
+                let rest: String = chars[idx..chars.len().min(idx + 2)].iter().collect();
+
+                let op = OPS
+                    .iter()
+                    .find(|op| rest.starts_with(**op))
+                    .ok_or_else(|| err(format!("unexpected character `{}`", c), start))?;
+
+                idx += op.len();
+                Token::Op(op)
+            }
+        };
+
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+/// Field of a record, paths are bound to column indices for tables.
+#[derive(Debug)]
+enum Field {
+    Path(String),
+    Column(usize),
+}
+
+#[derive(Debug)]
+enum Operand {
+    Field(Field),
+    Lit(Literal),
+}
+
+#[derive(Debug)]
+enum Literal {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+}
+
+#[derive(Debug)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp(Operand, &'static str, Operand),
+    Contains(Operand, Operand),
+    Regex(Operand, Regex, bool),
+    IsNull(Operand, bool),
+    Truthy(Operand),
+}
+
+/// Recursive descent parser for:
+///
+/// ```text
+/// or      = and ("or" and)*
+/// and     = unary ("and" unary)*
+/// unary   = "not" unary | "(" or ")" | operand [op operand | "contains" operand | "is" ["not"] "null"]
+/// ```
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        let col = self.tokens.get(self.pos).map_or(self.len, |(_, c)| *c);
+        Error::new(EK::InvalidInput, format!("{} at column {}", msg, col + 1))
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut left = self.and()?;
+
+        while self.eat(&Token::Word("or")) {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut left = self.unary()?;
+
+        while self.eat(&Token::Word("and")) {
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        if self.eat(&Token::Word("not")) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+
+        if self.eat(&Token::Open) {
+            let expr = self.or()?;
+
+            if !self.eat(&Token::Close) {
+                return Err(self.error("expected `)`"));
+            }
+
+            return Ok(expr);
+        }
+
+        let left = self.operand()?;
+
+        match self.peek().cloned() {
+            Some(Token::Op(op @ ("=~" | "!~"))) => {
+                self.pos += 1;
+
+                let Some(Token::Str(pattern)) = self.peek().cloned() else {
+                    return Err(self.error("expected a regex string"));
+                };
+
+                let regex = Regex::new(&pattern)
+                    .map_err(|e| self.error(&format!("invalid regex ({})", e)))?;
+                self.pos += 1;
+
+                Ok(Expr::Regex(left, regex, op == "!~"))
+            }
+            Some(Token::Op(op)) => {
+                self.pos += 1;
+                let op = if op == "=" { "==" } else { op };
+
+                Ok(Expr::Cmp(left, op, self.operand()?))
+            }
+            Some(Token::Word("contains")) => {
+                self.pos += 1;
+                Ok(Expr::Contains(left, self.operand()?))
+            }
+            Some(Token::Word("is")) => {
+                self.pos += 1;
+                let negated = self.eat(&Token::Word("not"));
+
+                if !self.eat(&Token::Word("null")) {
+                    return Err(self.error("expected `null`"));
+                }
+
+                Ok(Expr::IsNull(left, negated))
+            }
+            _ => Ok(Expr::Truthy(left)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, Error> {
+        let operand = match self.peek() {
+            Some(Token::Field(path)) => Operand::Field(Field::Path(path.clone())),
+            Some(Token::Str(s)) => Operand::Lit(Literal::Str(s.clone())),
+            Some(Token::Num(n)) => Operand::Lit(Literal::Num(*n)),
+            Some(Token::Word("null")) => Operand::Lit(Literal::Null),
+            Some(Token::Word("true")) => Operand::Lit(Literal::Bool(true)),
+            Some(Token::Word("false")) => Operand::Lit(Literal::Bool(false)),
+            Some(_) => return Err(self.error("expected a field or a value")),
+            None => return Err(self.error("unexpected end of expression")),
+        };
+
+        self.pos += 1;
+
+        Ok(operand)
+    }
+}
+
+/// Value of an operand while a record is evaluated.
+#[derive(Debug)]
+enum Val<'a> {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(Cow<'a, str>),
+    List(Vec<Val<'a>>),
+    Table,
+}
+
+impl<'a> Val<'a> {
+    fn of_json(value: &'a serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Val::Null,
+            serde_json::Value::Bool(b) => Val::Bool(*b),
+            serde_json::Value::Number(n) => n.as_f64().map_or(Val::Null, Val::Num),
+            serde_json::Value::String(s) => Val::Str(Cow::Borrowed(s)),
+            serde_json::Value::Array(arr) => Val::List(arr.iter().map(Val::of_json).collect()),
+            serde_json::Value::Object(_) => Val::Table,
+        }
+    }
+
+    fn of_toml(value: &'a toml::Value) -> Self {
+        match value {
+            toml::Value::Boolean(b) => Val::Bool(*b),
+            toml::Value::Integer(i) => Val::Num(*i as f64),
+            toml::Value::Float(f) => Val::Num(*f),
+            toml::Value::String(s) => Val::Str(Cow::Borrowed(s)),
+            toml::Value::Datetime(d) => Val::Str(Cow::Owned(d.to_string())),
+            toml::Value::Array(arr) => Val::List(arr.iter().map(Val::of_toml).collect()),
+            toml::Value::Table(_) => Val::Table,
+        }
+    }
+
+    /// Table fields are untyped, empty fields and `null` are null like in the writers.
+    fn of_field(field: &'a [u8]) -> Self {
+        match field {
+            b"" | b"null" => Val::Null,
+            b"true" => Val::Bool(true),
+            b"false" => Val::Bool(false),
+            _ => Val::Str(String::from_utf8_lossy(field)),
+        }
+    }
+
+    fn of_literal(lit: &'a Literal) -> Self {
+        match lit {
+            Literal::Null => Val::Null,
+            Literal::Bool(b) => Val::Bool(*b),
+            Literal::Num(n) => Val::Num(*n),
+            Literal::Str(s) => Val::Str(Cow::Borrowed(s)),
+        }
+    }
+
+    /// Orders two values, strings are compared as numbers against numbers and values of
+    /// different types are unordered.
+    fn compare(&self, other: &Val) -> Option<Ordering> {
+        match (self, other) {
+            (Val::Num(a), Val::Num(b)) => a.partial_cmp(b),
+            (Val::Num(a), Val::Str(b)) => a.partial_cmp(&b.trim().parse::<f64>().ok()?),
+            (Val::Str(a), Val::Num(b)) => a.trim().parse::<f64>().ok()?.partial_cmp(b),
+            (Val::Str(a), Val::Str(b)) => Some(a.cmp(b)),
+            (Val::Bool(a), Val::Bool(b)) => Some(a.cmp(b)),
+            (Val::Null, Val::Null) => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+
+    fn text(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Val::Str(s) => Some(Cow::Borrowed(s)),
+            Val::Num(n) => Some(Cow::Owned(n.to_string())),
+            Val::Bool(b) => Some(Cow::Owned(b.to_string())),
+            _ => None,
+        }
+    }
+}
+
+impl Expr {
+    /// Binds the fields to the columns of a table, fields are named by their whole header (e.g.
+    /// `address.city`) and unknown columns are an error.
+    fn bind(&mut self, headers: &[String]) -> Result<(), Error> {
+        let bind_operand = |operand: &mut Operand| -> Result<(), Error> {
+            let Operand::Field(field) = operand else { return Ok(()) };
+            let Field::Path(path) = field else { return Ok(()) };
+
+            let idx = headers.iter().position(|h| h == path).ok_or_else(|| {
+                Error::new(
+                    EK::InvalidInput,
+                    format!("Column: {} doesn't exist in the header row", path),
+                )
+            })?;
+
+            *field = Field::Column(idx);
+
+            Ok(())
+        };
+
+        match self {
+            Expr::Or(a, b) | Expr::And(a, b) => {
+                a.bind(headers)?;
+                b.bind(headers)
+            }
+            Expr::Not(a) => a.bind(headers),
+            Expr::Cmp(a, _, b) | Expr::Contains(a, b) => {
+                bind_operand(a)?;
+                bind_operand(b)
+            }
+            Expr::Regex(a, ..) | Expr::IsNull(a, _) | Expr::Truthy(a) => bind_operand(a),
+        }
+    }
+
+    fn eval(&self, record: &DataTypes) -> bool {
+        match self {
+            Expr::Or(a, b) => a.eval(record) || b.eval(record),
+            Expr::And(a, b) => a.eval(record) && b.eval(record),
+            Expr::Not(a) => !a.eval(record),
+            Expr::Cmp(a, op, b) => {
+                let ord = lookup(a, record).compare(&lookup(b, record));
+
+                match *op {
+                    "==" => ord == Some(Ordering::Equal),
+                    "!=" => ord != Some(Ordering::Equal),
+                    "<" => ord == Some(Ordering::Less),
+                    "<=" => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+                    ">" => ord == Some(Ordering::Greater),
+                    ">=" => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+                    _ => false,
+                }
+            }
+            Expr::Contains(a, b) => {
+                let needle = lookup(b, record);
+
+                match lookup(a, record) {
+                    Val::List(items) => {
+                        items.iter().any(|v| v.compare(&needle) == Some(Ordering::Equal))
+                    }
+                    haystack => match (haystack.text(), needle.text()) {
+                        (Some(h), Some(n)) => h.contains(n.as_ref()),
+                        _ => false,
+                    },
+                }
+            }
+            Expr::Regex(a, regex, negated) => {
+                lookup(a, record).text().is_some_and(|t| regex.is_match(&t)) != *negated
+            }
+            Expr::IsNull(a, negated) => matches!(lookup(a, record), Val::Null) != *negated,
+            Expr::Truthy(a) => !matches!(lookup(a, record), Val::Null | Val::Bool(false)),
+        }
+    }
+}
+
+/// Gets the value of an operand in `record`, missing fields are null.
+fn lookup<'a>(operand: &'a Operand, record: &'a DataTypes) -> Val<'a> {
+    let field = match operand {
+        Operand::Lit(lit) => return Val::of_literal(lit),
+        Operand::Field(field) => field,
+    };
+
+    match (field, record) {
+        (Field::Column(idx), DataTypes::Csv(row)) => row.get(*idx).map_or(Val::Null, Val::of_field),
+        (Field::Path(path), DataTypes::Json(value)) => path
+            .split('.')
+            .try_fold(value, |node, key| match node {
+                serde_json::Value::Object(map) => map.get(key),
+                serde_json::Value::Array(arr) => key.parse::<usize>().ok().and_then(|i| arr.get(i)),
+                _ => None,
+            })
+            .map_or(Val::Null, Val::of_json),
+        (Field::Path(path), DataTypes::Toml(value)) => path
+            .split('.')
+            .try_fold(value, |node, key| match node {
+                toml::Value::Table(map) => map.get(key),
+                toml::Value::Array(arr) => key.parse::<usize>().ok().and_then(|i| arr.get(i)),
+                _ => None,
+            })
+            .map_or(Val::Null, Val::of_toml),
+        _ => Val::Null,
+    }
+}
+
+fn parse(src: &str) -> Result<Expr, Error> {
+    let mut parser = Parser { tokens: tokenize(src)?, pos: 0, len: src.chars().count() };
+    let expr = parser.or()?;
+
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.error("unexpected token"));
+    }
+
+    Ok(expr)
+}
+
+/// Keeps `record` if it matches `expr`, arrays of records (e.g. YAML sequences) keep their
+/// matching elements.
+fn keep(expr: &Expr, record: DataTypes) -> Option<DataTypes> {
+    match record {
+        DataTypes::Json(serde_json::Value::Array(arr)) => {
+            Some(DataTypes::Json(serde_json::Value::Array(
+                arr.into_iter()
+                    .map(DataTypes::Json)
+                    .filter(|el| expr.eval(el))
+                    .filter_map(|el| el.into_json().ok())
+                    .collect(),
+            )))
+        }
+        record => expr.eval(&record).then_some(record),
+    }
+}
+
+/// Drops every record that doesn't match the `Options::filter` expression before it reaches the
+/// writers, invalid expressions and unknown table columns are an error.
+pub(crate) fn filter<I>(
+    data: WriterStreams<I>,
+    options: &Options,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error>
+where
+    I: Iterator<Item = CtxResult<DataTypes, Error>>,
+{
+    let mut expr = match &options.filter {
+        Some(src) => {
+            Some(parse(src).with_context(|| format!("Invalid `--where` expression: {}", src))?)
+        }
+        None => None,
+    };
+
+    if let (Some(expr), WriterStreams::Table { headers, .. }) = (expr.as_mut(), &data) {
+        expr.bind(headers).context("Failed to bind `--where` fields to the header row")?;
+    }
+
+    Ok(data.map_iter(move |iter| {
+        iter.filter_map(move |rec| match (&expr, rec) {
+            (Some(expr), Ok(record)) => keep(expr, record).map(Ok),
+            (_, rec) => Some(rec),
+        })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(src: &str, value: serde_json::Value) -> bool {
+        parse(src).unwrap().eval(&DataTypes::Json(value))
+    }
+
+    #[test]
+    fn test_filter_expressions() {
+        let rec = serde_json::json!({
+            "name": "Alice",
+            "age": 30,
+            "address": {"city": "NYC"},
+            "tags": ["a", "b"],
+            "zip": null,
+        });
+
+        assert!(eval("age >= 30 and address.city == 'NYC'", rec.clone()));
+        assert!(eval("not (age > 30) or name == \"Bob\"", rec.clone()));
+        assert!(eval("name contains 'lic' and tags contains 'b'", rec.clone()));
+        assert!(eval("name =~ '^A' and name !~ 'x'", rec.clone()));
+        assert!(eval("zip is null and missing is null and age is not null", rec.clone()));
+        assert!(!eval("age < '3'", rec.clone()));
+
+        // table fields are bound to columns and compared as numbers against numbers
+        let mut expr = parse("`first name` == 'Bob' and age > 9").unwrap();
+        expr.bind(&["first name".to_string(), "age".to_string()]).unwrap();
+        assert!(expr.eval(&DataTypes::Csv(csv::ByteRecord::from(vec!["Bob", "10"]))));
+
+        let err = parse("age > 30 and (name == 'x'").unwrap_err();
+        assert_eq!(err.to_string(), "expected `)` at column 26");
+    }
+}
@@ -0,0 +1,320 @@
+use std::io::{Error, ErrorKind as EK};
+
+use clap::ValueEnum;
+use csv::ByteRecord;
+use resext::{CtxResult, ResExt};
+
+/// Types of table columns, set for a column with `Options::types` instead of inferring them from
+/// every field
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColumnType {
+    /// Infer the type of every field
+    Auto,
+
+    /// Keep fields as strings
+    String,
+
+    /// 64-bit integers, or integer strings for bigger ones
+    Int,
+
+    /// Floats
+    Float,
+
+    /// `true` / `false` (or `1` / `0`)
+    Bool,
+
+    /// ISO dates (e.g. `2024-05-01`)
+    Date,
+
+    /// ISO datetimes (e.g. `2024-05-01T12:30:00Z`)
+    Datetime,
+}
+
+impl ColumnType {
+    fn name(self) -> &'static str {
+        match self {
+            ColumnType::Auto => "auto",
+            ColumnType::String => "string",
+            ColumnType::Int => "int",
+            ColumnType::Float => "float",
+            ColumnType::Bool => "bool",
+            ColumnType::Date => "date",
+            ColumnType::Datetime => "datetime",
+        }
+    }
+}
+
+/// Typed value of a table field.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Cell<'a> {
+    Null,
+    Bool(bool),
+    Int(i64),
+
+    /// Integer that doesn't fit into 64 bits, written as a number where the format allows it.
+    BigInt(&'a str),
+
+    Float(f64),
+    Datetime(toml::value::Datetime),
+    Str(&'a [u8]),
+
+    /// Fields past the header row collected by `--ragged extra-column`.
+    List(Vec<String>),
+}
+
+impl Cell<'_> {
+    /// Converts the field into a JSON value, integers that don't fit into 64 bits become floats.
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        match self {
+            Cell::Null => serde_json::Value::Null,
+            Cell::Bool(b) => serde_json::Value::Bool(*b),
+            Cell::Int(i) => serde_json::Value::from(*i),
+            Cell::BigInt(s) => serde_json::from_str(s).unwrap_or(serde_json::Value::Null),
+            Cell::Float(f) => serde_json::Value::from(*f),
+            Cell::Datetime(dt) => serde_json::Value::String(dt.to_string()),
+            Cell::Str(s) => serde_json::Value::String(String::from_utf8_lossy(s).into_owned()),
+            Cell::List(l) => serde_json::Value::from(l.clone()),
+        }
+    }
+
+    /// Writes the field as a JSON value into `buf`.
+    pub(crate) fn write_json(&self, buf: &mut Vec<u8>) {
+        match self {
+            Cell::Null => buf.extend_from_slice(b"null"),
+            Cell::Bool(b) => buf.extend_from_slice(if *b { b"true" } else { b"false" }),
+            Cell::Int(i) => buf.extend_from_slice(i.to_string().as_bytes()),
+            Cell::BigInt(s) => buf.extend_from_slice(s.as_bytes()),
+            // `Debug` keeps the fraction of whole floats (`1.0` instead of `1`)
+            Cell::Float(f) => buf.extend_from_slice(format!("{:?}", f).as_bytes()),
+            Cell::Datetime(dt) => buf.extend_from_slice(format!("\"{}\"", dt).as_bytes()),
+            Cell::Str(s) => {
+                buf.push(b'"');
+                s.iter().for_each(|byte| crate::utils::escape(*byte, buf));
+                buf.push(b'"');
+            }
+            Cell::List(l) => buf.extend_from_slice(serde_json::to_string(l).unwrap().as_bytes()),
+        }
+    }
+}
+
+/// Checks the JSON number grammar (no leading zeros, `+` or bare `.`) so numbers like ZIP codes
+/// stay strings, returns whether the number is an integer.
+fn number_kind(s: &[u8]) -> Option<bool> {
+    let digits =
+        |s: &[u8], from: usize| s[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+    let mut idx = usize::from(s.first() == Some(&b'-'));
+
+    match digits(s, idx) {
+        0 => return None,
+        n if n > 1 && s[idx] == b'0' => return None,
+        n => idx += n,
+    }
+
+    let mut int = true;
+
+    if s.get(idx) == Some(&b'.') {
+        match digits(s, idx + 1) {
+            0 => return None,
+            n => idx += n + 1,
+        }
+        int = false;
+    }
+
+    if matches!(s.get(idx), Some(b'e' | b'E')) {
+        idx += 1;
+        if matches!(s.get(idx), Some(b'+' | b'-')) {
+            idx += 1;
+        }
+
+        match digits(s, idx) {
+            0 => return None,
+            n => idx += n,
+        }
+        int = false;
+    }
+
+    (idx == s.len()).then_some(int)
+}
+
+/// Parses ISO dates / datetimes, `date` is whether the value has to be a date without a time.
+pub(crate) fn datetime(s: &str, date: bool) -> Option<toml::value::Datetime> {
+    let b = s.as_bytes();
+
+    // cheap check before parsing so most strings never get parsed
+    if b.len() < 10 || b[4] != b'-' || b[7] != b'-' || !b[..4].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    let dt: toml::value::Datetime = s.parse().ok()?;
+
+    (dt.date.is_some() && (dt.time.is_none() == date)).then_some(dt)
+}
+
+/// Infers the type of a field, only `true` / `false` / `null` are typed unless `parse` is set.
+pub(crate) fn infer(field: &[u8], parse: bool) -> Cell<'_> {
+    match field {
+        b"null" => return Cell::Null,
+        b"true" => return Cell::Bool(true),
+        b"false" => return Cell::Bool(false),
+        _ if !parse => return Cell::Str(field),
+        _ => {}
+    }
+
+    let Ok(s) = std::str::from_utf8(field) else { return Cell::Str(field) };
+
+    match number_kind(field) {
+        Some(true) => s.parse().map_or(Cell::BigInt(s), Cell::Int),
+        Some(false) => match s.parse::<f64>() {
+            Ok(f) if f.is_finite() => Cell::Float(f),
+            _ => Cell::Str(field),
+        },
+        None => datetime(s, true)
+            .or_else(|| datetime(s, false))
+            .map_or(Cell::Str(field), Cell::Datetime),
+    }
+}
+
+/// Converts a field into its column's type, `None` if it isn't a value of that type.
+fn convert(field: &[u8], ty: ColumnType) -> Option<Cell<'_>> {
+    if ty == ColumnType::String {
+        return Some(Cell::Str(field));
+    }
+
+    // empty fields are missing values in typed columns
+    if matches!(field, b"" | b"null") {
+        return Some(Cell::Null);
+    }
+
+    let s = std::str::from_utf8(field).ok()?.trim();
+
+    match ty {
+        ColumnType::Int => match s.parse::<i64>() {
+            Ok(i) => Some(Cell::Int(i)),
+            Err(_) => (number_kind(s.as_bytes()) == Some(true)).then_some(Cell::BigInt(s)),
+        },
+        ColumnType::Float => s.parse::<f64>().ok().filter(|f| f.is_finite()).map(Cell::Float),
+        ColumnType::Bool => match s.to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(Cell::Bool(true)),
+            "false" | "0" => Some(Cell::Bool(false)),
+            _ => None,
+        },
+        ColumnType::Date => datetime(s, true).map(Cell::Datetime),
+        ColumnType::Datetime => datetime(s, false).map(Cell::Datetime),
+        ColumnType::Auto | ColumnType::String => Some(infer(field, true)),
+    }
+}
+
+/// Types the fields of table rows, columns without a type in `Options::types` are inferred (or
+/// kept as strings unless `Options::parse_numbers` is set).
+pub(crate) struct Inference {
+    headers: Vec<String>,
+    columns: Vec<Option<ColumnType>>,
+    parse: bool,
+
+    /// Index of the trailing `_extra` column added by `--ragged extra-column`.
+    extra: Option<usize>,
+}
+
+impl Inference {
+    pub(crate) fn new(
+        headers: &[String],
+        parse: bool,
+        types: &[(String, ColumnType)],
+    ) -> CtxResult<Self, Error> {
+        let mut columns = vec![None; headers.len()];
+
+        for (name, ty) in types {
+            let idx = headers
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| {
+                    Error::new(
+                        EK::InvalidInput,
+                        format!("Column: {} doesn't exist in the header row", name),
+                    )
+                })
+                .context("Failed to set column types")?;
+
+            columns[idx] = Some(*ty);
+        }
+
+        let extra = headers
+            .last()
+            .filter(|h| *h == "_extra")
+            .map(|_| headers.len() - 1)
+            .filter(|idx| columns[*idx].is_none());
+
+        Ok(Inference { headers: headers.to_vec(), columns, parse, extra })
+    }
+
+    /// Types every field of a row, a field that isn't a value of its column's type fails the
+    /// whole row.
+    pub(crate) fn row<'a>(&self, record: &'a ByteRecord) -> Result<Vec<Cell<'a>>, Error> {
+        record
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| match self.columns.get(idx).copied().flatten() {
+                None if Some(idx) == self.extra => Ok(serde_json::from_slice(field)
+                    .map_or_else(|_| infer(field, self.parse), Cell::List)),
+                None => Ok(infer(field, self.parse)),
+                Some(ty) => convert(field, ty).ok_or_else(|| {
+                    Error::new(
+                        EK::InvalidData,
+                        format!(
+                            "Field: {} = `{}` is not a valid {}",
+                            self.headers.get(idx).map_or("", String::as_str),
+                            String::from_utf8_lossy(field),
+                            ty.name()
+                        ),
+                    )
+                }),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_fields() {
+        let dt = |s: &str| Cell::Datetime(s.parse().unwrap());
+
+        assert_eq!(infer(b"42", true), Cell::Int(42));
+        assert_eq!(infer(b"-1.5e3", true), Cell::Float(-1500.0));
+        assert_eq!(infer(b"12345678901234567890", true), Cell::BigInt("12345678901234567890"));
+        assert_eq!(infer(b"02134", true), Cell::Str(b"02134"));
+        assert_eq!(infer(b"+5", true), Cell::Str(b"+5"));
+        assert_eq!(infer(b"NaN", true), Cell::Str(b"NaN"));
+        assert_eq!(infer(b"2024-05-01", true), dt("2024-05-01"));
+        assert_eq!(infer(b"2024-05-01T12:30:00Z", true), dt("2024-05-01T12:30:00Z"));
+        assert_eq!(infer(b"2024-13-01", true), Cell::Str(b"2024-13-01"));
+        assert_eq!(infer(b"42", false), Cell::Str(b"42"));
+        assert_eq!(infer(b"null", false), Cell::Null);
+
+        let headers = ["zip".to_string(), "id".to_string()];
+        let types = [("zip".to_string(), ColumnType::String), ("id".to_string(), ColumnType::Int)];
+        let inference = Inference::new(&headers, true, &types).unwrap();
+
+        let row = ByteRecord::from(vec!["02134", "007"]);
+        assert_eq!(inference.row(&row).unwrap(), [Cell::Str(b"02134"), Cell::Int(7)]);
+
+        let row = ByteRecord::from(vec!["1", "x"]);
+        assert_eq!(
+            inference.row(&row).unwrap_err().to_string(),
+            "Field: id = `x` is not a valid int"
+        );
+
+        let headers = ["a".to_string(), "_extra".to_string()];
+        let inference = Inference::new(&headers, true, &[]).unwrap();
+
+        let row = ByteRecord::from(vec!["1", r#"["6","7"]"#]);
+        let list = Cell::List(vec!["6".to_string(), "7".to_string()]);
+        assert_eq!(inference.row(&row).unwrap(), [Cell::Int(1), list]);
+
+        let row = ByteRecord::from(vec!["1", "[]"]);
+        assert_eq!(inference.row(&row).unwrap(), [Cell::Int(1), Cell::List(Vec::new())]);
+    }
+}
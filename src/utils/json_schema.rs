@@ -0,0 +1,385 @@
+use std::io::{Error, ErrorKind as EK};
+use std::path::Path;
+
+use regex::Regex;
+use resext::{CtxResult, ResExt};
+use serde_json::{Map, Value};
+
+/// Compiled JSON Schema, only the core keywords of draft 2020-12 that constrain single values are
+/// checked (`type`, `enum`, `const`, `required`, `properties`, `additionalProperties`, `items`,
+/// `pattern` and the min / max keywords). Other keywords (e.g. `format`) are ignored.
+#[derive(Debug)]
+pub(crate) enum Schema {
+    Bool(bool),
+    Keywords(Box<Keywords>),
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Keywords {
+    types: Option<Vec<String>>,
+    enumeration: Option<Vec<Value>>,
+    constant: Option<Value>,
+
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    pattern: Option<Regex>,
+
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    items: Option<Schema>,
+
+    required: Vec<String>,
+    properties: Vec<(String, Schema)>,
+    additional_properties: Option<Schema>,
+}
+
+/// Numeric bound with the check a number breaks it with and the message for it.
+type Bound = (Option<f64>, fn(f64, f64) -> bool, &'static str);
+
+const TYPES: [&str; 7] = ["null", "boolean", "object", "array", "number", "string", "integer"];
+
+/// Appends a key to a JSON pointer, escaping `~` and `/`.
+fn push_pointer(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn invalid(pointer: &str, msg: impl std::fmt::Display) -> Error {
+    let at = if pointer.is_empty() { "the root".to_string() } else { format!("`{}`", pointer) };
+    Error::new(EK::InvalidInput, format!("Invalid JSON Schema at {}: {}", at, msg))
+}
+
+fn number(value: &Value, pointer: &str, key: &str) -> Result<f64, Error> {
+    value.as_f64().ok_or_else(|| invalid(&push_pointer(pointer, key), "expected a number"))
+}
+
+fn count(value: &Value, pointer: &str, key: &str) -> Result<usize, Error> {
+    value
+        .as_u64()
+        .map(|n| n as usize)
+        .ok_or_else(|| invalid(&push_pointer(pointer, key), "expected a non-negative integer"))
+}
+
+impl Schema {
+    /// Compiles a schema, `pointer` is the location of `value` in the schema document.
+    pub(crate) fn compile(value: &Value, pointer: &str) -> Result<Self, Error> {
+        let map: &Map<String, Value> = match value {
+            Value::Bool(b) => return Ok(Schema::Bool(*b)),
+            Value::Object(map) => map,
+            _ => return Err(invalid(pointer, "schemas have to be objects or booleans")),
+        };
+
+        let mut kw = Keywords::default();
+
+        for (key, value) in map {
+            let at = push_pointer(pointer, key);
+
+            match key.as_str() {
+                "type" => {
+                    let types: Vec<String> = match value {
+                        Value::String(ty) => vec![ty.clone()],
+                        Value::Array(arr) => arr
+                            .iter()
+                            .map(|ty| ty.as_str().map(str::to_string))
+                            .collect::<Option<_>>()
+                            .ok_or_else(|| invalid(&at, "expected type names"))?,
+                        _ => return Err(invalid(&at, "expected a type name or an array of them")),
+                    };
+
+                    if let Some(ty) = types.iter().find(|ty| !TYPES.contains(&ty.as_str())) {
+                        return Err(invalid(&at, format!("unknown type `{}`", ty)));
+                    }
+
+                    kw.types = Some(types);
+                }
+                "enum" => {
+                    let values =
+                        value.as_array().ok_or_else(|| invalid(&at, "expected an array"))?;
+                    kw.enumeration = Some(values.clone());
+                }
+                "const" => kw.constant = Some(value.clone()),
+                "minimum" => kw.minimum = Some(number(value, pointer, key)?),
+                "maximum" => kw.maximum = Some(number(value, pointer, key)?),
+                "exclusiveMinimum" => kw.exclusive_minimum = Some(number(value, pointer, key)?),
+                "exclusiveMaximum" => kw.exclusive_maximum = Some(number(value, pointer, key)?),
+                "minLength" => kw.min_length = Some(count(value, pointer, key)?),
+                "maxLength" => kw.max_length = Some(count(value, pointer, key)?),
+                "minItems" => kw.min_items = Some(count(value, pointer, key)?),
+                "maxItems" => kw.max_items = Some(count(value, pointer, key)?),
+                "pattern" => {
+                    let pattern =
+                        value.as_str().ok_or_else(|| invalid(&at, "expected a string"))?;
+                    kw.pattern = Some(Regex::new(pattern).map_err(|e| invalid(&at, e))?);
+                }
+                "items" => kw.items = Some(Schema::compile(value, &at)?),
+                "required" => {
+                    kw.required = value
+                        .as_array()
+                        .and_then(|arr| {
+                            arr.iter().map(|k| k.as_str().map(str::to_string)).collect()
+                        })
+                        .ok_or_else(|| invalid(&at, "expected an array of property names"))?;
+                }
+                "properties" => {
+                    let props =
+                        value.as_object().ok_or_else(|| invalid(&at, "expected an object"))?;
+
+                    kw.properties = props
+                        .iter()
+                        .map(|(k, v)| Ok((k.clone(), Schema::compile(v, &push_pointer(&at, k))?)))
+                        .collect::<Result<_, Error>>()?;
+                }
+                "additionalProperties" => {
+                    kw.additional_properties = Some(Schema::compile(value, &at)?);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Schema::Keywords(Box::new(kw)))
+    }
+
+    /// Schema of the property `key` of objects, `None` if any value is allowed.
+    pub(crate) fn property(&self, key: &str) -> Option<&Schema> {
+        let Schema::Keywords(kw) = self else { return None };
+
+        kw.properties
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, schema)| schema)
+            .or(kw.additional_properties.as_ref())
+    }
+
+    /// Whether the schema's `type` allows the type of `value`.
+    pub(crate) fn allows_type(&self, value: &Value) -> bool {
+        match self {
+            Schema::Bool(b) => *b,
+            Schema::Keywords(kw) => {
+                kw.types.as_ref().is_none_or(|types| types.iter().any(|ty| is_type(value, ty)))
+            }
+        }
+    }
+
+    /// Checks `value` (at `pointer` in the record) and adds a `(pointer, message)` pair for every
+    /// violation to `out`.
+    pub(crate) fn check(&self, value: &Value, pointer: &str, out: &mut Vec<(String, String)>) {
+        let kw = match self {
+            Schema::Bool(true) => return,
+            Schema::Bool(false) => {
+                out.push((pointer.to_string(), "no value is allowed here".to_string()));
+                return;
+            }
+            Schema::Keywords(kw) => kw,
+        };
+
+        let mut fail = |msg: String| out.push((pointer.to_string(), msg));
+
+        if let Some(types) = &kw.types
+            && !types.iter().any(|ty| is_type(value, ty))
+        {
+            fail(format!("expected {}, found {}", types.join(" or "), type_of(value)));
+        }
+
+        if let Some(values) = &kw.enumeration
+            && !values.iter().any(|allowed| equal(allowed, value))
+        {
+            fail(format!("{} isn't one of the allowed values", short(value)));
+        }
+
+        if let Some(constant) = &kw.constant
+            && !equal(constant, value)
+        {
+            fail(format!("expected {}, found {}", short(constant), short(value)));
+        }
+
+        if let Some(n) = value.as_f64() {
+            let bounds: [Bound; 4] = [
+                (kw.minimum, |n, b| n < b, "less than the minimum"),
+                (kw.maximum, |n, b| n > b, "greater than the maximum"),
+                (kw.exclusive_minimum, |n, b| n <= b, "not greater than the exclusive minimum"),
+                (kw.exclusive_maximum, |n, b| n >= b, "not less than the exclusive maximum"),
+            ];
+
+            for (bound, broken, msg) in bounds {
+                if let Some(bound) = bound
+                    && broken(n, bound)
+                {
+                    fail(format!("{} is {} {}", short(value), msg, bound));
+                }
+            }
+        }
+
+        if let Value::String(s) = value {
+            let len = s.chars().count();
+
+            if kw.min_length.is_some_and(|min| len < min)
+                || kw.max_length.is_some_and(|max| len > max)
+            {
+                fail(format!(
+                    "length {} isn't within {}..={}",
+                    len,
+                    kw.min_length.unwrap_or(0),
+                    kw.max_length.map_or(String::new(), |m| m.to_string())
+                ));
+            }
+
+            if let Some(pattern) = &kw.pattern
+                && !pattern.is_match(s)
+            {
+                fail(format!("{} doesn't match the pattern `{}`", short(value), pattern));
+            }
+        }
+
+        if let Value::Array(arr) = value {
+            if kw.min_items.is_some_and(|min| arr.len() < min)
+                || kw.max_items.is_some_and(|max| arr.len() > max)
+            {
+                fail(format!(
+                    "{} items aren't within {}..={}",
+                    arr.len(),
+                    kw.min_items.unwrap_or(0),
+                    kw.max_items.map_or(String::new(), |m| m.to_string())
+                ));
+            }
+
+            if let Some(items) = &kw.items {
+                for (idx, el) in arr.iter().enumerate() {
+                    items.check(el, &push_pointer(pointer, &idx.to_string()), out);
+                }
+            }
+        }
+
+        if let Value::Object(map) = value {
+            for key in kw.required.iter().filter(|k| !map.contains_key(*k)) {
+                out.push((pointer.to_string(), format!("missing required property `{}`", key)));
+            }
+
+            for (key, value) in map {
+                let at = push_pointer(pointer, key);
+
+                match kw.properties.iter().find(|(k, _)| k == key) {
+                    Some((_, schema)) => schema.check(value, &at, out),
+                    None => {
+                        if let Some(schema) = &kw.additional_properties {
+                            match schema {
+                                Schema::Bool(false) => out.push((
+                                    pointer.to_string(),
+                                    format!("additional property `{}` isn't allowed", key),
+                                )),
+                                schema => schema.check(value, &at, out),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        ty => type_of(value) == ty || (ty == "number" && value.is_number()),
+    }
+}
+
+fn type_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// JSON Schema equality, numbers are equal by value (e.g. `1` and `1.0`).
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x == y || x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| equal(x, y))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).is_some_and(|w| equal(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Value for messages, long values are cut.
+fn short(value: &Value) -> String {
+    let s = value.to_string();
+
+    if s.chars().count() > 40 { s.chars().take(39).chain(['…']).collect() } else { s }
+}
+
+/// Reads and compiles the JSON Schema at `path`.
+pub(crate) fn load_schema(path: &Path) -> CtxResult<Schema, Error> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read JSON Schema: {}", path.to_string_lossy()))?;
+
+    let value: Value =
+        serde_json::from_slice(&bytes)
+            .map_err(|e| Error::new(EK::InvalidData, e))
+            .with_context(|| format!("JSON Schema: {} isn't valid JSON", path.to_string_lossy()))?;
+
+    Schema::compile(&value, "")
+        .with_context(|| format!("Failed to compile JSON Schema: {}", path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn violations(schema: Value, value: Value) -> Vec<(String, String)> {
+        let mut out = Vec::new();
+        Schema::compile(&schema, "").unwrap().check(&value, "", &mut out);
+        out
+    }
+
+    #[test]
+    fn test_check_keywords() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "email"],
+            "properties": {
+                "id": {"type": "integer", "minimum": 1},
+                "email": {"type": "string"},
+                "zip": {"type": "string", "pattern": "^\\d{5}$"},
+                "tags": {"type": "array", "maxItems": 2, "items": {"enum": ["a", "b"]}},
+                "a/b": {"const": true},
+            },
+            "additionalProperties": false,
+        });
+
+        let valid = json!({"id": 1, "email": "x", "zip": "02134", "tags": ["a"], "a/b": true});
+        assert!(violations(schema.clone(), valid).is_empty());
+
+        let invalid = json!({"id": 0.5, "zip": "1234", "tags": ["a", "c", "b"], "a/b": 1, "x": 1});
+        let pointers: Vec<String> =
+            violations(schema, invalid).into_iter().map(|(p, _)| p).collect();
+
+        assert_eq!(pointers, ["", "/id", "/id", "/zip", "/tags", "/tags/1", "/a~1b", ""]);
+
+        assert_eq!(
+            violations(json!({"type": ["string", "null"]}), json!(1)),
+            [("".to_string(), "expected string or null, found integer".to_string())]
+        );
+
+        // numbers are compared by value
+        assert!(violations(json!({"enum": [1, "a"]}), json!(1.0)).is_empty());
+        assert!(violations(json!({"const": {"n": [2.0]}}), json!({"n": [2]})).is_empty());
+        assert_eq!(violations(json!({"const": 1}), json!(1.5)).len(), 1);
+
+        assert!(Schema::compile(&json!({"type": "text"}), "").is_err());
+        assert!(Schema::compile(&json!({"properties": {"a": {"pattern": "("}}}), "").is_err());
+    }
+}
@@ -1,67 +1,483 @@
 use std::{
     fs::{File, OpenOptions},
-    io::{BufWriter, Error, Write},
-    sync::{LazyLock, Mutex},
+    io::{BufWriter, Error, ErrorKind as EK, Write},
+    path::Path,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
 };
 
+use clap::ValueEnum;
 use owo_colors::OwoColorize;
 use resext::{CtxResult, ErrCtx, ResExt};
+use serde_json::json;
 
-static LOGGER: LazyLock<Option<Mutex<BufWriter<File>>>> = LazyLock::new(|| {
-    let args = &*crate::ARGS;
+use crate::utils::RecordError;
 
-    args.log_file.as_ref().map(|path| {
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(path)
-            .better_expect("Failed to open error logging file", 1, true);
+static LOGGER: OnceLock<Mutex<BufWriter<File>>> = OnceLock::new();
 
-        Mutex::new(BufWriter::with_capacity(256, file))
-    })
-});
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+/// Name of the input file for structured error events.
+static INPUT: OnceLock<String> = OnceLock::new();
+
+/// Number of errors logged so far, reported in the summary event of structured logs.
+static ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set once the opening bracket of a `LogFormat::Json` log was written.
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Number of skipped records logged so far, reported in the summary of a failed command.
+static SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Formats for error logs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable text, colored when written to stderr (default for stderr)
+    Text,
+
+    /// Markdown blocks separated by rules (default for log files)
+    Markdown,
+
+    /// A JSON array of events, closed by the final summary event
+    Json,
+
+    /// One JSON event per line
+    Ndjson,
+}
+
+/// Final status of a command, written as the last event of structured logs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Summary {
+    /// Whether the command succeeded.
+    pub success: bool,
+
+    /// Number of invalid records that were skipped.
+    pub skipped: usize,
+
+    /// Time the command took.
+    pub elapsed: Duration,
+}
+
+/// Location of an error in the input, not every error knows every part of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Position {
+    /// 1-based index of the record (e.g. NDJSON line or CSV row after the headers).
+    pub(crate) record: Option<usize>,
+
+    /// 1-based line.
+    pub(crate) line: Option<usize>,
+
+    /// 1-based column.
+    pub(crate) column: Option<usize>,
+
+    /// 0-based byte offset.
+    pub(crate) offset: Option<u64>,
+}
+
+/// Line of the input an error happened in, shown under the error as a code frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Snippet {
+    /// Bytes of the line without its newline, only the end of very long lines is kept.
+    pub(crate) line: Vec<u8>,
+
+    /// 1-based column of the first byte in `line`.
+    pub(crate) start: usize,
+}
+
+impl Default for Snippet {
+    fn default() -> Self {
+        Snippet { line: Vec::new(), start: 1 }
+    }
+}
+
+impl Snippet {
+    /// Gets the position and line of the byte at `offset` in `content`.
+    pub(crate) fn locate(content: &[u8], offset: usize) -> (Position, Snippet) {
+        let offset = offset.min(content.len());
+        let line_start = content[..offset].iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+        let line_end = content[offset..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(content.len(), |i| offset + i);
+
+        let pos = Position {
+            line: Some(content[..line_start].iter().filter(|b| **b == b'\n').count() + 1),
+            column: Some(offset - line_start + 1),
+            offset: Some(offset as u64),
+            ..Position::default()
+        };
+
+        (pos, Snippet { line: content[line_start..line_end].to_vec(), start: 1 })
+    }
+
+    /// Renders the line with a caret under `column`, long lines are cut around the column.
+    pub(crate) fn frame(&self, line: Option<usize>, column: usize) -> Option<String> {
+        let idx = column.checked_sub(self.start)?;
+
+        let mut text = self.line.as_slice();
+        if text.ends_with(b"\r") {
+            text = &text[..text.len() - 1];
+        }
+
+        // keep at most 60 bytes before and 20 bytes after the error
+        let from = idx.saturating_sub(60);
+        let to = text.len().min(idx + 20).max(from);
+        let text = text.get(from..to)?;
+
+        let cut = from > 0 || self.start > 1;
+        let before = String::from_utf8_lossy(&text[..(idx - from).min(text.len())]).chars().count();
+        let text = String::from_utf8_lossy(text).replace('\t', " ");
+
+        let gutter = line.map(|l| l.to_string()).unwrap_or_default();
+        let pad = " ".repeat(gutter.len());
+        let (dots, shift) = if cut { ("...", 3) } else { ("", 0) };
+
+        Some(format!("{pad} |\n{gutter} | {dots}{text}\n{pad} | {}^\n", " ".repeat(before + shift)))
+    }
+}
+
+/// Gets the code frame of an error whose source kept the line it happened in.
+fn frame_of(source: &(dyn std::error::Error + 'static)) -> Option<String> {
+    source
+        .downcast_ref::<Error>()
+        .and_then(|e| e.get_ref())
+        .and_then(|inner| inner.downcast_ref::<RecordError>())
+        .and_then(RecordError::frame)
+}
+
+/// Sets a file (preferably Markdown) to write error logs to instead of printing them to stderr.
+///
+/// The log file can only be set once, later calls return an error.
+pub fn set_log_file(path: &Path) -> CtxResult<(), Error> {
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .context("Failed to open error logging file")?;
+
+    LOGGER
+        .set(Mutex::new(BufWriter::with_capacity(256, file)))
+        .map_err(|_| Error::other("Log file is already set"))
+        .context("Failed to set error logging file")
+}
+
+/// Sets the format of error logs, defaults to `LogFormat::Markdown` for log files and
+/// `LogFormat::Text` for stderr.
+///
+/// The format can only be set once, later calls return an error.
+pub fn set_log_format(format: LogFormat) -> CtxResult<(), Error> {
+    FORMAT
+        .set(format)
+        .map_err(|_| Error::other("Log format is already set"))
+        .context("Failed to set log format")
+}
+
+/// Sets the name of the input file reported in structured error events.
+pub fn set_log_input(name: &str) {
+    let _ = INPUT.set(name.to_string());
+}
 
 #[inline]
-pub(crate) fn log_err<E: std::error::Error>(err: &ErrCtx<E>) -> CtxResult<(), Error> {
-    if let Some(wtr) = &*LOGGER {
+fn format() -> LogFormat {
+    match FORMAT.get() {
+        Some(format) => *format,
+        None if LOGGER.get().is_some() => LogFormat::Markdown,
+        None => LogFormat::Text,
+    }
+}
+
+/// Writes `bytes` into the log file, or stderr if there is none.
+#[inline]
+fn write_log(bytes: &[u8], flush: bool) -> CtxResult<(), Error> {
+    if let Some(wtr) = LOGGER.get() {
         let mut wtr = wtr
             .lock()
             .map_err(|_| Error::other("Failed to lock"))
-            .context("Failed to lock log file")?;
+            .context("Failed to lock logger")?;
 
-        writeln!(
-            wtr,
-            "{}\nHint: Try to use `fiux validate <INPUT>` for more information\n\n---\n",
-            err
-        )
-        .context("Failed to write error to log")?;
+        wtr.write_all(bytes).context("Failed to write into log file")?;
+
+        if flush {
+            wtr.flush().context("Failed to flush logger")?;
+        }
     } else {
-        eprintln!(
-            "{}\n{} Try to use {} for more information\n\n{}\n",
+        std::io::stderr().write_all(bytes).context("Failed to write log into stderr")?;
+    }
+
+    Ok(())
+}
+
+/// Writes a structured event as a JSON array element or an NDJSON line.
+#[inline]
+fn write_event(event: &serde_json::Value, last: bool) -> CtxResult<(), Error> {
+    let mut out = Vec::new();
+
+    if format() == LogFormat::Json {
+        out.extend_from_slice(if STARTED.swap(true, Ordering::Relaxed) { b",\n" } else { b"[\n" });
+    }
+
+    serde_json::to_writer(&mut out, event)
+        .map_err(|e| Error::new(EK::InvalidData, e))
+        .context("Failed to serialize log event")?;
+
+    if format() == LogFormat::Ndjson {
+        out.push(b'\n');
+    } else if last {
+        out.extend_from_slice(b"\n]\n");
+    }
+
+    write_log(&out, last)
+}
+
+/// Gets the kind and position of an error from its source.
+fn describe(source: &(dyn std::error::Error + 'static)) -> (String, Position) {
+    if let Some(e) = source.downcast_ref::<Error>() {
+        let record = e.get_ref().and_then(|inner| inner.downcast_ref::<RecordError>());
+        let pos = record.map(|record| record.pos).unwrap_or_default();
+
+        if record.is_some_and(RecordError::is_syntax) {
+            ("Syntax".to_string(), pos)
+        } else {
+            (format!("{:?}", e.kind()), pos)
+        }
+    } else if let Some(e) = source.downcast_ref::<serde_json::Error>() {
+        let pos = Position {
+            line: (e.line() != 0).then_some(e.line()),
+            column: (e.column() != 0).then_some(e.column()),
+            ..Position::default()
+        };
+
+        (format!("{:?}", e.classify()), pos)
+    } else if let Some(e) = source.downcast_ref::<toml::de::Error>() {
+        let pos = Position { offset: e.span().map(|s| s.start as u64), ..Position::default() };
+
+        ("Syntax".to_string(), pos)
+    } else if let Some(e) = source.downcast_ref::<serde_norway::Error>() {
+        let pos = e
+            .location()
+            .map(|l| Position {
+                line: Some(l.line()),
+                column: Some(l.column()),
+                offset: Some(l.index() as u64),
+                ..Position::default()
+            })
+            .unwrap_or_default();
+
+        ("Syntax".to_string(), pos)
+    } else if let Some(e) = source.downcast_ref::<csv::Error>() {
+        let pos = e
+            .position()
+            .map(|p| Position {
+                record: Some(p.record() as usize),
+                line: Some(p.line() as usize),
+                offset: Some(p.byte()),
+                ..Position::default()
+            })
+            .unwrap_or_default();
+
+        ("Csv".to_string(), pos)
+    } else {
+        ("Other".to_string(), Position::default())
+    }
+}
+
+/// Builds the structured event for an error.
+fn error_event<E: std::error::Error + 'static>(err: &ErrCtx<E>, event: &str) -> serde_json::Value {
+    let (kind, pos) = describe(&err.source);
+    let context: Vec<String> = String::from_utf8_lossy(&err.msg)
+        .split("\n- ")
+        .filter(|ctx| !ctx.is_empty())
+        .map(strip_ansi)
+        .collect();
+
+    json!({
+        "event": event,
+        "file": INPUT.get(),
+        "record": pos.record,
+        "line": pos.line,
+        "column": pos.column,
+        "offset": pos.offset,
+        "kind": kind,
+        "message": strip_ansi(&err.source.to_string()),
+        "context": context,
+    })
+}
+
+#[inline]
+pub(crate) fn log_err<E: std::error::Error + 'static>(err: &ErrCtx<E>) -> CtxResult<(), Error> {
+    ERRORS.fetch_add(1, Ordering::Relaxed);
+
+    let frame = match format() {
+        LogFormat::Json | LogFormat::Ndjson => None,
+        _ => frame_of(&err.source),
+    };
+
+    match format() {
+        LogFormat::Json | LogFormat::Ndjson => write_event(&error_event(err, "error"), false)?,
+        LogFormat::Text if LOGGER.get().is_none() => eprintln!(
+            "{}{}\n{} Try to use {} for more information\n\n{}\n",
             err,
+            frame.map(|f| f.blue().to_string()).unwrap_or_default(),
             "Hint:".bright_green(),
             "`fiux validate <INPUT>`".yellow(),
             "---".red()
-        );
+        ),
+        LogFormat::Text => write_log(
+            format!(
+                "{}{}\nHint: Try to use `fiux validate <INPUT>` for more information\n\n---\n\n",
+                err,
+                frame.unwrap_or_default()
+            )
+            .as_bytes(),
+            false,
+        )
+        .context("Failed to write error to log")?,
+        LogFormat::Markdown => write_log(
+            format!(
+                "{}{}\nHint: Try to use `fiux validate <INPUT>` for more information\n\n---\n\n",
+                err,
+                frame.map(|f| format!("\n```text\n{}```\n", f)).unwrap_or_default()
+            )
+            .as_bytes(),
+            false,
+        )
+        .context("Failed to write error to log")?,
     }
 
     Ok(())
 }
 
+/// Logs the error of a record that is skipped, the error budget is checked by
+/// `Conversion::log_skip`.
 #[inline]
-pub(crate) fn flush_logger(msg: &str) -> CtxResult<(), Error> {
-    if let Some(wtr) = &*LOGGER {
-        let mut wtr = wtr
-            .lock()
-            .map_err(|_| Error::other("Failed to lock"))
-            .context("Failed to lock logger")?;
+pub(crate) fn log_skipped<E: std::error::Error + 'static>(err: &ErrCtx<E>) {
+    SKIPPED.fetch_add(1, Ordering::Relaxed);
+    log_err(err).unwrap_or_else(|e| eprintln!("{}\n{}", e, err));
+}
 
-        wtr.write(msg.as_bytes()).context("Failed to write status message")?;
+#[inline]
+/// Writes an informational message (e.g. the detected input format) into the log file (or
+/// stderr).
+pub fn log_info(msg: &str) -> CtxResult<(), Error> {
+    match format() {
+        LogFormat::Json | LogFormat::Ndjson => {
+            write_event(&json!({ "event": "info", "message": strip_ansi(msg) }), false)
+        }
+        _ if LOGGER.get().is_none() => {
+            eprintln!("{msg}");
+            Ok(())
+        }
+        _ => write_log(format!("{}\n\n", msg).as_bytes(), false)
+            .context("Failed to write info message"),
+    }
+}
 
-        wtr.flush().context("Failed to flush logger")?;
-    } else {
-        eprintln!("{msg}");
+/// Writes a fatal error that ended the command and its summary into the log file (or stderr)
+/// and flushes it.
+pub fn log_fatal<E: std::error::Error + 'static>(
+    err: &ErrCtx<E>,
+    elapsed: Duration,
+) -> CtxResult<(), Error> {
+    match format() {
+        LogFormat::Json | LogFormat::Ndjson => {
+            ERRORS.fetch_add(1, Ordering::Relaxed);
+            write_event(&error_event(err, "fatal"), false)?;
+            let skipped = SKIPPED.load(Ordering::Relaxed);
+            flush_logger("", &Summary { success: false, skipped, elapsed })
+        }
+        _ => {
+            eprintln!("{} {}", "FATAL:".red().bold(), err);
+            Ok(())
+        }
+    }
+}
+
+/// Writes a final status message into the log file (or stderr) and flushes it, structured logs
+/// get a summary event with the number of errors, skipped records and the elapsed time instead.
+pub fn flush_logger(msg: &str, summary: &Summary) -> CtxResult<(), Error> {
+    match format() {
+        LogFormat::Json | LogFormat::Ndjson => write_event(
+            &json!({
+                "event": "summary",
+                "file": INPUT.get(),
+                "success": summary.success,
+                "errors": ERRORS.load(Ordering::Relaxed),
+                "skipped": summary.skipped,
+                "elapsed_ms": summary.elapsed.as_secs_f64() * 1000.0,
+                "message": strip_ansi(msg),
+            }),
+            true,
+        ),
+        _ if LOGGER.get().is_none() => {
+            eprintln!("{msg}");
+            Ok(())
+        }
+        _ => write_log(msg.as_bytes(), true).context("Failed to write status message"),
+    }
+}
+
+/// Removes ANSI color codes from messages written into structured logs.
+fn strip_ansi(msg: &str) -> String {
+    let mut out = String::with_capacity(msg.len());
+    let mut chars = msg.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip until the end of the escape sequence (e.g. `\x1b[92m`)
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("Finished in: \x1b[92m1.2ms\x1b[39m"), "Finished in: 1.2ms");
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+
+    #[test]
+    fn test_error_event() {
+        let source = serde_json::from_str::<serde_json::Value>("{\n  \"a\": }").unwrap_err();
+        let err = ErrCtx::new(source, b"Invalid JSON\n- Failed to deserialize file".to_vec());
+
+        let event = error_event(&err, "error");
+
+        assert_eq!(event["kind"], "Syntax");
+        assert_eq!(event["line"], 2);
+        assert_eq!(event["column"], 8);
+        assert_eq!(event["context"], json!(["Invalid JSON", "Failed to deserialize file"]));
+    }
+
+    #[test]
+    fn test_code_frame() {
+        let (pos, snippet) = Snippet::locate(b"a = 1\nb = = 2\n", 10);
+
+        assert_eq!((pos.line, pos.column), (Some(2), Some(5)));
+        assert_eq!(
+            snippet.frame(pos.line, pos.column.unwrap()).unwrap(),
+            "  |\n2 | b = = 2\n  |     ^\n"
+        );
+
+        // long lines are cut around the column
+        let long = Snippet { line: [b'x'; 200].to_vec(), start: 1 };
+        let frame = long.frame(Some(1), 150).unwrap();
+        assert!(frame.contains(&format!("1 | ...{}\n", "x".repeat(80))));
+        assert!(frame.ends_with(&format!(" | {}^\n", " ".repeat(63))));
     }
-    Ok(())
 }
@@ -1,15 +1,32 @@
-pub(crate) mod cli;
+pub(crate) mod codecs;
+pub(crate) mod compression;
 pub(crate) mod decoders;
+pub(crate) mod filter;
+pub(crate) mod infer;
+pub(crate) mod json_schema;
 pub(crate) mod log;
+pub(crate) mod options;
+pub(crate) mod projection;
 pub(crate) mod readers;
+pub(crate) mod rejects;
+pub(crate) mod schema;
+pub(crate) mod sniffer;
 pub(crate) mod utilities;
 pub(crate) mod validators;
 pub(crate) mod writers;
 
-pub(crate) use cli::*;
+pub(crate) use codecs::*;
+pub(crate) use compression::*;
 pub(crate) use decoders::*;
+pub(crate) use filter::*;
+pub(crate) use infer::*;
+pub(crate) use json_schema::*;
 pub(crate) use log::*;
+pub(crate) use options::*;
+pub(crate) use projection::*;
 pub(crate) use readers::*;
+pub(crate) use rejects::*;
+pub(crate) use schema::*;
 pub(crate) use utilities::*;
 pub(crate) use validators::*;
 pub(crate) use writers::*;
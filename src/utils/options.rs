@@ -0,0 +1,327 @@
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+
+use crate::utils::{Codec, ColumnType};
+
+/// Options for customizing conversions, these replace the CLI's flags when fiux is used as a
+/// library.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Infer the types of values converted from CSV (integers, floats, dates and datetimes)
+    /// instead of writing them as strings, `true` / `false` / `null` are always typed.
+    pub parse_numbers: bool,
+
+    /// Types of table columns as `(column, type)` pairs, overriding the inferred types. Columns
+    /// are named by their output headers (after `rename`).
+    pub types: Vec<(String, ColumnType)>,
+
+    /// Delimiter for CSV / CSV-like input (e.g. `'\t'` for TSV), must be ASCII.
+    pub input_delimiter: char,
+
+    /// Delimiter for CSV / CSV-like output (e.g. `'|'` for PSV), must be ASCII.
+    pub output_delimiter: char,
+
+    /// Quoting, escaping, comments and header handling of CSV input.
+    pub input_dialect: CsvDialect,
+
+    /// Quoting, escaping and header handling of CSV output.
+    pub output_dialect: CsvDialect,
+
+    /// Policy for keys that first appear after the header row when writing objects as CSV,
+    /// `LateKeys::Union` holds every record in memory until the end of the input.
+    pub late_keys: LateKeys,
+
+    /// Whether `convert()` / `schema()` detect compressed input from its magic bytes and
+    /// decompress it, `false` for input that was already decompressed (e.g. with `decompress()`).
+    pub decompress: bool,
+
+    /// Codec for compressing the output, `None` writes it uncompressed.
+    pub compress: Option<Codec>,
+
+    /// Compression level for `compress`, `None` uses the codec's default level.
+    pub level: Option<i32>,
+
+    /// File to write the original bytes of records that failed to decode into, every rejected
+    /// record is written as a `<line>\t<raw record>` line. Records that fail to encode are written
+    /// too, as CSV rows in the input's dialect or as JSON lines after their record number.
+    pub reject_file: Option<PathBuf>,
+
+    /// Number of invalid records after which the conversion is aborted, `None` skips any number
+    /// of them and `Some(1)` aborts on the first one.
+    pub max_errors: Option<usize>,
+
+    /// Name of the XML element that holds a record, `None` reads the children of the root
+    /// element as records.
+    pub xml_record: Option<String>,
+
+    /// Name of the root element of XML output.
+    pub xml_root: String,
+
+    /// Name of the element that wraps every record in XML output.
+    pub xml_row: String,
+
+    /// Representation of MessagePack binary blobs (and ext data) and CBOR byte strings in the
+    /// records read from them.
+    pub binary: Binary,
+
+    /// Whether CBOR tags with a known meaning (e.g. epoch datetimes, bignums) are converted into
+    /// plain values or kept as `{"$tag": .., "$value": ..}` objects like unknown tags.
+    pub cbor_tags: CborTags,
+
+    /// Maximum number of rows in a row group of Parquet output, the column types are inferred
+    /// from the first row group (or the first 65,536 records of bigger row groups).
+    pub row_group_size: usize,
+
+    /// Compression codec of the column chunks in Parquet output.
+    pub parquet_compression: ParquetCompression,
+
+    /// Maximum number of rows in a record batch of Arrow output, the column types are inferred
+    /// from the first batch.
+    pub batch_size: usize,
+
+    /// Compression codec of the record batch buffers in Arrow output.
+    pub arrow_compression: ArrowCompression,
+
+    /// Avro schema (`.avsc`) file for Avro output, `None` infers the schema from the first
+    /// `avro_infer` records.
+    pub avro_schema: Option<PathBuf>,
+
+    /// Number of records the schema of Avro output is inferred from when `avro_schema` isn't set.
+    pub avro_infer: usize,
+
+    /// Compression codec of the blocks in Avro output.
+    pub avro_codec: AvroCodec,
+
+    /// Expression records have to match to be written (e.g. `age > 30 and name contains "a"`),
+    /// evaluated before `select` / `exclude` / `rename`.
+    pub filter: Option<String>,
+
+    /// Fields to keep, in this order for tables, every other field is dropped. Fields are dotted
+    /// key paths (e.g. `address.city`) for records and whole headers for tables.
+    pub select: Vec<String>,
+
+    /// Fields to drop, applied after `select`.
+    pub exclude: Vec<String>,
+
+    /// Fields to rename as `(old, new)` pairs, applied after `select` / `exclude`.
+    pub rename: Vec<(String, String)>,
+
+    /// JSON Schema file `validate()` checks every record against besides checking its syntax.
+    pub schema: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            parse_numbers: false,
+            types: Vec::new(),
+            input_delimiter: ',',
+            output_delimiter: ',',
+            input_dialect: CsvDialect::default(),
+            output_dialect: CsvDialect::default(),
+            late_keys: LateKeys::Union,
+            decompress: true,
+            compress: None,
+            level: None,
+            reject_file: None,
+            max_errors: None,
+            xml_record: None,
+            xml_root: "root".to_string(),
+            xml_row: "row".to_string(),
+            binary: Binary::Base64,
+            cbor_tags: CborTags::Convert,
+            row_group_size: 1024 * 1024,
+            parquet_compression: ParquetCompression::Snappy,
+            batch_size: 64 * 1024,
+            arrow_compression: ArrowCompression::Uncompressed,
+            avro_schema: None,
+            avro_infer: 1000,
+            avro_codec: AvroCodec::Null,
+            filter: None,
+            select: Vec::new(),
+            exclude: Vec::new(),
+            rename: Vec::new(),
+            schema: None,
+        }
+    }
+}
+
+/// Policies for keys that show up in later records when flattening objects into a CSV table
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LateKeys {
+    /// Buffer all records and use the union of their keys as the header row
+    Union,
+
+    /// Stream records using the first record's keys as the header row, dropping unknown keys
+    Drop,
+
+    /// Stream records using the first record's keys as the header row, skipping records with
+    /// unknown keys
+    Error,
+}
+
+/// Compression codecs for the column chunks of Parquet output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ParquetCompression {
+    /// No compression
+    Uncompressed,
+
+    /// Snappy, fast with a moderate ratio
+    Snappy,
+
+    /// Gzip
+    Gzip,
+
+    /// Zstandard, a better ratio at a similar speed
+    Zstd,
+}
+
+/// Compression codecs for the record batch buffers of Arrow output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ArrowCompression {
+    /// No compression, readable by every Arrow implementation
+    Uncompressed,
+
+    /// LZ4 frames, the default of Feather files written by pyarrow
+    Lz4,
+
+    /// Zstandard
+    Zstd,
+}
+
+/// Compression codecs for the blocks of Avro output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum AvroCodec {
+    /// No compression
+    Null,
+
+    /// Raw deflate (RFC 1951)
+    Deflate,
+
+    /// Snappy, with a CRC32 checksum of every block
+    Snappy,
+}
+
+/// Handling of CBOR tags in records
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CborTags {
+    /// Convert date / time strings, epoch datetimes, bignums, decimal fractions and bigfloats
+    /// into plain values, other tags become `{"$tag": .., "$value": ..}` objects
+    Convert,
+
+    /// Keep every tag as a `{"$tag": .., "$value": ..}` object
+    Preserve,
+}
+
+/// Representations of MessagePack binary blobs and CBOR byte strings in records
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Binary {
+    /// Standard padded base64 strings
+    Base64,
+
+    /// Arrays of byte values
+    Array,
+}
+
+/// CSV dialect besides the delimiter, fields that only apply to reading (e.g. `comment`) or
+/// writing (e.g. `quote_style`) are ignored on the other side.
+#[derive(Clone, Debug)]
+pub struct CsvDialect {
+    /// Character that quotes fields, must be ASCII.
+    pub quote: char,
+
+    /// Character that escapes quotes inside quoted fields (e.g. `'\\'`), `None` escapes them by
+    /// doubling them.
+    pub escape: Option<char>,
+
+    /// Whether doubled quotes inside quoted fields are an escaped quote.
+    pub double_quote: bool,
+
+    /// Lines starting with this character are skipped when reading.
+    pub comment: Option<char>,
+
+    /// Trim whitespace around fields and headers when reading.
+    pub trim: bool,
+
+    /// Whether the first row is a header row, tables without one get `header_names` or `col1` to
+    /// `colN` as headers when reading and no header row when writing.
+    pub has_headers: bool,
+
+    /// Headers for input without a header row, `col1` to `colN` are generated if it's empty.
+    pub header_names: Vec<String>,
+
+    /// Record terminator, `None` reads any of `\r\n`, `\n` and `\r` and writes `\n`.
+    pub terminator: Option<Terminator>,
+
+    /// Which fields are quoted when writing.
+    pub quote_style: QuoteStyle,
+
+    /// Policy for rows with a different number of fields than the header row when reading.
+    pub ragged: Ragged,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self {
+            quote: '"',
+            escape: None,
+            double_quote: true,
+            comment: None,
+            trim: false,
+            has_headers: true,
+            header_names: Vec::new(),
+            terminator: None,
+            quote_style: QuoteStyle::Necessary,
+            ragged: Ragged::Error,
+        }
+    }
+}
+
+/// Record terminators for CSV input / output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Terminator {
+    /// `\r\n` when writing, `\r\n`, `\n` or `\r` when reading
+    Crlf,
+
+    /// `\n` only
+    Lf,
+
+    /// `\r` only
+    Cr,
+}
+
+/// Quoting policies for CSV output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum QuoteStyle {
+    /// Quote every field
+    Always,
+
+    /// Quote fields only when they contain quotes, delimiters or terminators
+    Necessary,
+
+    /// Never quote fields, even if it produces invalid CSV
+    Never,
+
+    /// Quote every field that isn't a number
+    NonNumeric,
+}
+
+/// Policies for CSV rows with a different number of fields than the header row, every policy but
+/// `error` pads short rows with empty fields (read as empty strings, or as nulls where the output
+/// has them for empty fields)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Ragged {
+    /// Skip rows with missing or extra fields as invalid records
+    Error,
+
+    /// Pad short rows, skipping rows with extra fields as invalid records
+    Pad,
+
+    /// Pad short rows and drop the extra fields of long rows
+    Truncate,
+
+    /// Pad short rows and collect the extra fields of long rows into an `_extra` column as a JSON
+    /// array (`[]` for rows without any), written as a real array by the formats that have them
+    ExtraColumn,
+}
@@ -0,0 +1,306 @@
+use std::io::{Error, ErrorKind as EK};
+
+use csv::ByteRecord;
+use resext::{CtxResult, ResExt};
+
+use crate::utils::{DataTypes, Options, WriterStreams};
+
+/// Records whose fields can be moved around by dotted key paths (e.g. `address.city`).
+trait Fields: Sized {
+    fn table() -> Self;
+
+    /// Removes the value at `path` and the tables it leaves empty, `None` if any of its keys
+    /// doesn't exist.
+    fn remove_path(&mut self, path: &[&str]) -> Option<Self>;
+
+    /// Inserts `value` at `path`, creating (or replacing non-table values with) the tables on the
+    /// way.
+    fn insert_path(&mut self, path: &[&str], value: Self);
+}
+
+impl Fields for serde_json::Value {
+    fn table() -> Self {
+        serde_json::Value::Object(serde_json::Map::new())
+    }
+
+    fn remove_path(&mut self, path: &[&str]) -> Option<Self> {
+        let (first, rest) = path.split_first()?;
+        let map = self.as_object_mut()?;
+
+        if rest.is_empty() {
+            return map.remove(*first);
+        }
+
+        let child = map.get_mut(*first)?;
+        let value = child.remove_path(rest)?;
+
+        if child.as_object().is_some_and(|m| m.is_empty()) {
+            map.remove(*first);
+        }
+
+        Some(value)
+    }
+
+    fn insert_path(&mut self, path: &[&str], value: Self) {
+        let Some((last, parents)) = path.split_last() else { return };
+        let mut node = self;
+
+        for key in parents.iter().chain([last]) {
+            if !node.is_object() {
+                *node = Self::table();
+            }
+
+            let map = node.as_object_mut().expect("node was made a table");
+
+            if key == last {
+                map.insert(key.to_string(), value);
+                return;
+            }
+
+            node = map.entry(*key).or_insert_with(Self::table);
+        }
+    }
+}
+
+impl Fields for toml::Value {
+    fn table() -> Self {
+        toml::Value::Table(toml::Table::new())
+    }
+
+    fn remove_path(&mut self, path: &[&str]) -> Option<Self> {
+        let (first, rest) = path.split_first()?;
+        let map = self.as_table_mut()?;
+
+        if rest.is_empty() {
+            return map.remove(*first);
+        }
+
+        let child = map.get_mut(*first)?;
+        let value = child.remove_path(rest)?;
+
+        if child.as_table().is_some_and(|m| m.is_empty()) {
+            map.remove(*first);
+        }
+
+        Some(value)
+    }
+
+    fn insert_path(&mut self, path: &[&str], value: Self) {
+        let Some((last, parents)) = path.split_last() else { return };
+        let mut node = self;
+
+        for key in parents.iter().chain([last]) {
+            if !node.is_table() {
+                *node = Self::table();
+            }
+
+            let map = node.as_table_mut().expect("node was made a table");
+
+            if key == last {
+                map.insert(key.to_string(), value);
+                return;
+            }
+
+            node = map.entry(*key).or_insert_with(Self::table);
+        }
+    }
+}
+
+/// Selected, excluded and renamed key paths, split into their keys once.
+struct Projector {
+    select: Vec<Vec<String>>,
+    exclude: Vec<Vec<String>>,
+    rename: Vec<(Vec<String>, Vec<String>)>,
+
+    /// Indices of the columns that are kept (in output order) for table rows.
+    columns: Option<Vec<usize>>,
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split('.').map(str::to_string).collect()
+}
+
+impl Projector {
+    fn new(options: &Options) -> Self {
+        Projector {
+            select: options.select.iter().map(|p| split_path(p)).collect(),
+            exclude: options.exclude.iter().map(|p| split_path(p)).collect(),
+            rename: options
+                .rename
+                .iter()
+                .map(|(old, new)| (split_path(old), split_path(new)))
+                .collect(),
+            columns: None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.select.is_empty() && self.exclude.is_empty() && self.rename.is_empty()
+    }
+
+    /// Picks the columns of a table by their header, columns are named by their whole header
+    /// (e.g. `address.city`) and unknown columns are an error.
+    fn project_headers(
+        &mut self,
+        headers: Vec<String>,
+        options: &Options,
+    ) -> Result<Vec<String>, Error> {
+        let index_of = |name: &str| {
+            headers.iter().position(|h| h == name).ok_or_else(|| {
+                Error::new(
+                    EK::InvalidInput,
+                    format!("Column: {} doesn't exist in the header row", name),
+                )
+            })
+        };
+
+        let mut columns: Vec<usize> = if options.select.is_empty() {
+            (0..headers.len()).collect()
+        } else {
+            options.select.iter().map(|s| index_of(s)).collect::<Result<_, _>>()?
+        };
+
+        for name in &options.exclude {
+            let idx = index_of(name)?;
+            columns.retain(|c| *c != idx);
+        }
+
+        let mut renamed: Vec<String> = columns.iter().map(|c| headers[*c].clone()).collect();
+
+        for (old, new) in &options.rename {
+            let idx = index_of(old)?;
+
+            if let Some(pos) = columns.iter().position(|c| *c == idx) {
+                renamed[pos] = new.clone();
+            }
+        }
+
+        self.columns = Some(columns);
+
+        Ok(renamed)
+    }
+
+    fn apply<F: Fields>(&self, mut value: F) -> F {
+        if !self.select.is_empty() {
+            let mut selected = F::table();
+
+            for path in &self.select {
+                let path: Vec<&str> = path.iter().map(String::as_str).collect();
+
+                if let Some(v) = value.remove_path(&path) {
+                    selected.insert_path(&path, v);
+                }
+            }
+
+            value = selected;
+        }
+
+        for path in &self.exclude {
+            let path: Vec<&str> = path.iter().map(String::as_str).collect();
+            value.remove_path(&path);
+        }
+
+        for (old, new) in &self.rename {
+            let old: Vec<&str> = old.iter().map(String::as_str).collect();
+            let new: Vec<&str> = new.iter().map(String::as_str).collect();
+
+            if let Some(v) = value.remove_path(&old) {
+                value.insert_path(&new, v);
+            }
+        }
+
+        value
+    }
+
+    fn project(&self, record: DataTypes) -> DataTypes {
+        match record {
+            DataTypes::Csv(row) => {
+                let Some(columns) = &self.columns else { return DataTypes::Csv(row) };
+
+                let mut projected = ByteRecord::with_capacity(row.as_slice().len(), columns.len());
+                columns.iter().for_each(|c| projected.push_field(row.get(*c).unwrap_or_default()));
+
+                DataTypes::Csv(projected)
+            }
+            // arrays of records (e.g. YAML sequences) are projected element by element
+            DataTypes::Json(serde_json::Value::Array(arr)) => DataTypes::Json(
+                serde_json::Value::Array(arr.into_iter().map(|v| self.apply(v)).collect()),
+            ),
+            DataTypes::Json(value) => DataTypes::Json(self.apply(value)),
+            DataTypes::Toml(value) => DataTypes::Toml(self.apply(value)),
+        }
+    }
+}
+
+/// Keeps only the selected fields of every record, drops the excluded ones and renames fields with
+/// `Options::rename`. Table rows are projected by column index while other records are projected
+/// by dotted key paths.
+pub(crate) fn project<I>(
+    data: WriterStreams<I>,
+    options: &Options,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>, Error>
+where
+    I: Iterator<Item = CtxResult<DataTypes, Error>>,
+{
+    let mut projector = Projector::new(options);
+
+    let data = match data {
+        WriterStreams::Table { headers, iter } if !projector.is_empty() => {
+            let headers = projector
+                .project_headers(headers, options)
+                .context("Failed to project columns of the header row")?;
+
+            WriterStreams::Table { headers, iter }
+        }
+        other => other,
+    };
+
+    let active = !projector.is_empty();
+
+    Ok(data.map_iter(move |iter| {
+        iter.map(move |rec| if active { rec.map(|r| projector.project(r)) } else { rec })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_key_paths() {
+        let options = Options {
+            select: vec!["id".to_string(), "address.city".to_string(), "tags".to_string()],
+            exclude: vec!["tags".to_string()],
+            rename: vec![("address.city".to_string(), "city".to_string())],
+            ..Options::default()
+        };
+
+        let value = serde_json::json!({
+            "id": 1,
+            "name": "Alice",
+            "address": {"city": "NYC", "zip": "10001"},
+            "tags": ["a"],
+        });
+
+        let projected = Projector::new(&options).apply(value);
+        assert_eq!(projected, serde_json::json!({"id": 1, "city": "NYC"}));
+
+        let options = Options { select: Vec::new(), ..options };
+        let mut projector = Projector::new(&options);
+        let headers = ["id", "name", "address.city", "tags"].map(String::from).to_vec();
+
+        assert_eq!(projector.project_headers(headers, &options).unwrap(), ["id", "name", "city"]);
+
+        let DataTypes::Csv(row) =
+            projector.project(DataTypes::Csv(ByteRecord::from(vec!["1", "Alice", "NYC", "a"])))
+        else {
+            unreachable!()
+        };
+        assert_eq!(row, ByteRecord::from(vec!["1", "Alice", "NYC"]));
+
+        // unknown columns are an error since tables have a known header row
+        let options = Options { select: vec!["missing".to_string()], ..Options::default() };
+        let headers = vec!["id".to_string()];
+        assert!(Projector::new(&options).project_headers(headers, &options).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+use std::io::{BufReader, Cursor, Error, ErrorKind as EK, Read};
+
+use arrow_ipc::reader::{FileReader, StreamReader};
+
+/// Reads an Arrow IPC file (Feather v2). The footer with the offsets of its record batches is at
+/// the end of the file, so the input is read into memory as a whole before the batches are
+/// decoded one at a time.
+pub(crate) fn arrow_file_reader<R: Read>(
+    mut reader: R,
+) -> Result<FileReader<Cursor<Vec<u8>>>, Error> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+
+    FileReader::try_new(Cursor::new(buf), None).map_err(arrow_error)
+}
+
+/// Reads an Arrow IPC stream, record batches are decoded as they arrive.
+#[inline]
+pub(crate) fn arrow_stream_reader<R: Read>(reader: R) -> Result<StreamReader<BufReader<R>>, Error> {
+    StreamReader::try_new(BufReader::with_capacity(256 * 1024, reader), None).map_err(arrow_error)
+}
+
+/// Creates an error for an invalid Arrow IPC file / stream or record batch.
+#[inline]
+pub(crate) fn arrow_error(e: impl std::fmt::Display) -> Error {
+    Error::new(EK::InvalidData, format!("Invalid Arrow: {}", e))
+}
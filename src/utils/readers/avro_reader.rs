@@ -0,0 +1,28 @@
+use std::io::{BufReader, Error, ErrorKind as EK, Read};
+
+/// Reads the header of an Avro object container file, the returned reader yields the values of
+/// its blocks one at a time. Deflate and snappy compressed blocks are decompressed transparently.
+pub(crate) fn avro_reader<R: Read>(
+    reader: R,
+) -> Result<apache_avro::Reader<'static, BufReader<R>>, Error> {
+    apache_avro::Reader::new(BufReader::with_capacity(256 * 1024, reader)).map_err(avro_error)
+}
+
+/// Creates an error for an invalid Avro file, block or value.
+#[inline]
+pub(crate) fn avro_error(e: impl std::fmt::Display) -> Error {
+    Error::new(EK::InvalidData, format!("Invalid Avro: {}", e))
+}
+
+/// Named types of `schema` by their full name, for resolving references to them.
+pub(crate) fn avro_names(
+    schema: &apache_avro::Schema,
+) -> Result<apache_avro::schema::Names, Error> {
+    let resolved = apache_avro::schema::ResolvedSchema::new(schema).map_err(avro_error)?;
+
+    Ok(resolved
+        .get_names()
+        .iter()
+        .map(|(name, schema)| (name.clone(), (*schema).clone()))
+        .collect())
+}
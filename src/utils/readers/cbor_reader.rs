@@ -0,0 +1,315 @@
+use std::io::{BufRead, BufReader, Error, ErrorKind as EK, Read};
+
+use crate::utils::{Position, Snippet, syntax_error};
+
+/// Maximum nesting of arrays, maps and tags, deeper items are rejected instead of overflowing the
+/// stack.
+const MAX_DEPTH: usize = 512;
+
+/// Decoded CBOR data item.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Item {
+    Uint(u64),
+
+    /// Negative integer `-1 - n`.
+    Nint(u64),
+
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Item>),
+    Map(Vec<(Item, Item)>),
+    Tag(u64, Box<Item>),
+    Bool(bool),
+    Null,
+    Undefined,
+
+    /// Simple value without a predefined meaning.
+    Simple(u8),
+
+    Float(f64),
+}
+
+/// Head of a data item, `None` lengths are indefinite.
+enum Head {
+    Uint(u64),
+    Nint(u64),
+    Bytes(Option<u64>),
+    Text(Option<u64>),
+    Array(Option<u64>),
+    Map(Option<u64>),
+    Tag(u64),
+    Simple(u8),
+    Float(f64),
+    Break,
+}
+
+/// Buffered reader over a CBOR sequence (RFC 8742) that keeps track of its byte offset for error
+/// positions.
+pub(crate) struct CborReader<R> {
+    reader: R,
+    offset: u64,
+
+    /// Offset of the last head that was read, malformed items are reported at their head.
+    head: u64,
+}
+
+impl<R: BufRead> CborReader<R> {
+    /// Reads the next top-level item, `None` at the end of the input. Items can't be
+    /// resynchronized after a malformed one, so callers should stop at the first error.
+    pub(crate) fn read_item(&mut self) -> Option<Result<Item, Error>> {
+        match self.reader.fill_buf() {
+            Ok([]) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(e)),
+        }
+
+        let start = self.offset;
+
+        Some(self.item(0).map_err(|e| {
+            let (msg, offset) = match e.kind() {
+                EK::UnexpectedEof => ("unexpected end of input".to_string(), self.offset),
+                EK::InvalidData => (e.to_string(), self.head),
+                _ => return e,
+            };
+            cbor_error(format!("{} in the item starting at byte {}", msg, start), offset)
+        }))
+    }
+
+    fn item(&mut self, depth: usize) -> Result<Item, Error> {
+        let head = self.head()?;
+        self.item_from(head, depth)
+    }
+
+    fn item_from(&mut self, head: Head, depth: usize) -> Result<Item, Error> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("items are nested too deeply"));
+        }
+
+        Ok(match head {
+            Head::Uint(n) => Item::Uint(n),
+            Head::Nint(n) => Item::Nint(n),
+            Head::Bytes(len) => Item::Bytes(self.string(len, 2)?),
+            Head::Text(len) => Item::Text(
+                String::from_utf8(self.string(len, 3)?)
+                    .map_err(|_| invalid("text string isn't valid UTF-8"))?,
+            ),
+            Head::Array(len) => {
+                let mut items = Vec::with_capacity(capacity(len));
+
+                match len {
+                    Some(len) => {
+                        for _ in 0..len {
+                            items.push(self.item(depth + 1)?);
+                        }
+                    }
+                    None => loop {
+                        match self.head()? {
+                            Head::Break => break,
+                            head => items.push(self.item_from(head, depth + 1)?),
+                        }
+                    },
+                }
+
+                Item::Array(items)
+            }
+            Head::Map(len) => {
+                let mut pairs = Vec::with_capacity(capacity(len));
+
+                match len {
+                    Some(len) => {
+                        for _ in 0..len {
+                            pairs.push((self.item(depth + 1)?, self.item(depth + 1)?));
+                        }
+                    }
+                    None => loop {
+                        match self.head()? {
+                            Head::Break => break,
+                            head => pairs
+                                .push((self.item_from(head, depth + 1)?, self.item(depth + 1)?)),
+                        }
+                    },
+                }
+
+                Item::Map(pairs)
+            }
+            Head::Tag(tag) => Item::Tag(tag, Box::new(self.item(depth + 1)?)),
+            Head::Simple(20) => Item::Bool(false),
+            Head::Simple(21) => Item::Bool(true),
+            Head::Simple(22) => Item::Null,
+            Head::Simple(23) => Item::Undefined,
+            Head::Simple(n) => Item::Simple(n),
+            Head::Float(f) => Item::Float(f),
+            Head::Break => return Err(invalid("unexpected break outside of an indefinite item")),
+        })
+    }
+
+    /// Reads a byte / text string of major type `major`, indefinite strings are concatenated from
+    /// their definite chunks.
+    fn string(&mut self, len: Option<u64>, major: u8) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::with_capacity(capacity(len));
+
+        match len {
+            Some(len) => self.bytes(len, &mut buf)?,
+            None => loop {
+                match self.head()? {
+                    Head::Break => break,
+                    Head::Bytes(Some(len)) if major == 2 => self.bytes(len, &mut buf)?,
+                    Head::Text(Some(len)) if major == 3 => self.bytes(len, &mut buf)?,
+                    _ => return Err(invalid("indefinite string chunks must be definite strings")),
+                }
+            },
+        }
+
+        Ok(buf)
+    }
+
+    fn bytes(&mut self, len: u64, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let read = self.take(len, buf)?;
+
+        if read < len {
+            return Err(Error::from(EK::UnexpectedEof));
+        }
+
+        Ok(())
+    }
+
+    fn take(&mut self, len: u64, buf: &mut Vec<u8>) -> Result<u64, Error> {
+        let read = (&mut self.reader).take(len).read_to_end(buf)? as u64;
+        self.offset += read;
+        Ok(read)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        let mut b = [0u8; 1];
+        self.reader.read_exact(&mut b)?;
+        self.offset += 1;
+        Ok(b[0])
+    }
+
+    fn uint(&mut self, size: usize) -> Result<u64, Error> {
+        let mut b = [0u8; 8];
+        self.reader.read_exact(&mut b[8 - size..])?;
+        self.offset += size as u64;
+        Ok(u64::from_be_bytes(b))
+    }
+
+    fn head(&mut self) -> Result<Head, Error> {
+        self.head = self.offset;
+        let initial = self.byte()?;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+
+        if major == 7 {
+            return Ok(match info {
+                0..=23 => Head::Simple(info),
+                24 => match self.byte()? {
+                    n if n < 32 => return Err(invalid("simple values below 32 use one byte")),
+                    n => Head::Simple(n),
+                },
+                25 => Head::Float(half(self.uint(2)? as u16)),
+                26 => Head::Float(f32::from_bits(self.uint(4)? as u32) as f64),
+                27 => Head::Float(f64::from_bits(self.uint(8)?)),
+                31 => Head::Break,
+                _ => return Err(invalid(format!("reserved additional info {}", info))),
+            });
+        }
+
+        let arg = match info {
+            0..=23 => Some(info as u64),
+            24 => Some(self.uint(1)?),
+            25 => Some(self.uint(2)?),
+            26 => Some(self.uint(4)?),
+            27 => Some(self.uint(8)?),
+            31 if matches!(major, 2..=5) => None,
+            _ => return Err(invalid(format!("reserved additional info {}", info))),
+        };
+
+        let value = || arg.unwrap_or_default();
+
+        Ok(match major {
+            0 => Head::Uint(value()),
+            1 => Head::Nint(value()),
+            2 => Head::Bytes(arg),
+            3 => Head::Text(arg),
+            4 => Head::Array(arg),
+            5 => Head::Map(arg),
+            _ => Head::Tag(value()),
+        })
+    }
+}
+
+/// Preallocated capacity for a collection of `len` items, capped so a bogus length can't
+/// allocate more than the input holds.
+#[inline]
+fn capacity(len: Option<u64>) -> usize {
+    len.unwrap_or_default().min(4096) as usize
+}
+
+/// Converts an IEEE 754 half-precision float.
+fn half(bits: u16) -> f64 {
+    let exp = (bits >> 10) & 0x1f;
+    let mant = (bits & 0x3ff) as f64;
+
+    let value = match exp {
+        0 => mant * 2f64.powi(-24),
+        31 if mant == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mant + 1024.0) * 2f64.powi(exp as i32 - 25),
+    };
+
+    if bits & 0x8000 != 0 { -value } else { value }
+}
+
+#[inline]
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::new(EK::InvalidData, msg.into())
+}
+
+#[inline]
+pub(crate) fn cbor_reader<R: Read>(reader: R) -> CborReader<BufReader<R>> {
+    CborReader { reader: BufReader::with_capacity(256 * 1024, reader), offset: 0, head: 0 }
+}
+
+/// Creates an error for a malformed CBOR item at byte `offset`.
+#[inline]
+pub(crate) fn cbor_error(msg: impl std::fmt::Display, offset: u64) -> Error {
+    let pos = Position { offset: Some(offset), ..Position::default() };
+
+    syntax_error(format!("Invalid CBOR: {}", msg), pos, Snippet::default(), Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(input: &[u8]) -> Vec<Result<Item, String>> {
+        let mut reader = cbor_reader(input);
+        std::iter::from_fn(move || reader.read_item())
+            .map(|item| item.map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_cbor_items() {
+        // {"a": [1, -2, 1.5]}, indefinite "ab" text, tag 1(0), half-float 1.0
+        let input = b"\xa1\x61a\x83\x01\x21\xf9\x3e\x00\x7f\x61a\x61b\xff\xc1\x00\xf9\x3c\x00";
+
+        let expected = [
+            Item::Map(vec![(
+                Item::Text("a".into()),
+                Item::Array(vec![Item::Uint(1), Item::Nint(1), Item::Float(1.5)]),
+            )]),
+            Item::Text("ab".into()),
+            Item::Tag(1, Box::new(Item::Uint(0))),
+            Item::Float(1.0),
+        ];
+        assert_eq!(items(input), expected.map(Ok));
+
+        // truncated array, then a reserved additional info after a valid item
+        let err = items(b"\x82\x01").pop().unwrap().unwrap_err();
+        assert!(err.contains("unexpected end of input in the item starting at byte 0"), "{}", err);
+
+        let all = items(b"\x01\x1c");
+        assert_eq!(all[0], Ok(Item::Uint(1)));
+        assert!(all[1].as_ref().unwrap_err().contains("reserved additional info 28"));
+    }
+}
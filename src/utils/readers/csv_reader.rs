@@ -1,24 +1,179 @@
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::{
+    cell::RefCell,
+    io::{BufReader, Error, ErrorKind as EK, Read},
+    rc::Rc,
+};
 
-use resext::{ResExt, throw_err_if};
+use resext::{CtxResult, ResExt};
 
+use crate::utils::{CsvDialect, Ragged, Terminator};
+
+/// Gets the byte of a dialect character, they have to be ASCII like delimiters.
+#[inline]
+pub(crate) fn ascii(c: char, name: &str) -> CtxResult<u8, Error> {
+    if !c.is_ascii() {
+        return Err(Error::new(
+            EK::InvalidInput,
+            format!("{} must be a single ASCII character", name),
+        ))
+        .with_context(|| format!("{}: {} is not valid ASCII", name, c));
+    }
+
+    Ok(c as u8)
+}
+
+/// Creates a reader builder for `delimiter` and `dialect`, shared by the decoder and the
+/// validator.
+pub(crate) fn reader_builder(
+    delimiter: char,
+    dialect: &CsvDialect,
+) -> CtxResult<csv::ReaderBuilder, Error> {
+    let mut builder = csv::ReaderBuilder::new();
+
+    builder
+        .delimiter(ascii(delimiter, "Input delimiter")?)
+        .quote(ascii(dialect.quote, "Input quote")?)
+        .double_quote(dialect.double_quote)
+        .has_headers(dialect.has_headers)
+        .trim(if dialect.trim { csv::Trim::All } else { csv::Trim::None })
+        .terminator(match dialect.terminator {
+            None | Some(Terminator::Crlf) => csv::Terminator::CRLF,
+            Some(Terminator::Lf) => csv::Terminator::Any(b'\n'),
+            Some(Terminator::Cr) => csv::Terminator::Any(b'\r'),
+        });
+
+    if let Some(escape) = dialect.escape {
+        builder.escape(Some(ascii(escape, "Input escape")?));
+    }
+
+    if let Some(comment) = dialect.comment {
+        builder.comment(Some(ascii(comment, "Comment")?));
+    }
+
+    Ok(builder)
+}
+
+/// Bytes read from the input that the CSV reader may still be parsing, so rows can be written into
+/// the reject file with their original bytes.
+#[derive(Default)]
+pub(crate) struct Seen {
+    buf: Vec<u8>,
+
+    /// Input offset of the first byte in `buf`.
+    start: u64,
+}
+
+impl Seen {
+    /// Original bytes of the input between the `from` and `to` offsets, without line terminators
+    /// (a row after a `\r\n` terminator starts at its `\n`).
+    pub(crate) fn raw(&self, from: u64, to: u64) -> Option<Vec<u8>> {
+        let from = usize::try_from(from.checked_sub(self.start)?).ok()?;
+        let to = usize::try_from(to.checked_sub(self.start)?).ok()?;
+
+        let mut raw = self.buf.get(from..to)?;
+        while let [b'\n' | b'\r', rest @ ..] | [rest @ .., b'\n' | b'\r'] = raw {
+            raw = rest;
+        }
+        Some(raw.to_vec())
+    }
+
+    /// Drops the bytes before the `to` offset once enough of them piled up.
+    pub(crate) fn consume(&mut self, to: u64) {
+        let used = usize::try_from(to.saturating_sub(self.start)).unwrap_or(usize::MAX);
+
+        if used >= 64 * 1024 {
+            self.buf.drain(..used.min(self.buf.len()));
+            self.start = to;
+        }
+    }
+}
+
+/// Reader that keeps the bytes it reads in `seen` (if it's set).
+pub(crate) struct Recorder<R> {
+    inner: R,
+    pub(crate) seen: Option<Rc<RefCell<Seen>>>,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        if let Some(seen) = &self.seen {
+            seen.borrow_mut().buf.extend_from_slice(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// Creates a flexible CSV reader, `record` keeps the bytes of the rows being read so invalid rows
+/// can be written into the reject file as they are.
 #[inline]
-pub(crate) fn csv_reader(path: &PathBuf, delimiter: char) -> csv::Reader<BufReader<File>> {
-    let file = File::open(path).dyn_expect(
-        || format!("Couldn't open input file {}", path.to_str().unwrap_or("[input.csv]")),
-        1,
-        true,
-    );
+pub(crate) fn csv_reader<R: Read>(
+    reader: R,
+    delimiter: char,
+    dialect: &CsvDialect,
+    record: bool,
+) -> CtxResult<csv::Reader<Recorder<BufReader<R>>>, Error> {
+    let buffered_reader = BufReader::with_capacity(256 * 1024, reader);
+    let seen = record.then(Rc::default);
+
+    Ok(reader_builder(delimiter, dialect)?
+        .flexible(true)
+        .from_reader(Recorder { inner: buffered_reader, seen }))
+}
+
+/// Fits a row with a different number of fields than the `len` headers according to `ragged`,
+/// rows the policy doesn't allow are left as they are and return their field-count mismatch. Rows
+/// get an extra `_extra` field with `Ragged::ExtraColumn`.
+pub(crate) fn fit_row(rec: &mut csv::ByteRecord, len: usize, ragged: Ragged) -> Result<(), String> {
+    let extra_column = ragged == Ragged::ExtraColumn;
+
+    if rec.len() == len && !extra_column {
+        return Ok(());
+    }
+
+    let mismatch = || format!("Record has {} fields, but the header row has {}", rec.len(), len);
+
+    match ragged {
+        Ragged::Error => return Err(mismatch()),
+        Ragged::Pad if rec.len() > len => return Err(mismatch()),
+        _ => {}
+    }
+
+    let extra: Vec<String> =
+        rec.iter().skip(len).map(|f| String::from_utf8_lossy(f).into_owned()).collect();
+
+    rec.truncate(len);
+    while rec.len() < len {
+        rec.push_field(b"");
+    }
+
+    if extra_column {
+        rec.push_field(serde_json::to_string(&extra).unwrap_or_default().as_bytes());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let buffered_reader = BufReader::with_capacity(256 * 1024, file);
+    #[test]
+    fn test_recorder_keeps_raw_rows() {
+        let input = "a,b\n1,\"x\ny\"\r\n2,3\n";
+        let mut reader = csv_reader(input.as_bytes(), ',', &CsvDialect::default(), true).unwrap();
+        let seen = reader.get_ref().seen.clone().unwrap();
 
-    throw_err_if!(
-        !delimiter.is_ascii(),
-        || format!("Input delimiter: {} is not valid UTF-8", delimiter),
-        1
-    );
+        let mut rec = csv::ByteRecord::new();
+        reader.read_byte_record(&mut rec).unwrap();
+        let start = rec.position().unwrap().byte();
 
-    let d = delimiter as u8;
+        assert_eq!(seen.borrow().raw(start, reader.position().byte()).unwrap(), b"1,\"x\ny\"");
 
-    csv::ReaderBuilder::new().delimiter(d).from_reader(buffered_reader)
+        seen.borrow_mut().consume(reader.position().byte());
+        reader.read_byte_record(&mut rec).unwrap();
+        let start = rec.position().unwrap().byte();
+        assert_eq!(seen.borrow().raw(start, reader.position().byte()).unwrap(), b"2,3");
+    }
 }
@@ -1,18 +1,473 @@
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::{
+    cell::RefCell,
+    io::{BufRead, BufReader, Error, Read},
+    rc::Rc,
+};
 
-use resext::ResExt;
+use resext::{CtxResult, ResExt};
 
+use crate::utils::{Position, Snippet, syntax_error};
+
+/// Capacity of the reader's buffer, serde_json's position can be this far behind the tracker.
+const CAPACITY: usize = 256 * 1024;
+
+/// Bytes kept by `TrackedLines`, enough for the buffered bytes and the line before them.
+const KEPT_BYTES: usize = CAPACITY + 64 * 1024;
+
+/// Last bytes read by a `LineTracker`, lines are only looked up when an error is reported.
+#[derive(Debug)]
+pub(crate) struct TrackedLines {
+    buf: Vec<u8>,
+
+    /// Byte offset of `buf[0]`.
+    start: u64,
+
+    /// 1-based line of `buf[0]`.
+    line: usize,
+
+    /// Byte offset of the start of `line`.
+    line_start: u64,
+}
+
+impl TrackedLines {
+    fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+
+        // dropped in large chunks so bytes are only moved once in a while
+        if self.buf.len() < KEPT_BYTES * 2 {
+            return;
+        }
+
+        let drop = self.buf.len() - KEPT_BYTES;
+        let dropped = &self.buf[..drop];
+
+        if let Some(idx) = memchr::memrchr(b'\n', dropped) {
+            self.line += memchr::memchr_iter(b'\n', dropped).count();
+            self.line_start = self.start + idx as u64 + 1;
+        }
+
+        self.start += drop as u64;
+        self.buf.drain(..drop);
+    }
+
+    /// Byte offset of the start of `line` and its kept bytes, `None` once it's no longer kept.
+    fn find(&self, line: usize) -> Option<(u64, Snippet)> {
+        let (line_start, from) = match line.checked_sub(self.line)? {
+            0 => (self.line_start, 0),
+            n => {
+                let idx = memchr::memchr_iter(b'\n', &self.buf).nth(n - 1)? + 1;
+                (self.start + idx as u64, idx)
+            }
+        };
+
+        let to = memchr::memchr(b'\n', &self.buf[from..]).map_or(self.buf.len(), |idx| from + idx);
+        let skipped = self.start + from as u64 - line_start;
+
+        Some((
+            line_start,
+            Snippet { line: self.buf[from..to].to_vec(), start: skipped as usize + 1 },
+        ))
+    }
+}
+
+/// Reader that keeps the last bytes it read, serde_json only reports lines and columns for
+/// errors so the bytes are kept for rendering code frames and getting byte offsets.
+pub(crate) struct LineTracker<R> {
+    inner: R,
+    lines: Option<Rc<RefCell<TrackedLines>>>,
+}
+
+impl<R: Read> Read for LineTracker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        if let Some(lines) = &self.lines {
+            lines.borrow_mut().push(&buf[..n]);
+        }
+
+        Ok(n)
+    }
+}
+
+/// JSON deserializer and the position of its reader.
+pub(crate) type TrackedJson<R> = (
+    serde_json::Deserializer<serde_json::de::IoRead<BufReader<LineTracker<R>>>>,
+    Rc<RefCell<TrackedLines>>,
+);
+
+/// JSON input, top-level arrays are read one element at a time instead of as a single value.
+pub(crate) enum JsonInput<R: Read> {
+    Array(ArrayElements<BufReader<LineTracker<R>>>),
+    Values(TrackedJson<R>),
+}
+
+/// Element of a top-level JSON array and the position of its first byte.
+#[derive(Debug)]
+pub(crate) struct Element {
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) offset: u64,
+}
+
+/// Splits a top-level JSON array into its elements without parsing them, so arrays of any size
+/// are read in constant memory. Elements are parsed on their own so an invalid element doesn't
+/// stop the rest of the array from being read.
+pub(crate) struct ArrayElements<R> {
+    reader: R,
+    line: usize,
+    column: usize,
+    offset: u64,
+    after_comma: bool,
+    closed: bool,
+    done: bool,
+}
+
+impl<R: BufRead> ArrayElements<R> {
+    /// Error at the reader's current position, it has no code frame since the line isn't kept.
+    fn error(&self, msg: &str) -> Error {
+        let pos =
+            Position { line: Some(self.line), offset: Some(self.offset), ..Position::default() };
+
+        syntax_error(format!("Invalid JSON: {}", msg), pos, Snippet::default(), Vec::new())
+    }
+
+    /// Checks that nothing but whitespace follows the closing bracket of the array.
+    fn check_trailing(&mut self) -> Option<Result<Element, Error>> {
+        self.done = true;
+
+        loop {
+            let (idx, len) = match self.reader.fill_buf() {
+                Ok([]) => return None,
+                Ok(buf) => (buf.iter().position(|b| !b.is_ascii_whitespace()), buf.len()),
+                Err(e) => return Some(Err(e)),
+            };
+
+            match idx {
+                Some(idx) => {
+                    self.advance_by(idx);
+                    return Some(Err(self.error("trailing characters after the top-level array")));
+                }
+                None => {
+                    self.advance_by(len);
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    /// Moves the position over the next `n` buffered bytes without consuming them.
+    fn advance_by(&mut self, n: usize) {
+        let Ok(buf) = self.reader.fill_buf() else { return };
+
+        for byte in &buf[..n] {
+            self.offset += 1;
+
+            if *byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ArrayElements<R> {
+    type Item = Result<Element, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if self.closed {
+            return self.check_trailing();
+        }
+
+        let mut el = Element { bytes: Vec::new(), line: 0, column: 0, offset: 0 };
+        let mut depth = 0usize;
+        let mut in_str = false;
+        let mut escaped = false;
+
+        loop {
+            let buf = match self.reader.fill_buf() {
+                Ok([]) => {
+                    self.done = true;
+                    return Some(Err(self.error("EOF while parsing the top-level array")));
+                }
+                Ok(buf) => buf,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+
+            let mut used = 0;
+            // whether the element ended and the array is closed
+            let mut end: Option<bool> = None;
+
+            for byte in buf {
+                let byte = *byte;
+                used += 1;
+
+                if el.bytes.is_empty() && !byte.is_ascii_whitespace() && byte != b',' {
+                    (el.line, el.column, el.offset) = (self.line, self.column, self.offset);
+                }
+
+                self.offset += 1;
+                if byte == b'\n' {
+                    self.line += 1;
+                    self.column = 1;
+                } else {
+                    self.column += 1;
+                }
+
+                if in_str {
+                    el.bytes.push(byte);
+
+                    if escaped {
+                        escaped = false;
+                    } else if byte == b'\\' {
+                        escaped = true;
+                    } else if byte == b'"' {
+                        in_str = false;
+                    }
+
+                    continue;
+                }
+
+                match byte {
+                    b'"' => {
+                        in_str = true;
+                        el.bytes.push(byte);
+                    }
+                    b'{' | b'[' => {
+                        depth += 1;
+                        el.bytes.push(byte);
+                    }
+                    b'}' | b']' if depth > 0 => {
+                        depth -= 1;
+                        el.bytes.push(byte);
+                    }
+                    b']' => {
+                        end = Some(true);
+                        break;
+                    }
+                    b',' if depth == 0 => {
+                        end = Some(false);
+                        break;
+                    }
+                    _ if byte.is_ascii_whitespace() && el.bytes.is_empty() => {}
+                    _ => el.bytes.push(byte),
+                }
+            }
+
+            self.reader.consume(used);
+
+            let Some(closed) = end else { continue };
+
+            let after_comma = std::mem::replace(&mut self.after_comma, !closed);
+            self.closed = closed;
+
+            if !el.bytes.is_empty() {
+                el.bytes.truncate(el.bytes.trim_ascii_end().len());
+                return Some(Ok(el));
+            }
+
+            // `[]` is the only place where an element can be missing
+            if closed && !after_comma {
+                return self.check_trailing();
+            }
+
+            return Some(Err(self.error(if closed {
+                "trailing comma in the top-level array"
+            } else {
+                "expected value before `,`"
+            })));
+        }
+    }
+}
+
+/// Reads JSON input, a top-level array (after leading whitespace) is split into its elements
+/// while anything else is read as a stream of values.
 #[inline]
-pub(crate) fn json_reader(
-    path: &PathBuf,
-) -> serde_json::Deserializer<serde_json::de::IoRead<BufReader<File>>> {
-    let file = File::open(path).dyn_expect(
-        || format!("Failed to open input file: {}", path.to_str().unwrap_or("[input.json]")),
-        1,
-        true,
-    );
-
-    let buffered = BufReader::with_capacity(256 * 1024, file);
-
-    serde_json::Deserializer::from_reader(buffered)
+pub(crate) fn json_reader<R: Read>(reader: R) -> CtxResult<JsonInput<R>, Error> {
+    let lines =
+        Rc::new(RefCell::new(TrackedLines { buf: Vec::new(), start: 0, line: 1, line_start: 0 }));
+
+    // the tracker reads whole chunks so serde_json's byte by byte reads don't go through it
+    let tracker = LineTracker { inner: reader, lines: Some(Rc::clone(&lines)) };
+    let mut buffered = BufReader::with_capacity(CAPACITY, tracker);
+
+    let buf = buffered.fill_buf().context("Failed to read input")?;
+
+    if let Some(idx) = buf.iter().position(|b| !b.is_ascii_whitespace())
+        && buf[idx] == b'['
+    {
+        // elements keep their own positions
+        buffered.get_mut().lines = None;
+
+        let mut elements = ArrayElements {
+            reader: buffered,
+            line: 1,
+            column: 1,
+            offset: 0,
+            after_comma: false,
+            closed: false,
+            done: false,
+        };
+
+        elements.advance_by(idx + 1);
+        elements.reader.consume(idx + 1);
+
+        return Ok(JsonInput::Array(elements));
+    }
+
+    Ok(JsonInput::Values((serde_json::Deserializer::from_reader(buffered), lines)))
+}
+
+/// Converts a serde_json error for an array element into an error that keeps its position in the
+/// input and the element's bytes for the reject file, `record` is the element's 1-based index.
+pub(crate) fn element_error(e: serde_json::Error, el: &Element, record: usize) -> Error {
+    let mut pos = Position { record: Some(record), ..Position::default() };
+    let mut snippet = Snippet::default();
+
+    if e.line() != 0 && e.column() != 0 {
+        // serde_json's position is relative to the element
+        let line_start = match e.line() {
+            1 => 0,
+            line => el
+                .bytes
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'\n')
+                .nth(line - 2)
+                .map_or(el.bytes.len(), |(idx, _)| idx + 1),
+        };
+
+        let (inner, line) = Snippet::locate(&el.bytes, line_start + e.column() - 1);
+        snippet = line;
+
+        pos.line = inner.line.map(|l| l + el.line - 1);
+        pos.column = inner.column;
+        pos.offset = inner.offset.map(|o| o + el.offset);
+
+        if e.line() == 1 {
+            snippet.start = el.column;
+            pos.column = inner.column.map(|c| c + el.column - 1);
+        }
+    }
+
+    // the element is kept on a single line for the reject file
+    let raw = el.bytes.iter().map(|b| if matches!(b, b'\n' | b'\r') { b' ' } else { *b }).collect();
+
+    // serde_json's message ends with the position in the element, it's replaced with the
+    // position in the input
+    let msg = e.to_string();
+    let msg = match msg.rfind(" at line ") {
+        Some(idx) if e.line() != 0 => &msg[..idx],
+        _ => msg.as_str(),
+    };
+
+    let msg = match (pos.line, pos.column) {
+        (Some(line), Some(column)) => {
+            format!("Invalid JSON: {} at line {} column {}", msg, line, column)
+        }
+        _ => format!("Invalid JSON: {}", msg),
+    };
+
+    syntax_error(msg, pos, snippet, raw)
+}
+
+/// Converts a serde_json error into an error that keeps its position and line, `record` is the
+/// 1-based index of the top-level value that failed.
+pub(crate) fn json_error(
+    e: serde_json::Error,
+    lines: &RefCell<TrackedLines>,
+    record: Option<usize>,
+) -> Error {
+    let mut pos = Position {
+        record,
+        line: (e.line() != 0).then_some(e.line()),
+        column: (e.column() != 0).then_some(e.column()),
+        ..Position::default()
+    };
+
+    // IO errors and lines that are no longer kept don't get a code frame
+    let snippet = match pos.line.and_then(|line| lines.borrow().find(line)) {
+        Some((start, snippet)) => {
+            pos.offset = pos.column.map(|c| start + c as u64 - 1);
+            snippet
+        }
+        None => {
+            pos.column = None;
+            Snippet::default()
+        }
+    };
+
+    syntax_error(format!("Invalid JSON: {}", e), pos, snippet, Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::RecordError;
+
+    fn elements(input: &str) -> Vec<Result<Element, Error>> {
+        match json_reader(input.as_bytes()).unwrap() {
+            JsonInput::Array(elements) => elements.collect(),
+            JsonInput::Values(_) => panic!("expected a top-level array"),
+        }
+    }
+
+    #[test]
+    fn test_array_elements() {
+        let els = elements(" [\n  {\"a\": \"x,]\\\"\"},\n  [1, [2]] , 3\n]\n");
+        let els: Vec<Element> = els.into_iter().map(Result::unwrap).collect();
+
+        let bytes: Vec<&[u8]> = els.iter().map(|el| el.bytes.as_slice()).collect();
+        assert_eq!(bytes, [&b"{\"a\": \"x,]\\\"\"}"[..], b"[1, [2]]", b"3"]);
+        assert_eq!((els[1].line, els[1].column, els[1].offset), (3, 3, 23));
+
+        assert!(elements("[]").is_empty());
+        assert!(elements("[1,]").last().unwrap().is_err());
+        assert!(elements("[1] 2").last().unwrap().is_err());
+        assert!(elements("[1, 2").last().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_element_error_position() {
+        let els = elements("[\n  {\"a\": 1}, {\"a\": }\n]");
+        let el = els.into_iter().nth(1).unwrap().unwrap();
+
+        let e = serde_json::from_slice::<serde_json::Value>(&el.bytes).unwrap_err();
+        let err = element_error(e, &el, 2);
+        let record = err.get_ref().and_then(|e| e.downcast_ref::<RecordError>()).unwrap();
+
+        assert_eq!(record.pos.line, Some(2));
+        assert_eq!(record.pos.column, Some(19));
+        assert_eq!(record.pos.offset, Some(20));
+        assert_eq!(err.to_string(), "Invalid JSON: expected value at line 2 column 19");
+    }
+
+    #[test]
+    fn test_value_error_position() {
+        // long enough for the tracker to drop the bytes before the error
+        let input = format!("{}{{\"a\": }}\n", "{\"a\": 1}\n".repeat(100_000));
+
+        let (reader, lines) = match json_reader(input.as_bytes()).unwrap() {
+            JsonInput::Values(values) => values,
+            JsonInput::Array(_) => panic!("expected a stream of values"),
+        };
+
+        let e = reader.into_iter::<serde_json::Value>().find_map(Result::err).unwrap();
+        let err = json_error(e, &lines, None);
+        let record = err.get_ref().and_then(|e| e.downcast_ref::<RecordError>()).unwrap();
+
+        assert_eq!(record.pos.line, Some(100_001));
+        assert_eq!(record.pos.column, Some(7));
+        assert_eq!(record.pos.offset, Some(900_006));
+        assert!(record.is_syntax());
+    }
 }
@@ -1,4 +1,11 @@
+pub(crate) mod arrow_reader;
+pub(crate) mod avro_reader;
+pub(crate) mod cbor_reader;
 pub(crate) mod csv_reader;
 pub(crate) mod json_reader;
+pub(crate) mod msgpack_reader;
 pub(crate) mod ndjson_reader;
+pub(crate) mod parquet_reader;
 pub(crate) mod toml_reader;
+pub(crate) mod xml_reader;
+pub(crate) mod yaml_reader;
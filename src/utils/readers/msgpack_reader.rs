@@ -0,0 +1,121 @@
+use std::io::{BufRead, BufReader, Error, Read};
+
+use rmp::Marker;
+
+use crate::utils::{Position, Snippet, syntax_error};
+
+/// Buffered reader over a stream of concatenated MessagePack values that keeps track of its byte
+/// offset for error positions.
+pub(crate) struct MsgpackReader<R> {
+    reader: R,
+    offset: u64,
+
+    /// Bytes of the value being read.
+    raw: Vec<u8>,
+}
+
+impl<R: BufRead> MsgpackReader<R> {
+    /// Reads the next value, `None` at the end of the input. Values can't be resynchronized after
+    /// an invalid or truncated one, so callers should stop at the first error.
+    pub(crate) fn read_value(&mut self) -> Option<Result<rmpv::Value, Error>> {
+        match self.reader.fill_buf() {
+            Ok([]) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(e)),
+        }
+
+        let start = self.offset;
+        self.raw.clear();
+
+        let value = match rmpv::decode::read_value(self) {
+            Ok(ok) => ok,
+            Err(e) => return Some(Err(msgpack_error(e, start))),
+        };
+
+        // rmpv decodes the reserved marker as nil instead of rejecting it
+        if memchr::memchr(0xc1, &self.raw).is_some()
+            && let Some(idx) = reserved_marker(&self.raw)
+        {
+            return Some(Err(msgpack_error("reserved marker 0xc1", start + idx as u64)));
+        }
+
+        Some(Ok(value))
+    }
+}
+
+impl<R: BufRead> Read for MsgpackReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.offset += n as u64;
+        self.raw.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Index of the first reserved marker in an encoded value, `0xc1` bytes inside strings, binary
+/// and numbers aren't markers so the value's structure is walked to find it.
+fn reserved_marker(raw: &[u8]) -> Option<usize> {
+    // big-endian length at `idx`, the value was already decoded so it's in bounds
+    let len = |idx: usize, size: usize| {
+        raw.get(idx..idx + size).map_or(0, |b| b.iter().fold(0, |acc, b| acc << 8 | *b as usize))
+    };
+
+    let mut idx = 0;
+    let mut pending = 1;
+
+    while pending > 0 && idx < raw.len() {
+        pending -= 1;
+        let marker = Marker::from_u8(raw[idx]);
+        let at = idx;
+        idx += 1;
+
+        match marker {
+            Marker::Reserved => return Some(at),
+            Marker::FixArray(n) => pending += n as usize,
+            Marker::FixMap(n) => pending += n as usize * 2,
+            Marker::Array16 | Marker::Array32 | Marker::Map16 | Marker::Map32 => {
+                let size = if matches!(marker, Marker::Array16 | Marker::Map16) { 2 } else { 4 };
+                let n = len(idx, size);
+                pending += if matches!(marker, Marker::Map16 | Marker::Map32) { n * 2 } else { n };
+                idx += size;
+            }
+            Marker::FixStr(n) => idx += n as usize,
+            Marker::Str8 | Marker::Bin8 => idx += 1 + len(idx, 1),
+            Marker::Str16 | Marker::Bin16 => idx += 2 + len(idx, 2),
+            Marker::Str32 | Marker::Bin32 => idx += 4 + len(idx, 4),
+            Marker::U8 | Marker::I8 => idx += 1,
+            Marker::U16 | Marker::I16 => idx += 2,
+            Marker::U32 | Marker::I32 | Marker::F32 => idx += 4,
+            Marker::U64 | Marker::I64 | Marker::F64 => idx += 8,
+            Marker::FixExt1 => idx += 2,
+            Marker::FixExt2 => idx += 3,
+            Marker::FixExt4 => idx += 5,
+            Marker::FixExt8 => idx += 9,
+            Marker::FixExt16 => idx += 17,
+            Marker::Ext8 => idx += 2 + len(idx, 1),
+            Marker::Ext16 => idx += 3 + len(idx, 2),
+            Marker::Ext32 => idx += 5 + len(idx, 4),
+            Marker::FixPos(_) | Marker::FixNeg(_) | Marker::Null | Marker::True | Marker::False => {
+            }
+        }
+    }
+
+    None
+}
+
+#[inline]
+pub(crate) fn msgpack_reader<R: Read>(reader: R) -> MsgpackReader<BufReader<R>> {
+    MsgpackReader {
+        reader: BufReader::with_capacity(256 * 1024, reader),
+        offset: 0,
+        raw: Vec::new(),
+    }
+}
+
+/// Creates an error for an invalid MessagePack value starting at byte `offset`.
+#[inline]
+pub(crate) fn msgpack_error(msg: impl std::fmt::Display, offset: u64) -> Error {
+    let pos = Position { offset: Some(offset), ..Position::default() };
+
+    syntax_error(format!("Invalid MessagePack: {}", msg), pos, Snippet::default(), Vec::new())
+}
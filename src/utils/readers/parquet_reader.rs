@@ -0,0 +1,25 @@
+use std::io::{Error, ErrorKind as EK, Read};
+
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+
+/// Number of rows in the record batches read from a row group.
+const BATCH_SIZE: usize = 8192;
+
+/// Reads a Parquet file into record batches. The footer with the file's metadata is at the end
+/// of the file, so the input is read into memory as a whole before its row groups are decoded
+/// one batch at a time.
+pub(crate) fn parquet_reader<R: Read>(mut reader: R) -> Result<ParquetRecordBatchReader, Error> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+
+    ParquetRecordBatchReaderBuilder::try_new(Bytes::from(buf))
+        .and_then(|builder| builder.with_batch_size(BATCH_SIZE).build())
+        .map_err(parquet_error)
+}
+
+/// Creates an error for an invalid Parquet file or row group.
+#[inline]
+pub(crate) fn parquet_error(e: impl std::fmt::Display) -> Error {
+    Error::new(EK::InvalidData, format!("Invalid Parquet: {}", e))
+}
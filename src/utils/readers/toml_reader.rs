@@ -1,12 +1,27 @@
-use std::path::PathBuf;
+use std::io::{Error, Read};
 
-use resext::ResExt;
+use resext::{CtxResult, ResExt};
+
+use crate::utils::{Snippet, syntax_error};
 
 #[inline]
-pub(crate) fn toml_reader(path: &PathBuf) -> Vec<u8> {
-    std::fs::read(path).dyn_expect(
-        || format!("Failed to read input file: {}", path.to_str().unwrap_or("[input.toml]")),
-        1,
-        true,
-    )
+pub(crate) fn toml_reader<R: Read>(mut reader: R) -> CtxResult<Vec<u8>, Error> {
+    let mut content = Vec::new();
+
+    reader.read_to_end(&mut content).context("Failed to read input")?;
+
+    Ok(content)
+}
+
+/// Converts a toml error into an error that keeps its position and line in `content`.
+pub(crate) fn toml_error(e: toml::de::Error, content: &[u8]) -> Error {
+    let msg = format!("Invalid TOML: {}", e.message().trim_end());
+
+    match e.span() {
+        Some(span) => {
+            let (pos, snippet) = Snippet::locate(content, span.start);
+            syntax_error(msg, pos, snippet, Vec::new())
+        }
+        None => syntax_error(msg, Default::default(), Snippet::default(), Vec::new()),
+    }
 }
@@ -0,0 +1,46 @@
+use std::io::{BufReader, Error, Read};
+
+use quick_xml::{Reader, events::BytesRef};
+
+use crate::utils::{Position, Snippet, syntax_error};
+
+#[inline]
+pub(crate) fn xml_reader<R: Read>(reader: R) -> Reader<BufReader<R>> {
+    let buffered = BufReader::with_capacity(256 * 1024, reader);
+
+    let mut reader = Reader::from_reader(buffered);
+    reader.config_mut().check_end_names = true;
+
+    reader
+}
+
+/// Whether `c` is a control character that XML 1.0 doesn't allow anywhere (every C0 control but
+/// tab, line feed and carriage return), not even as a character reference.
+#[inline]
+pub(crate) fn is_restricted(c: char) -> bool {
+    c < ' ' && !matches!(c, '\t' | '\n' | '\r')
+}
+
+/// Resolves a character or predefined entity reference (e.g. `&#65;` or `&amp;`), unknown
+/// entities are kept as they are.
+pub(crate) fn resolve_ref(e: &BytesRef) -> String {
+    match e.resolve_char_ref() {
+        Ok(Some(c)) => c.to_string(),
+        _ => {
+            let name = e.xml10_content();
+
+            match quick_xml::escape::resolve_predefined_entity(&name) {
+                Some(resolved) => resolved.to_string(),
+                None => format!("&{};", name),
+            }
+        }
+    }
+}
+
+/// Converts an XML error into an error that keeps its byte offset, XML can't be recovered after
+/// a syntax error so the input isn't read any further.
+pub(crate) fn xml_error(msg: impl std::fmt::Display, offset: u64) -> Error {
+    let pos = Position { offset: Some(offset), ..Position::default() };
+
+    syntax_error(format!("Invalid XML: {}", msg), pos, Snippet::default(), Vec::new())
+}
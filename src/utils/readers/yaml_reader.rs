@@ -0,0 +1,8 @@
+use std::io::{BufReader, Read};
+
+#[inline]
+pub(crate) fn yaml_reader<'a, R: Read + 'a>(reader: R) -> serde_norway::Deserializer<'a> {
+    let buffered = BufReader::with_capacity(256 * 1024, reader);
+
+    serde_norway::Deserializer::from_reader(buffered)
+}
@@ -0,0 +1,292 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Error, ErrorKind as EK, Write},
+};
+
+use csv::ByteRecord;
+use resext::{CtxResult, ErrCtx, ResExt};
+
+use crate::utils::{DataTypes, Options, Position, Snippet, WriterStreams};
+
+/// Payload of errors for single records that failed to decode, it keeps the record's original bytes
+/// so the record can be written into the reject file.
+#[derive(Debug)]
+pub(crate) struct RecordError {
+    msg: String,
+    pub(crate) pos: Position,
+    raw: Vec<u8>,
+    snippet: Option<Snippet>,
+}
+
+impl RecordError {
+    /// Whether the error is a syntax error that kept the line it happened in.
+    pub(crate) fn is_syntax(&self) -> bool {
+        self.snippet.is_some()
+    }
+
+    /// Renders the line the error happened in with a caret under its column, records without a
+    /// separate snippet use their original bytes as the line.
+    pub(crate) fn frame(&self) -> Option<String> {
+        let column = self.pos.column?;
+
+        match &self.snippet {
+            Some(snippet) => snippet.frame(self.pos.line, column),
+            None => Snippet { line: self.raw.trim_ascii_end().to_vec(), start: 1 }
+                .frame(self.pos.line, column),
+        }
+    }
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+/// Payload of errors for input that can't be read any further (e.g. truncated compressed input),
+/// these end the conversion instead of skipping a record.
+#[derive(Debug)]
+pub(crate) struct ReadError(String);
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+/// Marks a failure to read the input as fatal, see `ReadError`.
+#[inline]
+pub(crate) fn read_error(e: impl Into<Error>) -> Error {
+    let e = e.into();
+    Error::new(e.kind(), ReadError(e.to_string()))
+}
+
+/// Creates an error for the invalid record at `pos` whose original bytes are `raw`.
+#[inline]
+pub(crate) fn record_error(msg: impl Into<String>, pos: Position, raw: Vec<u8>) -> Error {
+    Error::new(EK::InvalidData, RecordError { msg: msg.into(), pos, raw, snippet: None })
+}
+
+/// Creates an error for a syntax error at `pos` that keeps the line it happened in, `raw` is the
+/// original bytes of the record for the reject file and is empty for whole documents.
+#[inline]
+pub(crate) fn syntax_error(
+    msg: impl Into<String>,
+    pos: Position,
+    snippet: Snippet,
+    raw: Vec<u8>,
+) -> Error {
+    Error::new(EK::InvalidData, RecordError { msg: msg.into(), pos, raw, snippet: Some(snippet) })
+}
+
+/// Reject file of a conversion, table rows that fail to encode are written with the input's CSV
+/// delimiter and quote.
+struct Rejects {
+    file: BufWriter<File>,
+    delimiter: u8,
+    quote: u8,
+}
+
+/// Record that a writer failed to encode.
+pub(crate) enum Rejected<'a> {
+    /// Table row, written with the input's CSV dialect.
+    Row(&'a ByteRecord),
+
+    /// Record of any other format, written as a JSON line.
+    Value(&'a serde_json::Value),
+
+    /// Decoded record, written like its row or value.
+    Record(&'a DataTypes),
+}
+
+/// State of a single conversion shared by the quarantine and the writers: its error budget and
+/// reject file.
+pub(crate) struct Conversion {
+    /// Number of records skipped so far.
+    skipped: Cell<usize>,
+
+    /// Number of skipped records after which the conversion is aborted.
+    max_errors: Option<usize>,
+
+    rejects: RefCell<Option<Rejects>>,
+}
+
+impl Conversion {
+    /// Starts a conversion without a reject file, the reject file is opened by `quarantine`.
+    pub(crate) fn new(max_errors: Option<usize>) -> Self {
+        Conversion { skipped: Cell::new(0), max_errors, rejects: RefCell::new(None) }
+    }
+
+    /// Number of records skipped so far.
+    #[inline]
+    pub(crate) fn skipped(&self) -> usize {
+        self.skipped.get()
+    }
+
+    /// Logs the error of a record that is skipped, returns an error once the error budget is used
+    /// up so the conversion gets aborted. Read errors aren't skipped but returned as they are.
+    #[inline]
+    pub(crate) fn log_skip<E: std::error::Error + 'static>(
+        &self,
+        err: &ErrCtx<E>,
+    ) -> CtxResult<(), Error> {
+        let source: &(dyn std::error::Error + 'static) = &err.source;
+
+        if let Some(e) = source.downcast_ref::<Error>()
+            && let Some(read) = e.get_ref().and_then(|inner| inner.downcast_ref::<ReadError>())
+        {
+            let e = Error::new(e.kind(), ReadError(read.0.clone()));
+            return Err(ErrCtx::new(e, err.msg.clone()));
+        }
+
+        let skipped = self.skipped.get();
+
+        if self.max_errors.is_none_or(|max| skipped < max) {
+            crate::utils::log_skipped(err);
+            self.skipped.set(skipped + 1);
+        }
+
+        match self.max_errors {
+            Some(max) if skipped + 1 >= max => {
+                Err(Error::new(EK::InvalidData, "Error budget exceeded"))
+                    .with_context(|| format!("Conversion aborted after {} invalid record(s)", max))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes a `<line>\t<raw record>` line into the reject file (if any), the file is dropped
+    /// once a write fails so a failing file doesn't log every record.
+    fn write_reject(&self, line: usize, raw: impl FnOnce(&Rejects) -> Vec<u8>) {
+        let mut rejects = self.rejects.borrow_mut();
+        let Some(wtr) = rejects.as_mut() else { return };
+        let raw = raw(wtr);
+
+        let res = write!(wtr.file, "{}\t", line)
+            .and_then(|_| wtr.file.write_all(&raw))
+            .and_then(|_| wtr.file.write_all(b"\n"))
+            .and_then(|_| wtr.file.flush())
+            .with_context(|| format!("Failed to write record at line: {} into reject file", line));
+
+        if let Err(err) = res {
+            crate::utils::log_err::<Error>(&err).unwrap_or_else(|e| eprintln!("{}\n{}", e, &err));
+            *rejects = None;
+        }
+    }
+
+    /// Logs and skips a record that a writer failed to encode, the record is also written into the
+    /// reject file (if any). `line` is used for records that don't know their input line (e.g. the
+    /// record number of JSON records).
+    pub(crate) fn skip_rejected<E: std::error::Error + 'static>(
+        &self,
+        err: &ErrCtx<E>,
+        line: usize,
+        record: Rejected,
+    ) -> CtxResult<(), Error> {
+        let budget = self.log_skip(err);
+
+        match record {
+            Rejected::Row(rec) => {
+                let line = rec.position().map_or(line, |p| p.line() as usize);
+                self.write_reject(line, |wtr| raw_row(rec, wtr.delimiter, wtr.quote));
+            }
+            Rejected::Value(value) => {
+                self.write_reject(line, |_| serde_json::to_vec(value).unwrap_or_default());
+            }
+            Rejected::Record(DataTypes::Json(value)) => {
+                return self.skip_rejected(err, line, Rejected::Value(value));
+            }
+            Rejected::Record(DataTypes::Toml(value)) => {
+                let value = crate::utils::toml_to_json(value.clone());
+                self.write_reject(line, |_| serde_json::to_vec(&value).unwrap_or_default());
+            }
+            Rejected::Record(DataTypes::Csv(rec)) => {
+                return self.skip_rejected(err, line, Rejected::Row(rec));
+            }
+        }
+
+        budget
+    }
+
+    /// Closes the reject file.
+    #[inline]
+    pub(crate) fn close_rejects(&self) {
+        self.rejects.take();
+    }
+}
+
+/// Re-encodes a row into CSV bytes (without the line terminator) for the reject file.
+#[inline]
+pub(crate) fn raw_row(rec: &ByteRecord, delimiter: u8, quote: u8) -> Vec<u8> {
+    let mut wtr =
+        csv::WriterBuilder::new().delimiter(delimiter).quote(quote).from_writer(Vec::new());
+
+    if wtr.write_byte_record(rec).is_err() {
+        return Vec::new();
+    }
+
+    let mut raw = wtr.into_inner().unwrap_or_default();
+    if raw.last() == Some(&b'\n') {
+        raw.pop();
+    }
+    raw
+}
+
+/// Logs and drops every record in `data` that failed to decode, so invalid records never reach the
+/// writers unless the error budget is used up. Records that kept their original bytes are also
+/// written into `Options::reject_file` (if any) as `<line>\t<raw record>` lines, the file stays
+/// open for the writers until `Conversion::close_rejects`.
+pub(crate) fn quarantine<'a, I>(
+    data: WriterStreams<I>,
+    options: &Options,
+    conv: &'a Conversion,
+) -> CtxResult<WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>> + 'a>, Error>
+where
+    I: Iterator<Item = CtxResult<DataTypes, Error>> + 'a,
+{
+    let rejects = match &options.reject_file {
+        Some(path) => Some(Rejects {
+            file: BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(path)
+                    .with_context(|| {
+                        format!("Failed to open reject file: {}", path.to_string_lossy())
+                    })?,
+            ),
+            // only CSV input uses them, where the reader already checked that they're ASCII
+            delimiter: options.input_delimiter as u8,
+            quote: options.input_dialect.quote as u8,
+        }),
+        None => None,
+    };
+    conv.rejects.replace(rejects);
+
+    Ok(data.map_iter(move |iter| {
+        iter.filter_map(move |item| {
+            let e = match item {
+                Ok(ok) => return Some(Ok(ok)),
+                Err(e) => e,
+            };
+
+            let budget = conv.log_skip(&e);
+
+            let record = e.source.get_ref().and_then(|s| s.downcast_ref::<RecordError>());
+
+            if let Some(record) = record.filter(|r| !r.raw.is_empty()) {
+                conv.write_reject(record.pos.line.unwrap_or_default(), |_| record.raw.clone());
+            }
+
+            // the writers stop the conversion when they get an error after the budget is used up
+            budget.err().map(Err)
+        })
+    }))
+}
@@ -0,0 +1,598 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufWriter, Error, Write};
+
+use clap::ValueEnum;
+use resext::{CtxResult, ResExt};
+use serde_json::{Value, json};
+
+use crate::utils::{
+    Cell, Conversion, DataTypes, Inference, Options, WriterStreams, datetime, into_byte_record,
+};
+
+/// Formats of the `schema` report
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SchemaFormat {
+    /// Human-readable table with a row per field
+    Table,
+
+    /// JSON Schema (draft 2020-12) document
+    JsonSchema,
+}
+
+/// Types of non-null values, ordered like they're reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Date,
+    Datetime,
+    Array,
+    Object,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Boolean => "boolean",
+            Kind::Integer => "integer",
+            Kind::Number => "number",
+            Kind::String => "string",
+            Kind::Date => "date",
+            Kind::Datetime => "datetime",
+            Kind::Array => "array",
+            Kind::Object => "object",
+        }
+    }
+
+    fn json_type(self) -> &'static str {
+        match self {
+            Kind::String | Kind::Date | Kind::Datetime => "string",
+            kind => kind.name(),
+        }
+    }
+}
+
+/// Number of examples kept for every field.
+const EXAMPLES: usize = 3;
+
+/// Number of HyperLogLog registers (2^10, about 3% error).
+const REGISTERS: usize = 1 << 10;
+
+/// Number of distinct values that are counted exactly before only the sketch is kept.
+const EXACT: usize = 10_000;
+
+/// Distinct-count estimate, exact for fields with few distinct values and a HyperLogLog sketch
+/// for the rest.
+struct Distinct {
+    exact: Option<HashSet<u64>>,
+    registers: Vec<u8>,
+}
+
+impl Distinct {
+    fn new() -> Self {
+        Distinct { exact: Some(HashSet::new()), registers: vec![0; REGISTERS] }
+    }
+
+    fn insert(&mut self, value: &Value) {
+        let mut hasher = DefaultHasher::new();
+        value.to_string().hash(&mut hasher);
+        let hash = hasher.finish();
+
+        // the first 10 bits pick the register, the rest are ranked by their leading zeros
+        let idx = (hash >> 54) as usize;
+        let rank = (hash << 10).leading_zeros().min(54) as u8 + 1;
+        self.registers[idx] = self.registers[idx].max(rank);
+
+        if let Some(exact) = &mut self.exact {
+            exact.insert(hash);
+
+            if exact.len() > EXACT {
+                self.exact = None;
+            }
+        }
+    }
+
+    /// Number of distinct values and whether it's an estimate.
+    fn count(&self) -> (usize, bool) {
+        if let Some(exact) = &self.exact {
+            return (exact.len(), false);
+        }
+
+        let m = REGISTERS as f64;
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-i32::from(*r))).sum();
+        let estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+
+        // linear counting is more accurate while many registers are still empty
+        let estimate =
+            if estimate <= 2.5 * m && zeros > 0 { m * (m / zeros as f64).ln() } else { estimate };
+
+        (estimate.round() as usize, true)
+    }
+}
+
+/// Statistics of a field, nested fields are profiled under their object's profile and array
+/// elements under `items`. The root profile holds the records themselves.
+pub(crate) struct Profile {
+    /// Number of values (including nulls) the field had.
+    present: usize,
+    nulls: usize,
+    kinds: BTreeMap<Kind, usize>,
+
+    /// Smallest and largest number with the value it was read from.
+    min: Option<(f64, Value)>,
+    max: Option<(f64, Value)>,
+
+    /// Smallest and largest string (dates sort chronologically) and their lengths.
+    min_str: Option<String>,
+    max_str: Option<String>,
+    lengths: Option<(usize, usize)>,
+
+    distinct: Distinct,
+    examples: Vec<Value>,
+
+    /// Fields of objects in order of their first appearance.
+    fields: Vec<(String, Profile)>,
+    index: HashMap<String, usize>,
+
+    items: Option<Box<Profile>>,
+}
+
+impl Profile {
+    fn new() -> Self {
+        Profile {
+            present: 0,
+            nulls: 0,
+            kinds: BTreeMap::new(),
+            min: None,
+            max: None,
+            min_str: None,
+            max_str: None,
+            lengths: None,
+            distinct: Distinct::new(),
+            examples: Vec::new(),
+            fields: Vec::new(),
+            index: HashMap::new(),
+            items: None,
+        }
+    }
+
+    fn field(&mut self, key: &str) -> &mut Profile {
+        let idx = match self.index.get(key) {
+            Some(idx) => *idx,
+            None => {
+                self.fields.push((key.to_string(), Profile::new()));
+                self.index.insert(key.to_string(), self.fields.len() - 1);
+                self.fields.len() - 1
+            }
+        };
+
+        &mut self.fields[idx].1
+    }
+
+    fn objects(&self) -> usize {
+        self.kinds.get(&Kind::Object).copied().unwrap_or(0)
+    }
+
+    fn observe(&mut self, value: &Value) {
+        self.present += 1;
+
+        match value {
+            Value::Null => self.nulls += 1,
+            Value::Bool(_) => self.scalar(Kind::Boolean, value, None),
+            Value::Number(n) => {
+                let kind = if n.is_f64() { Kind::Number } else { Kind::Integer };
+                self.scalar(kind, value, n.as_f64());
+            }
+            Value::String(s) => {
+                let kind = if datetime(s, true).is_some() {
+                    Kind::Date
+                } else if datetime(s, false).is_some() {
+                    Kind::Datetime
+                } else {
+                    Kind::String
+                };
+                self.scalar(kind, value, None);
+            }
+            Value::Array(arr) => {
+                *self.kinds.entry(Kind::Array).or_default() += 1;
+                let items = self.items.get_or_insert_with(|| Box::new(Profile::new()));
+                arr.iter().for_each(|v| items.observe(v));
+            }
+            Value::Object(map) => {
+                *self.kinds.entry(Kind::Object).or_default() += 1;
+                map.iter().for_each(|(k, v)| self.field(k).observe(v));
+            }
+        }
+    }
+
+    /// Profiles a table field, empty fields are nulls.
+    fn observe_cell(&mut self, cell: &Cell) {
+        match cell {
+            Cell::Null | Cell::Str(b"") => self.observe(&Value::Null),
+            Cell::Bool(b) => self.observe(&Value::Bool(*b)),
+            Cell::Int(i) => self.observe(&Value::from(*i)),
+            Cell::Float(f) => self.observe(&Value::from(*f)),
+            Cell::BigInt(s) => {
+                self.present += 1;
+                let value = s.parse::<u64>().map_or(Value::String(s.to_string()), Value::from);
+                self.scalar(Kind::Integer, &value, s.parse().ok());
+            }
+            Cell::Datetime(dt) => {
+                self.present += 1;
+                let kind = if dt.time.is_none() { Kind::Date } else { Kind::Datetime };
+                self.scalar(kind, &Value::String(dt.to_string()), None);
+            }
+            Cell::Str(s) => {
+                self.present += 1;
+                let value = Value::String(String::from_utf8_lossy(s).into_owned());
+                self.scalar(Kind::String, &value, None);
+            }
+            Cell::List(_) => self.observe(&cell.to_json()),
+        }
+    }
+
+    fn scalar(&mut self, kind: Kind, value: &Value, num: Option<f64>) {
+        *self.kinds.entry(kind).or_default() += 1;
+
+        if let Some(n) = num {
+            if self.min.as_ref().is_none_or(|(min, _)| n < *min) {
+                self.min = Some((n, value.clone()));
+            }
+            if self.max.as_ref().is_none_or(|(max, _)| n > *max) {
+                self.max = Some((n, value.clone()));
+            }
+        }
+
+        if let (Kind::String | Kind::Date | Kind::Datetime, Value::String(s)) = (kind, value) {
+            if self.min_str.as_ref().is_none_or(|min| s < min) {
+                self.min_str = Some(s.clone());
+            }
+            if self.max_str.as_ref().is_none_or(|max| s > max) {
+                self.max_str = Some(s.clone());
+            }
+
+            let len = s.chars().count();
+            let (min, max) = self.lengths.unwrap_or((len, len));
+            self.lengths = Some((min.min(len), max.max(len)));
+        }
+
+        self.distinct.insert(value);
+
+        if self.examples.len() < EXAMPLES && !self.examples.contains(value) {
+            self.examples.push(value.clone());
+        }
+    }
+
+    /// Smallest and largest value of fields with numbers or strings, fields with both have no
+    /// bounds since they can't be compared.
+    fn bounds(&self) -> Option<(String, String)> {
+        let plain = |v: &Value| match v {
+            Value::String(s) => s.clone(),
+            v => v.to_string(),
+        };
+
+        match (&self.min, &self.max, &self.min_str, &self.max_str) {
+            (Some((_, min)), Some((_, max)), None, None) => Some((plain(min), plain(max))),
+            (None, None, Some(min), Some(max)) => Some((min.clone(), max.clone())),
+            _ => None,
+        }
+    }
+
+    /// Adds a row for every field under this profile (and for the profile itself unless it only
+    /// held objects), `parent` is the number of objects the field could have been in.
+    fn rows(&self, path: &str, parent: usize, rows: &mut Vec<[String; 8]>) {
+        let scalars = self.kinds.keys().any(|k| *k != Kind::Object) || self.nulls > 0;
+
+        if !path.is_empty() && (scalars || self.fields.is_empty()) {
+            let types = match self.kinds.len() {
+                0 => "null".to_string(),
+                1 => self.kinds.keys().map(|k| k.name()).collect(),
+                _ => self
+                    .kinds
+                    .iter()
+                    .map(|(k, n)| format!("{} ({})", k.name(), n))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+
+            let (min, max) = self.bounds().unwrap_or_default();
+
+            let distinct = match self.distinct.count() {
+                (0, _) => String::new(),
+                (n, true) => format!("~{}", n),
+                (n, false) => n.to_string(),
+            };
+
+            let examples: Vec<String> = self.examples.iter().map(Value::to_string).collect();
+
+            rows.push([
+                path.to_string(),
+                types,
+                format!("{}/{}", self.present, parent),
+                self.nulls.to_string(),
+                min,
+                max,
+                distinct,
+                examples.join(", "),
+            ]);
+        }
+
+        for (key, field) in &self.fields {
+            let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+            field.rows(&path, self.objects(), rows);
+        }
+
+        if let Some(items) = &self.items {
+            let path = if path.is_empty() { "[]".to_string() } else { format!("{}[]", path) };
+            items.rows(&path, items.present, rows);
+        }
+    }
+
+    fn json_schema(&self) -> Value {
+        let mut schema = serde_json::Map::new();
+
+        let mut types: Vec<&str> = Vec::new();
+        for kind in self.kinds.keys() {
+            if !types.contains(&kind.json_type()) {
+                types.push(kind.json_type());
+            }
+        }
+
+        // integers are numbers too
+        if types.contains(&"number") {
+            types.retain(|t| *t != "integer");
+        }
+
+        if self.nulls > 0 {
+            types.push("null");
+        }
+
+        match types.as_slice() {
+            [] => {}
+            [ty] => _ = schema.insert("type".to_string(), json!(ty)),
+            types => _ = schema.insert("type".to_string(), json!(types)),
+        }
+
+        let strings: Vec<Kind> = self
+            .kinds
+            .keys()
+            .copied()
+            .filter(|k| matches!(k, Kind::String | Kind::Date | Kind::Datetime))
+            .collect();
+
+        match strings.as_slice() {
+            [Kind::Date] => _ = schema.insert("format".to_string(), json!("date")),
+            [Kind::Datetime] => _ = schema.insert("format".to_string(), json!("date-time")),
+            _ => {}
+        }
+
+        // numbers that don't fit into 64 bits are kept as strings
+        let number = |(n, v): &(f64, Value)| if v.is_number() { v.clone() } else { json!(n) };
+
+        if let (Some(min), Some(max)) = (&self.min, &self.max) {
+            schema.insert("minimum".to_string(), number(min));
+            schema.insert("maximum".to_string(), number(max));
+        }
+
+        if let Some((min, max)) = self.lengths {
+            schema.insert("minLength".to_string(), json!(min));
+            schema.insert("maxLength".to_string(), json!(max));
+        }
+
+        if self.kinds.contains_key(&Kind::Object) {
+            let properties: serde_json::Map<String, Value> =
+                self.fields.iter().map(|(k, f)| (k.clone(), f.json_schema())).collect();
+
+            let required: Vec<&str> = self
+                .fields
+                .iter()
+                .filter(|(_, f)| f.present == self.objects())
+                .map(|(k, _)| k.as_str())
+                .collect();
+
+            schema.insert("properties".to_string(), Value::Object(properties));
+            schema.insert("required".to_string(), json!(required));
+        }
+
+        if let Some(items) = &self.items {
+            schema.insert("items".to_string(), items.json_schema());
+        }
+
+        if !self.examples.is_empty() {
+            schema.insert("examples".to_string(), json!(self.examples));
+        }
+
+        Value::Object(schema)
+    }
+}
+
+/// Streams the records of `data` into a profile of their fields, table fields are typed like
+/// `--parse-numbers` types them (with `Options::types` overrides).
+pub(crate) fn profile<I>(
+    data: WriterStreams<I>,
+    options: &Options,
+    conv: &Conversion,
+) -> CtxResult<Profile, Error>
+where
+    I: Iterator<Item = CtxResult<DataTypes, Error>>,
+{
+    let mut root = Profile::new();
+
+    match data {
+        WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, true, &options.types)?;
+
+            // columns are reported in header order even if they're always empty
+            headers.iter().for_each(|h| _ = root.field(h));
+
+            for (line_no, rec) in iter.enumerate() {
+                let line_no = line_no + 1;
+
+                let record = match into_byte_record(rec).context("Failed to read record") {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let cells = match inference
+                    .row(&record)
+                    .with_context(|| format!("Failed to infer the types of record: {}", line_no))
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                root.present += 1;
+                *root.kinds.entry(Kind::Object).or_default() += 1;
+
+                for (h, cell) in headers.iter().zip(&cells) {
+                    root.field(h).observe_cell(cell);
+                }
+            }
+        }
+
+        WriterStreams::Values { iter } | WriterStreams::Ndjson { values: iter } => {
+            for rec in iter {
+                let rec = match rec.and_then(DataTypes::into_json).context("Failed to read record")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                // sequences of records (e.g. YAML documents) are profiled element by element
+                match rec {
+                    Value::Array(arr) => arr.iter().for_each(|v| root.observe(v)),
+                    value => root.observe(&value),
+                }
+            }
+        }
+    }
+
+    Ok(root)
+}
+
+/// Writes the profile as a table of fields or as a JSON Schema document.
+pub(crate) fn write_schema<W: Write>(
+    root: &Profile,
+    writer: W,
+    format: SchemaFormat,
+) -> CtxResult<(), Error> {
+    let mut writer = BufWriter::new(writer);
+
+    match format {
+        SchemaFormat::JsonSchema => {
+            let mut schema = root.json_schema();
+
+            if let Value::Object(map) = schema {
+                let mut with_dialect = serde_json::Map::with_capacity(map.len() + 1);
+                with_dialect.insert(
+                    "$schema".to_string(),
+                    json!("https://json-schema.org/draft/2020-12/schema"),
+                );
+                with_dialect.extend(map);
+                schema = Value::Object(with_dialect);
+            }
+
+            serde_json::to_writer_pretty(&mut writer, &schema)
+                .map_err(|_| Error::other("Failed to write into file"))
+                .context("Failed to write JSON Schema into output")?;
+
+            writeln!(writer).context("Failed to write newline into output")?;
+        }
+
+        SchemaFormat::Table => {
+            let mut rows = vec![
+                ["FIELD", "TYPES", "PRESENT", "NULLS", "MIN", "MAX", "DISTINCT", "EXAMPLES"]
+                    .map(String::from),
+            ];
+            root.rows("", root.present, &mut rows);
+
+            // long values are cut so every row fits on a line
+            for row in &mut rows {
+                for cell in row.iter_mut() {
+                    if cell.chars().count() > 40 {
+                        *cell = cell.chars().take(39).chain(['…']).collect();
+                    }
+                }
+            }
+
+            let widths: Vec<usize> = (0..8)
+                .map(|c| rows.iter().map(|r| r[c].chars().count()).max().unwrap_or(0))
+                .collect();
+
+            writeln!(writer, "Records: {}\n", root.present)
+                .context("Failed to write record count into output")?;
+
+            for row in &rows {
+                let line: Vec<String> = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, w)| format!("{:<w$}", cell, w = w))
+                    .collect();
+
+                writeln!(writer, "{}", line.join("  ").trim_end())
+                    .context("Failed to write field into output")?;
+            }
+        }
+    }
+
+    writer.flush().context("Failed to flush final bytes into output")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_records() {
+        let mut root = Profile::new();
+
+        for value in [
+            json!({"id": 1, "email": "a@x.io", "since": "2024-05-01", "tags": ["a"]}),
+            json!({"id": 2.5, "email": null, "since": "2023-01-02", "tags": []}),
+            json!({"id": 3, "since": "2024-05-01"}),
+        ] {
+            root.observe(&value);
+        }
+
+        let schema = root.json_schema();
+
+        assert_eq!(schema["required"], json!(["id", "since"]));
+        assert_eq!(
+            schema["properties"]["id"],
+            json!({"type": "number", "minimum": 1, "maximum": 3, "examples": [1, 2.5, 3]})
+        );
+        assert_eq!(schema["properties"]["email"]["type"], json!(["string", "null"]));
+        assert_eq!(schema["properties"]["since"]["format"], json!("date"));
+        assert_eq!(schema["properties"]["tags"]["items"]["type"], json!("string"));
+
+        let since = &root.fields[2].1;
+        assert_eq!(since.distinct.count(), (2, false));
+        assert_eq!(since.bounds(), Some(("2023-01-02".to_string(), "2024-05-01".to_string())));
+
+        // numbers and strings can't be compared
+        let mut zip = Profile::new();
+        [json!("02134"), json!(10001)].iter().for_each(|v| zip.observe(v));
+        assert_eq!(zip.bounds(), None);
+
+        // the sketch stays close once there are too many values to count exactly
+        let mut distinct = Distinct::new();
+        (0..50_000).for_each(|i| distinct.insert(&json!(i)));
+        let (count, estimate) = distinct.count();
+        assert!(estimate && count.abs_diff(50_000) < 2_500);
+    }
+}
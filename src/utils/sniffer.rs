@@ -0,0 +1,438 @@
+use std::io::{Cursor, Error, ErrorKind as EK, Read};
+
+use resext::{CtxResult, ResExt};
+use serde::de::IgnoredAny;
+
+/// Number of bytes read from the start of the input for detecting its format.
+const SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Maximum number of lines inspected for line-based formats (NDJSON, TOML, YAML, CSV).
+const SAMPLE_LINES: usize = 50;
+
+/// Candidate delimiters for CSV-like formats (CSV, TSV, PSV, SSV).
+const DELIMITERS: [u8; 4] = [b',', b'\t', b'|', b';'];
+
+/// Result of detecting an input's format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Detection {
+    /// Detected format name (e.g. `"ndjson"`).
+    pub format: String,
+
+    /// Detected delimiter for CSV-like formats.
+    pub delimiter: Option<char>,
+
+    /// Why this format was picked.
+    pub reason: String,
+}
+
+/// Detects the format of `reader` by inspecting its first bytes, `ext` is the input's extension
+/// (if any) which is trusted as long as the content looks consistent with it.
+///
+/// Returns the detection together with a reader that still yields the whole input.
+pub fn detect_format<R: Read>(
+    mut reader: R,
+    ext: Option<&str>,
+) -> CtxResult<(Detection, impl Read + use<R>), Error> {
+    let mut sample = vec![0u8; SAMPLE_SIZE];
+    let mut len = 0;
+
+    while len < SAMPLE_SIZE {
+        match reader.read(&mut sample[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == EK::Interrupted => continue,
+            Err(e) => return Err(e).context("Failed to read input for format detection"),
+        }
+    }
+    sample.truncate(len);
+
+    let complete = len < SAMPLE_SIZE;
+
+    let detection = match ext {
+        Some(ext) if plausible(ext, &sample) => Detection {
+            format: ext.to_string(),
+            delimiter: None,
+            reason: format!("the content is consistent with its `.{}` extension", ext),
+        },
+        _ => match sniff(&sample, complete) {
+            Some(detection) => detection,
+            None => match ext {
+                Some(ext) => Detection {
+                    format: ext.to_string(),
+                    delimiter: None,
+                    reason: format!(
+                        "the content didn't match any format, falling back to its `.{}` extension",
+                        ext
+                    ),
+                },
+                None => {
+                    return Err(Error::new(EK::InvalidData, "Input format couldn't be detected"))
+                        .context("Failed to detect input format from its content")
+                        .context("Use `--from <FORMAT>` to set it explicitly");
+                }
+            },
+        },
+    };
+
+    Ok((detection, Cursor::new(sample).chain(reader)))
+}
+
+/// Checks if `sample` could be the start of a file in the `ext` format, unknown extensions are
+/// never plausible so their content always gets sniffed.
+fn plausible(ext: &str, sample: &[u8]) -> bool {
+    let trimmed = trim_start(sample);
+
+    match ext {
+        "json" | "ndjson" => matches!(
+            trimmed.first(),
+            None | Some(b'{' | b'[' | b'"' | b'-' | b'0'..=b'9' | b't' | b'f' | b'n')
+        ),
+        "toml" => trimmed.first() != Some(&b'{'),
+        "xml" => matches!(trimmed.first(), None | Some(b'<')),
+        "yaml" | "yml" | "csv" | "msgpack" | "mpk" | "cbor" => true,
+        "parquet" => sample.is_empty() || sample.starts_with(b"PAR1"),
+        "avro" => sample.is_empty() || sample.starts_with(b"Obj\x01"),
+        "arrow" | "feather" => sample.is_empty() || sample.starts_with(b"ARROW1"),
+        "arrows" => sample.is_empty() || sample.starts_with(b"\xff\xff\xff\xff"),
+        _ => false,
+    }
+}
+
+/// Sniffs the format of `sample`, `complete` is true if the sample is the whole input.
+pub(crate) fn sniff(sample: &[u8], complete: bool) -> Option<Detection> {
+    let sample = sample.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(sample);
+    let trimmed = trim_start(sample);
+
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let lines = sample_lines(sample, complete);
+
+    let detect = |format: &str, delimiter: Option<char>, reason: String| {
+        Some(Detection { format: format.to_string(), delimiter, reason })
+    };
+
+    if sample.starts_with(b"PAR1") {
+        return detect("parquet", None, "the content starts with the Parquet magic `PAR1`".into());
+    }
+
+    if sample.starts_with(b"Obj\x01") {
+        return detect("avro", None, "the content starts with the Avro magic `Obj\\x01`".into());
+    }
+
+    if sample.starts_with(b"ARROW1") {
+        return detect(
+            "arrow",
+            None,
+            "the content starts with the Arrow IPC magic `ARROW1`".into(),
+        );
+    }
+
+    // every message of an Arrow IPC stream starts with a continuation marker, the schema first
+    if sample.starts_with(b"\xff\xff\xff\xff") {
+        return detect(
+            "arrows",
+            None,
+            "the content starts with the Arrow IPC stream marker `0xFFFFFFFF`".into(),
+        );
+    }
+
+    // CBOR maps and the self-described CBOR tag, CBOR arrays share their markers with
+    // MessagePack's maps / arrays which are far more common as records
+    if sample.starts_with(b"\xd9\xd9\xf7")
+        || (matches!(sample[0], 0xa0..=0xbf)
+            && std::str::from_utf8(sample).is_err_and(|e| e.valid_up_to() == 0))
+    {
+        return detect(
+            "cbor",
+            None,
+            format!("the content starts with the CBOR marker `0x{:02x}`", sample[0]),
+        );
+    }
+
+    // MessagePack maps / arrays start with bytes that can't start UTF-8 text
+    if matches!(sample[0], 0x80..=0x9f | 0xdc..=0xdf)
+        && std::str::from_utf8(sample).is_err_and(|e| e.valid_up_to() == 0)
+    {
+        return detect(
+            "msgpack",
+            None,
+            format!("the content starts with the MessagePack marker `0x{:02x}`", sample[0]),
+        );
+    }
+
+    if trimmed.starts_with(b"<?xml")
+        || (trimmed[0] == b'<'
+            && trimmed.get(1).is_some_and(|b| b.is_ascii_alphabetic() || *b == b'!'))
+    {
+        return detect("xml", None, "the content starts with an XML tag".to_string());
+    }
+
+    if trimmed[0] == b'{' || trimmed[0] == b'[' {
+        if lines.len() >= 2 && is_toml_table_header(lines[0]) && is_toml_line(lines[1]) {
+            return detect(
+                "toml",
+                None,
+                format!(
+                    "`{}` is a TOML table header",
+                    String::from_utf8_lossy(lines[0].trim_ascii())
+                ),
+            );
+        }
+
+        if lines.len() >= 2 && lines.iter().all(|l| serde_json::from_slice::<IgnoredAny>(l).is_ok())
+        {
+            return detect(
+                "ndjson",
+                None,
+                format!("each of the {} sampled lines is a complete JSON value", lines.len()),
+            );
+        }
+
+        return detect("json", None, format!("the content starts with `{}`", trimmed[0] as char));
+    }
+
+    if !lines.is_empty() && lines.iter().all(|l| is_toml_line(l)) {
+        return detect(
+            "toml",
+            None,
+            format!(
+                "each of the {} sampled lines is a `key = value` pair or a `[table]` header",
+                lines.len()
+            ),
+        );
+    }
+
+    if let Some((d, count)) = consistent_delimiter(&lines) {
+        let name = match d {
+            b'\t' => "\\t",
+            b'|' => "|",
+            b';' => ";",
+            _ => ",",
+        };
+
+        return detect(
+            "csv",
+            Some(d as char),
+            format!(
+                "`{}` appears {} time(s) on each of the {} sampled lines",
+                name,
+                count,
+                lines.len()
+            ),
+        );
+    }
+
+    if lines.first().is_some_and(|l| l.trim_ascii() == b"---")
+        || (!lines.is_empty() && lines.iter().all(|l| is_yaml_line(l)))
+    {
+        return detect(
+            "yaml",
+            None,
+            "the sampled lines are YAML `key: value` pairs or sequence items".to_string(),
+        );
+    }
+
+    if matches!(trimmed[0], b'"' | b'-' | b'0'..=b'9')
+        && serde_json::from_slice::<IgnoredAny>(trimmed).is_ok()
+    {
+        return detect("json", None, "the content is a single JSON value".to_string());
+    }
+
+    None
+}
+
+#[inline]
+fn trim_start(sample: &[u8]) -> &[u8] {
+    let sample = sample.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(sample);
+    sample.trim_ascii_start()
+}
+
+/// Splits `sample` into its first non-empty, non-comment lines, the last line is dropped if the
+/// sample was cut in the middle of it.
+fn sample_lines(sample: &[u8], complete: bool) -> Vec<&[u8]> {
+    let mut lines: Vec<&[u8]> = sample.split(|b| *b == b'\n').collect();
+
+    if !complete && lines.len() > 1 {
+        lines.pop();
+    }
+
+    lines
+        .into_iter()
+        .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+        .filter(|l| !l.trim_ascii().is_empty() && !l.trim_ascii_start().starts_with(b"#"))
+        .take(SAMPLE_LINES)
+        .collect()
+}
+
+fn is_toml_table_header(line: &[u8]) -> bool {
+    let line = line.trim_ascii();
+
+    let inner = match line.strip_prefix(b"[[").and_then(|l| l.strip_suffix(b"]]")) {
+        Some(inner) => inner,
+        None => match line.strip_prefix(b"[").and_then(|l| l.strip_suffix(b"]")) {
+            Some(inner) => inner,
+            None => return false,
+        },
+    };
+
+    !inner.is_empty()
+        && inner.iter().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.' | b' '))
+        && inner.iter().any(|b| b.is_ascii_alphabetic())
+}
+
+fn is_toml_line(line: &[u8]) -> bool {
+    if is_toml_table_header(line) {
+        return true;
+    }
+
+    let Some(eq) = line.iter().position(|b| *b == b'=') else {
+        return false;
+    };
+
+    let key = line[..eq].trim_ascii();
+
+    !key.is_empty()
+        && key
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.' | b'"' | b' '))
+        && !line[eq + 1..].trim_ascii().is_empty()
+}
+
+fn is_yaml_line(line: &[u8]) -> bool {
+    let trimmed = line.trim_ascii_start();
+
+    if trimmed.starts_with(b"- ") || trimmed == b"-" {
+        return true;
+    }
+
+    match trimmed.iter().position(|b| *b == b':') {
+        Some(colon) => {
+            colon > 0
+                && trimmed.get(colon + 1).is_none_or(|b| *b == b' ')
+                && !trimmed[..colon].iter().any(|b| DELIMITERS.contains(b))
+        }
+        // indented continuation lines
+        None => line.first().is_some_and(|b| *b == b' '),
+    }
+}
+
+/// Finds a delimiter that appears the same (non-zero) number of times on every line, quoted
+/// fields are skipped so delimiters inside them aren't counted.
+fn consistent_delimiter(lines: &[&[u8]]) -> Option<(u8, usize)> {
+    if lines.is_empty() {
+        return None;
+    }
+
+    DELIMITERS
+        .iter()
+        .filter_map(|d| {
+            let first = count_unquoted(lines[0], *d);
+
+            (first > 0 && lines.iter().all(|l| count_unquoted(l, *d) == first))
+                .then_some((*d, first))
+        })
+        .max_by_key(|(_, count)| *count)
+}
+
+#[inline]
+fn count_unquoted(line: &[u8], delimiter: u8) -> usize {
+    let mut in_quotes = false;
+
+    line.iter()
+        .filter(|b| {
+            if **b == b'"' {
+                in_quotes = !in_quotes;
+            }
+            !in_quotes && **b == delimiter
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_ndjson() {
+        let d = sniff(b"{\"a\": 1}\n{\"a\": 2}\n", true).unwrap();
+        assert_eq!(d.format, "ndjson");
+    }
+
+    #[test]
+    fn test_sniff_json() {
+        let d = sniff(b"[\n  {\"a\": 1},\n  {\"a\": 2}\n]\n", true).unwrap();
+        assert_eq!(d.format, "json");
+    }
+
+    #[test]
+    fn test_sniff_toml() {
+        let d = sniff(b"# config\ntitle = \"fiux\"\n\n[owner]\nname = \"Taha\"\n", true).unwrap();
+        assert_eq!(d.format, "toml");
+
+        let d = sniff(b"[[Rows]]\na = 1\n", true).unwrap();
+        assert_eq!(d.format, "toml");
+
+        let d = sniff(b"[true]\n", true).unwrap();
+        assert_eq!(d.format, "json");
+    }
+
+    #[test]
+    fn test_sniff_delimiters() {
+        let d = sniff(b"a,b,c\n1,\"x,y\",3\n", true).unwrap();
+        assert_eq!((d.format.as_str(), d.delimiter), ("csv", Some(',')));
+
+        let d = sniff(b"a\tb\n1\t2\n", true).unwrap();
+        assert_eq!((d.format.as_str(), d.delimiter), ("csv", Some('\t')));
+
+        let d = sniff(b"a|b|c\n1|2|3\n", true).unwrap();
+        assert_eq!((d.format.as_str(), d.delimiter), ("csv", Some('|')));
+    }
+
+    #[test]
+    fn test_sniff_yaml() {
+        let d = sniff(b"name: fiux\ntags:\n  - cli\n  - converter\n", true).unwrap();
+        assert_eq!(d.format, "yaml");
+    }
+
+    #[test]
+    fn test_sniff_xml() {
+        let d = sniff(b"<?xml version=\"1.0\"?>\n<feed><item/></feed>\n", true).unwrap();
+        assert_eq!(d.format, "xml");
+
+        let d = sniff(b"<feed>\n  <item id=\"1\"/>\n</feed>\n", true).unwrap();
+        assert_eq!(d.format, "xml");
+    }
+
+    #[test]
+    fn test_sniff_msgpack() {
+        let d = sniff(b"\x82\xa2id\x01\xa4name\xa1a", true).unwrap();
+        assert_eq!(d.format, "msgpack");
+        assert_ne!(
+            sniff("\u{700} is text".as_bytes(), true).map(|d| d.format).as_deref(),
+            Some("msgpack")
+        );
+    }
+
+    #[test]
+    fn test_sniff_cbor() {
+        let d = sniff(b"\xa2\x62id\x01\x64name\x61a", true).unwrap();
+        assert_eq!(d.format, "cbor");
+    }
+
+    #[test]
+    fn test_sniff_columnar() {
+        assert_eq!(sniff(b"PAR1\x15\x04", false).unwrap().format, "parquet");
+        assert_eq!(sniff(b"Obj\x01\x04\x14avro.codec", false).unwrap().format, "avro");
+        assert_eq!(sniff(b"ARROW1\0\0\xff\xff", false).unwrap().format, "arrow");
+        assert_eq!(sniff(b"\xff\xff\xff\xff\x78\0\0\0", false).unwrap().format, "arrows");
+    }
+
+    #[test]
+    fn test_plausible_extension() {
+        assert!(plausible("json", b"  {\"a\": 1}"));
+        assert!(!plausible("json", b"a,b\n1,2\n"));
+        assert!(!plausible("txt", b"a,b\n1,2\n"));
+    }
+}
@@ -4,22 +4,51 @@ use csv::ByteRecord;
 use resext::{CtxResult, ResExt};
 use serde::Serialize;
 
-pub(crate) enum WriterStreams<I>
+/// Stream of records produced by a `Decoder` and consumed by an `Encoder`.
+pub enum WriterStreams<I>
 where
     I: Iterator<Item = CtxResult<DataTypes, Error>>,
 {
+    /// Whole documents (e.g. JSON values, TOML files, YAML documents).
     Values { iter: I },
 
+    /// Table rows with a header row, every item is a `DataTypes::Csv` record (other records are
+    /// skipped as invalid).
     Table { headers: Vec<String>, iter: I },
 
+    /// Records in a stream of records (e.g. NDJSON lines).
     Ndjson { values: I },
 }
 
-pub(crate) enum DataTypes {
+impl<I> WriterStreams<I>
+where
+    I: Iterator<Item = CtxResult<DataTypes, Error>>,
+{
+    /// Applies `f` to the stream's iterator, keeping its variant (and headers).
+    pub(crate) fn map_iter<J>(self, f: impl FnOnce(I) -> J) -> WriterStreams<J>
+    where
+        J: Iterator<Item = CtxResult<DataTypes, Error>>,
+    {
+        match self {
+            WriterStreams::Values { iter } => WriterStreams::Values { iter: f(iter) },
+            WriterStreams::Table { headers, iter } => {
+                WriterStreams::Table { headers, iter: f(iter) }
+            }
+            WriterStreams::Ndjson { values } => WriterStreams::Ndjson { values: f(values) },
+        }
+    }
+}
+
+/// A single record in a `WriterStreams`.
+pub enum DataTypes {
+    /// Record from JSON-like formats (JSON, NDJSON, YAML).
     Json(serde_json::Value),
 
+    /// Record from TOML documents.
     Toml(toml::Value),
 
+    /// Table row, only serializable together with its stream's headers. Table rows in `Values` /
+    /// `Ndjson` streams are skipped as invalid records.
     Csv(ByteRecord),
 }
 
@@ -31,15 +60,174 @@ impl Serialize for DataTypes {
         match self {
             DataTypes::Json(j) => j.serialize(serializer),
             DataTypes::Toml(t) => t.serialize(serializer),
-            DataTypes::Csv(_) => unreachable!(),
+            DataTypes::Csv(_) => Err(serde::ser::Error::custom(TABLE_ROW)),
         }
     }
 }
 
+/// Message for records whose variant doesn't match their stream's.
+pub(crate) const TABLE_ROW: &str = "Table rows are only valid in `Table` streams";
+
 pub(crate) fn into_byte_record(brec: CtxResult<DataTypes, Error>) -> CtxResult<ByteRecord, Error> {
     match brec.context("Failed to unwrap record")? {
         DataTypes::Csv(csv) => Ok(csv),
-        _ => unreachable!(),
+        _ => Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "`Table` streams can only hold table rows",
+        ))
+        .context("Failed to unwrap record"),
+    }
+}
+
+impl DataTypes {
+    /// Converts a JSON / TOML record into a `serde_json::Value`, TOML datetimes are kept as
+    /// strings since JSON has no native type for them.
+    pub(crate) fn into_json(self) -> CtxResult<serde_json::Value, Error> {
+        match self {
+            DataTypes::Json(j) => Ok(j),
+            DataTypes::Toml(t) => Ok(toml_to_json(t)),
+            DataTypes::Csv(_) => Err(Error::new(std::io::ErrorKind::InvalidInput, TABLE_ROW))
+                .context("Failed to unwrap record"),
+        }
+    }
+}
+
+/// Converts a binary blob (e.g. MessagePack bin / CBOR byte strings) into `binary`'s
+/// representation.
+pub(crate) fn bytes_to_json(data: Vec<u8>, binary: crate::utils::Binary) -> serde_json::Value {
+    use base64::Engine;
+
+    match binary {
+        crate::utils::Binary::Base64 => {
+            serde_json::Value::String(base64::engine::general_purpose::STANDARD.encode(data))
+        }
+        crate::utils::Binary::Array => {
+            serde_json::Value::Array(data.into_iter().map(serde_json::Value::from).collect())
+        }
+    }
+}
+
+/// Days since 1970-01-01 of `date` (Howard Hinnant's algorithm).
+pub(crate) fn epoch_days(date: &toml::value::Date) -> i64 {
+    let (month, day) = (i64::from(date.month), i64::from(date.day));
+    let year = i64::from(date.year) - i64::from(month <= 2);
+
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+/// Date `days` days after 1970-01-01, `None` outside of the years 0 to 9999.
+pub(crate) fn date_from_days(days: i64) -> Option<toml::value::Date> {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    Some(toml::value::Date { year: u16::try_from(year).ok().filter(|y| *y <= 9999)?, month, day })
+}
+
+/// Seconds and nanoseconds since the Unix epoch of a datetime with a date and a time, datetimes
+/// without an offset are read as UTC.
+pub(crate) fn epoch_seconds(dt: &toml::value::Datetime) -> Option<(i64, u32)> {
+    let (date, time) = (dt.date?, dt.time?);
+    let offset = match dt.offset {
+        Some(toml::value::Offset::Custom { minutes }) => i64::from(minutes) * 60,
+        _ => 0,
+    };
+
+    let secs = epoch_days(&date) * 86_400
+        + i64::from(time.hour) * 3600
+        + i64::from(time.minute) * 60
+        + i64::from(time.second)
+        - offset;
+
+    Some((secs, time.nanosecond))
+}
+
+/// Datetime `secs` seconds and `nanosecond` nanoseconds after the Unix epoch, in UTC with
+/// `Offset::Z` or a local datetime without an offset. `None` outside of the years 0 to 9999.
+pub(crate) fn epoch_datetime(
+    secs: i64,
+    nanosecond: u32,
+    offset: Option<toml::value::Offset>,
+) -> Option<toml::value::Datetime> {
+    let rem = secs.rem_euclid(86_400);
+
+    Some(toml::value::Datetime {
+        date: Some(date_from_days(secs.div_euclid(86_400))?),
+        time: Some(toml::value::Time {
+            hour: (rem / 3600) as u8,
+            minute: (rem % 3600 / 60) as u8,
+            second: (rem % 60) as u8,
+            nanosecond,
+        }),
+        offset,
+    })
+}
+
+pub(crate) fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s),
+        toml::Value::Integer(i) => serde_json::Value::from(i),
+        toml::Value::Float(f) => serde_json::Value::from(f),
+        toml::Value::Boolean(b) => serde_json::Value::Bool(b),
+        toml::Value::Datetime(d) => serde_json::Value::String(d.to_string()),
+        toml::Value::Array(arr) => {
+            serde_json::Value::Array(arr.into_iter().map(toml_to_json).collect())
+        }
+        toml::Value::Table(map) => {
+            serde_json::Value::Object(map.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect())
+        }
+    }
+}
+
+/// Flattens a JSON value into `(key, leaf)` pairs where nested keys are joined with dots
+/// (e.g. `address.city`) and array elements are keyed by their index (e.g. `tags.0`).
+///
+/// Empty objects and arrays are kept as leaves under their own key so they still get a column.
+pub(crate) fn flatten_json<'a>(
+    prefix: &mut String,
+    value: &'a serde_json::Value,
+    out: &mut Vec<(String, &'a serde_json::Value)>,
+) {
+    let len = prefix.len();
+
+    match value {
+        // records without any keys have no columns
+        serde_json::Value::Object(map) if map.is_empty() && len == 0 => {}
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                if len != 0 {
+                    prefix.push('.');
+                }
+                prefix.push_str(k);
+                flatten_json(prefix, v, out);
+                prefix.truncate(len);
+            }
+        }
+        serde_json::Value::Array(arr) if !arr.is_empty() => {
+            for (idx, v) in arr.iter().enumerate() {
+                if len != 0 {
+                    prefix.push('.');
+                }
+                prefix.push_str(&idx.to_string());
+                flatten_json(prefix, v, out);
+                prefix.truncate(len);
+            }
+        }
+        _ => {
+            let key = if prefix.is_empty() { "value".to_string() } else { prefix.clone() };
+            out.push((key, value));
+        }
     }
 }
 
@@ -121,6 +309,28 @@ mod tests {
         assert_eq!(out, b"0");
     }
 
+    #[test]
+    fn test_flatten_nested() {
+        let value = serde_json::json!({
+            "name": "Alice",
+            "address": { "city": "NYC", "zip": "10001" },
+            "tags": ["a", "b"],
+            "empty": {}
+        });
+
+        let mut out = Vec::new();
+        flatten_json(&mut String::new(), &value, &mut out);
+
+        let keys: Vec<&str> = out.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["name", "address.city", "address.zip", "tags.0", "tags.1", "empty"]);
+
+        // records without keys have no columns
+        let empty = serde_json::json!({});
+        let mut out = Vec::new();
+        flatten_json(&mut String::new(), &empty, &mut out);
+        assert!(out.is_empty());
+    }
+
     #[test]
     fn test_needs_escape_table() {
         // Should escape these
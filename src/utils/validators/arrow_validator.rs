@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use arrow_array::RecordBatch;
+use arrow_schema::ArrowError;
+use resext::{CtxResult, ResExt};
+
+use crate::utils::arrow_reader::{arrow_error, arrow_file_reader, arrow_stream_reader};
+
+/// Decodes every record batch, counting their rows until the first one that fails to decode.
+fn first_error(
+    mut batches: impl Iterator<Item = Result<RecordBatch, ArrowError>>,
+    rows: &mut usize,
+) -> Option<std::io::Error> {
+    batches.find_map(|batch| match batch {
+        Ok(batch) => {
+            *rows += batch.num_rows();
+            None
+        }
+        Err(e) => Some(arrow_error(e)),
+    })
+}
+
+/// Validates an Arrow IPC file or, with `stream`, an Arrow IPC stream by decoding every record
+/// batch, invalid files are logged with the number of rows decoded before the error.
+pub(crate) fn validate_arrow(path: &Path, stream: bool) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
+        .context("Failed to validate file")
+        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
+
+    let mut rows = 0usize;
+
+    let checked = if stream {
+        arrow_stream_reader(file).map(|reader| first_error(reader, &mut rows))
+    } else {
+        arrow_file_reader(file).map(|reader| first_error(reader, &mut rows))
+    };
+
+    let err = checked.unwrap_or_else(Some);
+
+    match err {
+        None => Ok(()),
+        Some(e) => {
+            let e = resext::ErrCtx::new(
+                e,
+                format!(
+                    "Invalid Arrow data in input file: {} after row: {}",
+                    &path.to_string_lossy(),
+                    rows
+                )
+                .into_bytes(),
+            );
+            crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+
+            Err(resext::ErrCtx::new(
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid Arrow in input file"),
+                b"Input file is invalid".to_vec(),
+            ))
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use resext::{CtxResult, ResExt};
+
+use crate::utils::avro_reader::{avro_error, avro_reader};
+
+/// Validates an Avro object container file by decoding every value against the embedded schema,
+/// the first value that fails to decode is logged with its record number.
+pub(crate) fn validate_avro(path: &Path) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
+        .context("Failed to validate file")
+        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
+
+    let mut rec_no = 1usize;
+
+    // blocks can't be resynchronized after a corrupt one, so stop at the first error
+    let err = match avro_reader(file) {
+        Ok(reader) => reader.into_iter().find_map(|value| match value {
+            Ok(_) => {
+                rec_no += 1;
+                None
+            }
+            Err(e) => Some(avro_error(e)),
+        }),
+        Err(e) => Some(e),
+    };
+
+    match err {
+        None => Ok(()),
+        Some(e) => {
+            let e = resext::ErrCtx::new(
+                e,
+                format!(
+                    "Invalid Avro data in input file: {} at record: {}",
+                    &path.to_string_lossy(),
+                    rec_no
+                )
+                .into_bytes(),
+            );
+            crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+
+            Err(resext::ErrCtx::new(
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid Avro in input file"),
+                b"Input file is invalid".to_vec(),
+            ))
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use resext::{CtxResult, ResExt};
+
+use crate::utils::cbor_reader::cbor_reader;
+
+/// Validates a CBOR sequence, the byte offset of a malformed item is logged along with the offset
+/// it starts at.
+pub(crate) fn validate_cbor(path: &Path) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
+        .context("Failed to validate file")
+        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
+
+    let mut reader = cbor_reader(file);
+    let mut item_no = 0usize;
+
+    // CBOR can't be resynchronized after a malformed item, so stop at the first one
+    let err = loop {
+        item_no += 1;
+
+        match reader.read_item() {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => break Some(e),
+            None => break None,
+        }
+    };
+
+    match err {
+        None => Ok(()),
+        Some(e) => {
+            let e = resext::ErrCtx::new(
+                e,
+                format!(
+                    "Invalid CBOR data in input file: {} at item: {}",
+                    &path.to_string_lossy(),
+                    item_no
+                )
+                .into_bytes(),
+            );
+            crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+
+            Err(resext::ErrCtx::new(
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid CBOR in input file"),
+                b"Input file is invalid".to_vec(),
+            ))
+        }
+    }
+}
@@ -1,50 +1,70 @@
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::{io::BufReader, path::Path};
 
-use resext::{CtxResult, ResExt, throw_err_if};
+use resext::{CtxResult, ResExt};
 
-pub(crate) fn validate_csv(path: &PathBuf, delimiter: char) -> CtxResult<(), std::io::Error> {
-    let file = File::open(path)
+use crate::utils::{Position, csv_reader::fit_row, record_error};
+
+pub(crate) fn validate_csv(
+    path: &Path,
+    delimiter: char,
+    dialect: &crate::utils::CsvDialect,
+) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
         .context("Failed to validate file")
         .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
 
     let buf = BufReader::with_capacity(256 * 1024, file);
 
-    throw_err_if!(
-        !delimiter.is_ascii(),
-        || format!("Input delimiter: {} is not valid UTF-8", delimiter),
-        1
-    );
-
-    let d = delimiter as u8;
-
-    let mut reader = csv::ReaderBuilder::new().delimiter(d).from_reader(buf);
+    // rows are read flexibly so every row is checked against the header row instead of the row
+    // before it
+    let mut reader = crate::utils::csv_reader::reader_builder(delimiter, dialect)?
+        .flexible(true)
+        .from_reader(buf);
 
     let mut res = Ok(());
 
-    reader
+    let headers = reader
         .byte_headers()
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{e}")))
         .with_context(|| format!("Input file: {} is invalid", &path.to_string_lossy()))
         .context("Failed to read input file headers")
         .context("CSV files are required to have valid headers for parsing and validation")?;
 
+    let len = if dialect.has_headers || dialect.header_names.is_empty() {
+        headers.len()
+    } else {
+        dialect.header_names.len()
+    };
+
     for (idx, rec) in reader.byte_records().enumerate() {
-        rec.with_context(|| format!("Invalid CSV data at record: {}", idx + 1)).unwrap_or_else(
-            |e: resext::ErrCtx<csv::Error>| {
-                crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-
-                if res.is_ok() {
-                    res = Err(resext::ErrCtx::new(
-                        std::io::Error::new(
-                            std::io::ErrorKind::InvalidData,
-                            "Invalid CSV in input file",
-                        ),
-                        b"Input file is invalid".to_vec(),
-                    ));
-                }
-                csv::ByteRecord::default()
-            },
-        );
+        let checked = rec
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{e}")))
+            .and_then(|mut rec| {
+                let Err(msg) = fit_row(&mut rec, len, dialect.ragged) else { return Ok(()) };
+
+                let pos = Position {
+                    record: Some(idx + 1),
+                    line: rec.position().map(|p| p.line() as usize),
+                    offset: rec.position().map(|p| p.byte()),
+                    ..Position::default()
+                };
+                Err(record_error(msg, pos, Vec::new()))
+            })
+            .with_context(|| format!("Invalid CSV data at record: {}", idx + 1));
+
+        if let Err(e) = checked {
+            crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+
+            if res.is_ok() {
+                res = Err(resext::ErrCtx::new(
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid CSV in input file",
+                    ),
+                    b"Input file is invalid".to_vec(),
+                ));
+            }
+        }
     }
 
     res
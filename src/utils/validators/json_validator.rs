@@ -1,24 +1,37 @@
 use resext::{CtxResult, ResExt};
 use serde::de::IgnoredAny;
-use serde_json::Deserializer;
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::path::Path;
 
-pub(crate) fn validate_json(path: &PathBuf) -> CtxResult<(), std::io::Error> {
-    let file = File::open(path)
+use crate::utils::json_reader::{JsonInput, element_error, json_error, json_reader};
+
+pub(crate) fn validate_json(path: &Path) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
         .context("Failed to validate file")
         .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
 
-    let reader = BufReader::with_capacity(256 * 1024, file);
-
-    let file_stream = Deserializer::from_reader(reader).into_iter::<IgnoredAny>();
+    // top-level arrays are validated one element at a time so every invalid element is reported
+    let items: Box<dyn Iterator<Item = Result<(), std::io::Error>>> = match json_reader(file)? {
+        JsonInput::Array(elements) => Box::new(elements.enumerate().map(|(idx, el)| {
+            el.and_then(|el| {
+                serde_json::from_slice::<IgnoredAny>(&el.bytes)
+                    .map(|_| ())
+                    .map_err(|e| element_error(e, &el, idx + 1))
+            })
+        })),
+        JsonInput::Values((reader, state)) => {
+            Box::new(reader.into_iter::<IgnoredAny>().enumerate().map(move |(idx, item)| {
+                item.map(|_| ()).map_err(|e| json_error(e, &state, Some(idx + 1)))
+            }))
+        }
+    };
 
     let mut res = Ok(());
 
-    for item in file_stream {
+    for item in items {
         item.with_context(|| {
             format!("Invalid JSON data in input file: {}", &path.to_string_lossy())
         })
-        .unwrap_or_else(|e: resext::ErrCtx<serde_json::Error>| {
+        .unwrap_or_else(|e: resext::ErrCtx<std::io::Error>| {
             crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
 
             if res.is_ok() {
@@ -30,7 +43,6 @@ pub(crate) fn validate_json(path: &PathBuf) -> CtxResult<(), std::io::Error> {
                     b"Input file is invalid".to_vec(),
                 ));
             }
-            IgnoredAny
         });
     }
 
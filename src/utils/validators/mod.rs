@@ -1,4 +1,12 @@
+pub(crate) mod arrow_validator;
+pub(crate) mod avro_validator;
+pub(crate) mod cbor_validator;
 pub(crate) mod csv_validator;
 pub(crate) mod json_validator;
+pub(crate) mod msgpack_validator;
 pub(crate) mod ndjson_validator;
+pub(crate) mod parquet_validator;
+pub(crate) mod schema_validator;
 pub(crate) mod toml_validator;
+pub(crate) mod xml_validator;
+pub(crate) mod yaml_validator;
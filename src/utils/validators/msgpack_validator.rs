@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use resext::{CtxResult, ResExt};
+
+use crate::utils::msgpack_reader::msgpack_reader;
+
+pub(crate) fn validate_msgpack(path: &Path) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
+        .context("Failed to validate file")
+        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
+
+    let mut reader = msgpack_reader(file);
+    let mut value_no = 0usize;
+
+    // MessagePack can't be resynchronized after an invalid value, so stop at the first one
+    let err = loop {
+        value_no += 1;
+
+        match reader.read_value() {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => break Some(e),
+            None => break None,
+        }
+    };
+
+    match err {
+        None => Ok(()),
+        Some(e) => {
+            let e = resext::ErrCtx::new(
+                e,
+                format!(
+                    "Invalid MessagePack data in input file: {} at value: {}",
+                    &path.to_string_lossy(),
+                    value_no
+                )
+                .into_bytes(),
+            );
+            crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+
+            Err(resext::ErrCtx::new(
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Invalid MessagePack in input file",
+                ),
+                b"Input file is invalid".to_vec(),
+            ))
+        }
+    }
+}
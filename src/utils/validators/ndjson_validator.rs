@@ -1,14 +1,15 @@
 use std::{
-    fs::File,
     io::{BufRead, BufReader},
-    path::PathBuf,
+    path::Path,
 };
 
 use resext::{CtxResult, ResExt};
 use serde::de::IgnoredAny;
 
-pub(crate) fn validate_ndjson(path: &PathBuf) -> CtxResult<(), std::io::Error> {
-    let file = File::open(path)
+use crate::utils::{Position, record_error};
+
+pub(crate) fn validate_ndjson(path: &Path) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
         .context("Failed to validate file")
         .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
 
@@ -17,6 +18,7 @@ pub(crate) fn validate_ndjson(path: &PathBuf) -> CtxResult<(), std::io::Error> {
     // read lines one by one and deserialize them to check for errors
     let mut buf: Vec<u8> = Vec::new();
     let mut idx: usize = 1;
+    let mut offset: u64 = 0;
     let mut res = Ok(());
 
     loop {
@@ -30,10 +32,20 @@ pub(crate) fn validate_ndjson(path: &PathBuf) -> CtxResult<(), std::io::Error> {
             break;
         };
 
-        // check line validity
+        // check line validity, serde_json's position is relative to the line so it's replaced
+        // with the line's position in the file
         serde_json::from_slice::<IgnoredAny>(&buf)
+            .map_err(|e| {
+                let pos = Position {
+                    line: Some(idx),
+                    column: (e.column() != 0).then_some(e.column()),
+                    offset: Some(offset),
+                    ..Position::default()
+                };
+                record_error(format!("Invalid NDJSON: {}", e), pos, buf.clone())
+            })
             .with_context(|| format!("Invalid NDJSON values in input file at line: {}", idx))
-            .unwrap_or_else(|e: resext::ErrCtx<serde_json::Error>| {
+            .unwrap_or_else(|e: resext::ErrCtx<std::io::Error>| {
                 crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
 
                 if res.is_ok() {
@@ -47,6 +59,7 @@ pub(crate) fn validate_ndjson(path: &PathBuf) -> CtxResult<(), std::io::Error> {
                 }
                 IgnoredAny
             });
+        offset += n as u64;
         buf.clear();
         idx += 1;
     }
@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use resext::{CtxResult, ResExt};
+
+use crate::utils::parquet_reader::{parquet_error, parquet_reader};
+
+/// Validates a Parquet file by decoding every row group, invalid files are logged with the number
+/// of rows decoded before the error.
+pub(crate) fn validate_parquet(path: &Path) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
+        .context("Failed to validate file")
+        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
+
+    let mut rows = 0usize;
+
+    let err = match parquet_reader(file) {
+        Ok(reader) => reader.into_iter().find_map(|batch| match batch {
+            Ok(batch) => {
+                rows += batch.num_rows();
+                None
+            }
+            Err(e) => Some(parquet_error(e)),
+        }),
+        Err(e) => Some(e),
+    };
+
+    match err {
+        None => Ok(()),
+        Some(e) => {
+            let e = resext::ErrCtx::new(
+                e,
+                format!(
+                    "Invalid Parquet data in input file: {} after row: {}",
+                    &path.to_string_lossy(),
+                    rows
+                )
+                .into_bytes(),
+            );
+            crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+
+            Err(resext::ErrCtx::new(
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Invalid Parquet in input file",
+                ),
+                b"Input file is invalid".to_vec(),
+            ))
+        }
+    }
+}
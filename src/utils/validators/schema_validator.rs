@@ -0,0 +1,129 @@
+use std::{io::Error, path::Path};
+
+use resext::{CtxResult, ResExt};
+use serde_json::Value;
+
+use crate::utils::{
+    Arrow, ArrowStream, Avro, Cbor, Cell, Csv, DataTypes, Decoder, Inference, Json, Msgpack,
+    Ndjson, Options, Parquet, Position, Schema, Toml, WriterStreams, Xml, Yaml, into_byte_record,
+    log_invalid_ext, record_error,
+};
+
+/// Checks every record of the file at `path` against `schema`, every violation is logged with its
+/// record number and JSON pointer. Records that fail to decode are left to the syntax check.
+pub(crate) fn validate_schema(
+    path: &Path,
+    format: &str,
+    schema: &Schema,
+    options: &Options,
+) -> CtxResult<(), Error> {
+    let file = crate::utils::open_decompressed(path)
+        .context("Failed to validate file")
+        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
+
+    let valid = match format {
+        "json" => check_records(Json.decode(file, options)?, schema, options),
+        "toml" => check_records(Toml.decode(file, options)?, schema, options),
+        "csv" => check_records(Csv.decode(file, options)?, schema, options),
+        "ndjson" => check_records(Ndjson.decode(file, options)?, schema, options),
+        "yaml" | "yml" => check_records(Yaml.decode(file, options)?, schema, options),
+        "xml" => check_records(Xml.decode(file, options)?, schema, options),
+        "msgpack" | "mpk" => check_records(Msgpack.decode(file, options)?, schema, options),
+        "cbor" => check_records(Cbor.decode(file, options)?, schema, options),
+        "parquet" => check_records(Parquet.decode(file, options)?, schema, options),
+        "avro" => check_records(Avro.decode(file, options)?, schema, options),
+        "arrow" | "feather" => check_records(Arrow.decode(file, options)?, schema, options),
+        "arrows" => check_records(ArrowStream.decode(file, options)?, schema, options),
+        _ => return log_invalid_ext(format, false),
+    }?;
+
+    if valid {
+        Ok(())
+    } else {
+        Err(resext::ErrCtx::new(
+            Error::new(std::io::ErrorKind::InvalidData, "Records don't match the JSON Schema"),
+            b"Input file is invalid".to_vec(),
+        ))
+    }
+}
+
+/// Checks the records of `data`, returns whether all of them matched the schema.
+fn check_records<I>(
+    data: WriterStreams<I>,
+    schema: &Schema,
+    options: &Options,
+) -> CtxResult<bool, Error>
+where
+    I: Iterator<Item = CtxResult<DataTypes, Error>>,
+{
+    let mut valid = true;
+
+    let mut check = |idx: usize, value: &Value| {
+        let mut violations = Vec::new();
+        schema.check(value, "", &mut violations);
+
+        for (pointer, msg) in violations {
+            let at =
+                if pointer.is_empty() { "the root".to_string() } else { format!("`{}`", pointer) };
+            let pos = Position { record: Some(idx), ..Position::default() };
+
+            let checked: Result<(), Error> =
+                Err(record_error(format!("Schema violation at {}: {}", at, msg), pos, Vec::new()));
+
+            if let Err(e) =
+                checked.with_context(|| format!("Record: {} doesn't match the JSON Schema", idx))
+            {
+                crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+            }
+
+            valid = false;
+        }
+    };
+
+    match data {
+        // table rows are checked as objects with the same types `--parse-numbers` infers, empty
+        // fields are nulls
+        WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, true, &options.types)?;
+
+            for (idx, rec) in iter.enumerate() {
+                let Ok(record) = into_byte_record(rec) else { continue };
+                let Ok(cells) = inference.row(&record) else { continue };
+
+                let row: serde_json::Map<String, Value> = headers
+                    .iter()
+                    .zip(cells.iter().zip(record.iter()))
+                    .map(|(h, (cell, raw))| {
+                        let typed = match cell {
+                            Cell::Str(b"") => Value::Null,
+                            cell => cell.to_json(),
+                        };
+
+                        // fields are text, so ones whose property doesn't allow their inferred
+                        // type (e.g. ZIP codes as strings) are checked as text
+                        let value = match schema.property(h) {
+                            Some(prop) if !prop.allows_type(&typed) => {
+                                let text = Value::String(String::from_utf8_lossy(raw).into_owned());
+                                if prop.allows_type(&text) { text } else { typed }
+                            }
+                            _ => typed,
+                        };
+
+                        (h.clone(), value)
+                    })
+                    .collect();
+
+                check(idx + 1, &Value::Object(row));
+            }
+        }
+
+        WriterStreams::Values { iter } | WriterStreams::Ndjson { values: iter } => {
+            for (idx, rec) in iter.enumerate() {
+                let Ok(rec) = rec.and_then(DataTypes::into_json) else { continue };
+                check(idx + 1, &rec);
+            }
+        }
+    }
+
+    Ok(valid)
+}
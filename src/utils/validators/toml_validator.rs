@@ -1,20 +1,24 @@
-use std::path::PathBuf;
+use std::{io::Read, path::Path};
 
 use resext::{CtxResult, ResExt};
 
 /// Toml cannot be streamed so how validation for it works is by reading the whole file into memory
-/// then trying to serialize it and if it hits an error, it prints an error message like all other
-/// validators except for line numbers.
-pub(crate) fn validate_toml(path: &PathBuf) -> CtxResult<(), std::io::Error> {
-    let file_bytes = std::fs::read(path)
+/// then trying to serialize it and if it hits an error, it prints an error message with the line
+/// and column of the error like all other validators.
+pub(crate) fn validate_toml(path: &Path) -> CtxResult<(), std::io::Error> {
+    let mut file_bytes = Vec::new();
+    crate::utils::open_decompressed(path)
         .context("Failed to validate file")
-        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
+        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?
+        .read_to_end(&mut file_bytes)
+        .with_context(|| format!("Failed to read input file: {}", &path.to_string_lossy()))?;
 
     let mut res = Ok(());
 
     toml::from_slice::<serde::de::IgnoredAny>(&file_bytes)
+        .map_err(|e| crate::utils::toml_reader::toml_error(e, &file_bytes))
         .with_context(|| format!("Invalid TOML values in input file: {}", &path.to_string_lossy()))
-        .unwrap_or_else(|e: resext::ErrCtx<toml::de::Error>| {
+        .unwrap_or_else(|e: resext::ErrCtx<std::io::Error>| {
             crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
 
             if res.is_ok() {
@@ -0,0 +1,70 @@
+use std::{io::BufReader, path::Path};
+
+use quick_xml::events::Event;
+use resext::{CtxResult, ResExt};
+
+use crate::utils::xml_reader::{is_restricted, xml_error};
+
+pub(crate) fn validate_xml(path: &Path) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
+        .context("Failed to validate file")
+        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
+
+    let mut reader = crate::utils::xml_reader::xml_reader(BufReader::new(file));
+    let mut buf = Vec::new();
+    let mut depth = 0usize;
+
+    // XML can't be recovered after an error, so stop at the first one
+    let err = loop {
+        buf.clear();
+        let before = reader.buffer_position();
+
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(_)) => depth += 1,
+            Ok(Event::End(_)) => depth = depth.saturating_sub(1),
+            Ok(Event::GeneralRef(e)) => match e.resolve_char_ref() {
+                Ok(Some(c)) if is_restricted(c) => {
+                    break Some(xml_error(
+                        format!("character reference to U+{:04X} isn't allowed", c as u32),
+                        before,
+                    ));
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) if depth > 0 => {
+                break Some(xml_error(
+                    "unexpected end of input, some elements were never closed",
+                    reader.buffer_position(),
+                ));
+            }
+            Ok(Event::Eof) => break None,
+            Ok(_) => {}
+            Err(e) => break Some(xml_error(e, reader.error_position())),
+        }
+
+        // `buf` holds the whole markup of the event, so control characters are located from
+        // where it starts
+        if let Some(idx) = buf.iter().position(|b| is_restricted(*b as char)) {
+            break Some(xml_error(
+                format!("control character U+{:04X} isn't allowed", buf[idx]),
+                before + idx as u64,
+            ));
+        }
+    };
+
+    match err {
+        None => Ok(()),
+        Some(e) => {
+            let e = resext::ErrCtx::new(
+                e,
+                format!("Invalid XML data in input file: {}", &path.to_string_lossy()).into_bytes(),
+            );
+            crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+
+            Err(resext::ErrCtx::new(
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid XML in input file"),
+                b"Input file is invalid".to_vec(),
+            ))
+        }
+    }
+}
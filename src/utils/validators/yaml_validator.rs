@@ -0,0 +1,40 @@
+use std::{io::BufReader, path::Path};
+
+use resext::{CtxResult, ResExt};
+use serde::{Deserialize, de::IgnoredAny};
+
+pub(crate) fn validate_yaml(path: &Path) -> CtxResult<(), std::io::Error> {
+    let file = crate::utils::open_decompressed(path)
+        .context("Failed to validate file")
+        .with_context(|| format!("Failed to open input file: {}", &path.to_string_lossy()))?;
+
+    let reader = BufReader::with_capacity(256 * 1024, file);
+
+    let mut res = Ok(());
+
+    for (idx, doc) in serde_norway::Deserializer::from_reader(reader).enumerate() {
+        IgnoredAny::deserialize(doc)
+            .with_context(|| format!("Invalid YAML data at document: {}", idx + 1))
+            .unwrap_or_else(|e: resext::ErrCtx<serde_norway::Error>| {
+                crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+
+                if res.is_ok() {
+                    res = Err(resext::ErrCtx::new(
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "Invalid YAML in input file",
+                        ),
+                        b"Input file is invalid".to_vec(),
+                    ));
+                }
+                IgnoredAny
+            });
+
+        // YAML streams can't be recovered after an error, so stop at the first one
+        if res.is_err() {
+            break;
+        }
+    }
+
+    res
+}
@@ -0,0 +1,110 @@
+use std::io::{BufWriter, Error, Write};
+
+use arrow_array::RecordBatch;
+use arrow_ipc::{
+    CompressionType,
+    writer::{FileWriter, IpcWriteOptions, StreamWriter},
+};
+use arrow_schema::SchemaRef;
+use resext::{CtxResult, ResExt};
+
+use crate::utils::{
+    ArrowCompression, ColumnType, Conversion, DataTypes, Options, WriterStreams,
+    record_batches::{BatchSink, write_batches},
+};
+
+#[inline]
+fn arrow_error(e: impl std::fmt::Display) -> Error {
+    Error::other(format!("Failed to write Arrow: {}", e))
+}
+
+enum IpcWriter<W: Write> {
+    File(FileWriter<W>),
+    Stream(StreamWriter<W>),
+}
+
+/// Arrow IPC file or stream that record batches are written into as they're built, the writer is
+/// created once the schema is known.
+struct Sink<W: Write> {
+    writer: Option<IpcWriter<BufWriter<W>>>,
+    output: Option<W>,
+    stream: bool,
+    options: IpcWriteOptions,
+}
+
+impl<W: Write> BatchSink for Sink<W> {
+    fn start(&mut self, schema: SchemaRef) -> Result<(), Error> {
+        let Some(output) = self.output.take() else { return Ok(()) };
+        let output = BufWriter::with_capacity(256 * 1024, output);
+        let options = self.options.clone();
+
+        let writer = if self.stream {
+            IpcWriter::Stream(
+                StreamWriter::try_new_with_options(output, &schema, options)
+                    .map_err(arrow_error)?,
+            )
+        } else {
+            IpcWriter::File(
+                FileWriter::try_new_with_options(output, &schema, options).map_err(arrow_error)?,
+            )
+        };
+
+        self.writer = Some(writer);
+        Ok(())
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), Error> {
+        match self.writer.as_mut() {
+            Some(IpcWriter::File(w)) => w.write(batch).map_err(arrow_error),
+            Some(IpcWriter::Stream(w)) => w.write(batch).map_err(arrow_error),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(mut self, schema: SchemaRef) -> Result<(), Error> {
+        if self.writer.is_none() {
+            self.start(schema)?;
+        }
+
+        let output = match self.writer.take() {
+            Some(IpcWriter::File(mut w)) => {
+                w.finish().map_err(arrow_error)?;
+                w.into_inner().map_err(arrow_error)?
+            }
+            Some(IpcWriter::Stream(mut w)) => {
+                w.finish().map_err(arrow_error)?;
+                w.into_inner().map_err(arrow_error)?
+            }
+            None => return Ok(()),
+        };
+
+        output.into_inner().map_err(|e| e.into_error())?.flush()
+    }
+}
+
+/// Rows are buffered into record batches of `Options::batch_size` rows and written one batch at a
+/// time as an Arrow IPC file (Feather v2) or, with `stream`, an Arrow IPC stream. The Arrow schema
+/// is inferred from the first batch (see `write_batches`).
+pub(crate) fn arrow_writer<W: Write>(
+    data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+    output: W,
+    options: &Options,
+    types: &[(String, ColumnType)],
+    stream: bool,
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let compression = match options.arrow_compression {
+        ArrowCompression::Uncompressed => None,
+        ArrowCompression::Lz4 => Some(CompressionType::LZ4_FRAME),
+        ArrowCompression::Zstd => Some(CompressionType::ZSTD),
+    };
+
+    let ipc_options = IpcWriteOptions::default()
+        .try_with_compression(compression)
+        .map_err(arrow_error)
+        .context("Failed to set up Arrow compression")?;
+
+    let sink = Sink { writer: None, output: Some(output), stream, options: ipc_options };
+
+    write_batches(data_stream, sink, options, types, options.batch_size.max(1), "Arrow", conv)
+}
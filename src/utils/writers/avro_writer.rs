@@ -0,0 +1,690 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufWriter, Error, ErrorKind as EK, Write},
+    str::FromStr,
+};
+
+use apache_avro::{
+    BigDecimal, Codec, Days, Decimal, DeflateSettings, Duration, Millis, Months, Schema, Uuid,
+    schema::{InnerDecimalSchema, Names},
+    types::Value as Avro,
+};
+use resext::{CtxResult, ErrCtx, ResExt};
+use serde_json::{Value, json};
+use toml::value::Datetime;
+
+use crate::utils::{
+    AvroCodec, Cell, ColumnType, Conversion, DataTypes, Inference, Options, Rejected,
+    WriterStreams,
+    avro_reader::{avro_error, avro_names},
+    epoch_days, epoch_seconds, into_byte_record,
+};
+
+/// JSON types seen in a field of the records a schema is inferred from.
+#[derive(Clone, Debug, Default)]
+struct Shape {
+    null: bool,
+    bool: bool,
+    long: bool,
+    double: bool,
+    string: bool,
+    items: Option<Box<Shape>>,
+
+    /// Number of objects and their keys.
+    fields: Option<(usize, Keys)>,
+}
+
+/// Keys of objects with the number of objects every key was in and the shape of its values.
+type Keys = Vec<(String, usize, Shape)>;
+
+/// Checks if `name` is a valid Avro name, objects with other keys become maps.
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Shape {
+    fn add(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.bool = true,
+            Value::Number(n) if n.is_i64() => self.long = true,
+            // integers beyond the range of longs are kept as their digits
+            Value::Number(n) if n.is_u64() => self.string = true,
+            Value::Number(_) => self.double = true,
+            Value::String(_) => self.string = true,
+            Value::Array(items) => {
+                let shape = self.items.get_or_insert_default();
+                items.iter().for_each(|item| shape.add(item));
+            }
+            Value::Object(obj) => {
+                let (count, fields) = self.fields.get_or_insert_default();
+                *count += 1;
+
+                for (key, value) in obj {
+                    let i = fields.iter().position(|(k, ..)| k == key).unwrap_or_else(|| {
+                        fields.push((key.clone(), 0, Shape::default()));
+                        fields.len() - 1
+                    });
+                    let (_, seen, shape) = &mut fields[i];
+
+                    *seen += 1;
+                    shape.add(value);
+                }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Shape) {
+        self.null |= other.null;
+        self.bool |= other.bool;
+        self.long |= other.long;
+        self.double |= other.double;
+        self.string |= other.string;
+
+        if let Some(items) = other.items {
+            self.items.get_or_insert_default().merge(*items);
+        }
+
+        if let Some((count, others)) = other.fields {
+            let (total, fields) = self.fields.get_or_insert_default();
+            *total += count;
+
+            for (key, seen, shape) in others {
+                match fields.iter().position(|(k, ..)| *k == key) {
+                    Some(i) => {
+                        fields[i].1 += seen;
+                        fields[i].2.merge(shape);
+                    }
+                    None => fields.push((key, seen, shape)),
+                }
+            }
+        }
+    }
+
+    /// Avro schema of the shape as JSON, a union of every type that was seen (null first, so it
+    /// can be the default of missing fields). Integers and floats become doubles, objects become
+    /// records named after their path unless their keys aren't valid names, then they're maps.
+    fn schema(&self, path: &str, nullable: bool, used: &mut HashSet<String>) -> Value {
+        let mut branches = Vec::new();
+
+        if self.null || nullable {
+            branches.push(json!("null"));
+        }
+        if self.bool {
+            branches.push(json!("boolean"));
+        }
+        if self.double {
+            branches.push(json!("double"));
+        } else if self.long {
+            branches.push(json!("long"));
+        }
+        if self.string {
+            branches.push(json!("string"));
+        }
+
+        if let Some(items) = &self.items {
+            let items = items.schema(&format!("{}_item", path), false, used);
+            branches.push(json!({ "type": "array", "items": items }));
+        }
+
+        if let Some((count, fields)) = &self.fields {
+            if fields.iter().all(|(key, ..)| is_name(key)) {
+                let mut name = path.to_string();
+                let mut n = 1;
+                while !used.insert(name.clone()) {
+                    n += 1;
+                    name = format!("{}_{}", path, n);
+                }
+
+                let fields: Vec<Value> = fields
+                    .iter()
+                    .map(|(key, seen, shape)| {
+                        let schema = shape.schema(&format!("{}_{}", name, key), seen < count, used);
+                        let nullable = schema == "null" || schema.get(0) == Some(&json!("null"));
+
+                        match nullable {
+                            true => json!({ "name": key, "type": schema, "default": null }),
+                            false => json!({ "name": key, "type": schema }),
+                        }
+                    })
+                    .collect();
+
+                branches.push(json!({ "type": "record", "name": name, "fields": fields }));
+            } else {
+                let mut values = Shape::default();
+                fields.iter().for_each(|(_, _, shape)| values.merge(shape.clone()));
+
+                let values = values.schema(&format!("{}_value", path), false, used);
+                branches.push(json!({ "type": "map", "values": values }));
+            }
+        }
+
+        match branches.len() {
+            0 => json!("null"),
+            1 => branches.remove(0),
+            _ => Value::Array(branches),
+        }
+    }
+}
+
+/// Infers an Avro schema from `values`, the top-level record is named `Record`. Every top-level
+/// field is `nullable` for tables, since any later row can leave a field empty.
+fn infer_schema(values: &[(usize, Value)], nullable: bool) -> CtxResult<Schema, Error> {
+    let mut shape = Shape::default();
+    values.iter().for_each(|(_, value)| shape.add(value));
+
+    if let (true, Some((_, fields))) = (nullable, shape.fields.as_mut()) {
+        fields.iter_mut().for_each(|(_, _, shape)| shape.null = true);
+    }
+
+    if shape.fields.is_none() && values.is_empty() {
+        shape.fields = Some((0, Vec::new()));
+    }
+
+    let schema = shape.schema("Record", false, &mut HashSet::new());
+
+    Schema::parse(&schema)
+        .map_err(avro_error)
+        .with_context(|| format!("Failed to infer an Avro schema from: {}", schema))
+}
+
+/// Short description of a JSON value for mismatch errors.
+fn found(value: &Value) -> String {
+    let text = value.to_string();
+
+    match text.char_indices().nth(40) {
+        Some((i, _)) => format!("{}..", &text[..i]),
+        None => text,
+    }
+}
+
+/// Name of a schema's type for mismatch errors.
+fn expected(schema: &Schema) -> String {
+    let name = match schema {
+        Schema::Record(record) => return format!("record {}", record.name),
+        Schema::Enum(e) => return format!("enum {}", e.name),
+        Schema::Fixed(fixed) => return format!("fixed {}", fixed.name),
+        Schema::Ref { name } => return name.to_string(),
+        Schema::Union(union) => {
+            let variants: Vec<String> = union.variants().iter().map(expected).collect();
+            return format!("one of [{}]", variants.join(", "));
+        }
+        Schema::Null => "null",
+        Schema::Boolean => "boolean",
+        Schema::Int => "int",
+        Schema::Long => "long",
+        Schema::Float => "float",
+        Schema::Double => "double",
+        Schema::Bytes => "bytes",
+        Schema::String => "string",
+        Schema::Array(_) => "array",
+        Schema::Map(_) => "map",
+        Schema::Decimal(_) | Schema::BigDecimal => "decimal",
+        Schema::Uuid(_) => "uuid",
+        Schema::Date => "date",
+        Schema::TimeMillis => "time-millis",
+        Schema::TimeMicros => "time-micros",
+        Schema::TimestampMillis => "timestamp-millis",
+        Schema::TimestampMicros => "timestamp-micros",
+        Schema::TimestampNanos => "timestamp-nanos",
+        Schema::LocalTimestampMillis => "local-timestamp-millis",
+        Schema::LocalTimestampMicros => "local-timestamp-micros",
+        Schema::LocalTimestampNanos => "local-timestamp-nanos",
+        Schema::Duration(_) => "duration",
+    };
+
+    name.to_string()
+}
+
+/// JSON pointer to the part of a value that doesn't match the schema, and why.
+type Mismatch = (String, String);
+
+fn mismatch(schema: &Schema, value: &Value) -> Mismatch {
+    (String::new(), format!("expected {}, found {}", expected(schema), found(value)))
+}
+
+/// Bytes of a base64 string or an array of byte values.
+fn bytes(value: &Value) -> Option<Vec<u8>> {
+    use base64::Engine;
+
+    match value {
+        Value::String(s) => base64::engine::general_purpose::STANDARD.decode(s).ok(),
+        Value::Array(items) => {
+            items.iter().map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok())).collect()
+        }
+        _ => None,
+    }
+}
+
+/// Ticks of `1 / per_sec` seconds of an RFC 3339 datetime, or of a time of day if `time_of_day`.
+fn ticks(value: &Value, per_sec: i64, time_of_day: bool) -> Option<i64> {
+    let dt = match value {
+        Value::Number(n) => return n.as_i64(),
+        Value::String(s) => Datetime::from_str(s).ok()?,
+        _ => return None,
+    };
+
+    let (secs, nanos) = match (time_of_day, dt.date, dt.time) {
+        (true, None, Some(t)) => (
+            i64::from(t.hour) * 3600 + i64::from(t.minute) * 60 + i64::from(t.second),
+            t.nanosecond,
+        ),
+        (false, Some(_), Some(_)) => epoch_seconds(&dt)?,
+        _ => return None,
+    };
+
+    secs.checked_mul(per_sec)?.checked_add(i64::from(nanos) / (1_000_000_000 / per_sec))
+}
+
+/// Converts a JSON value into an Avro value of `schema`. Unions take the first branch the value
+/// matches (strings take the `string` branch if there is one) and record fields missing from
+/// objects take their default. Unknown keys are dropped, or are a mismatch when `strict` (for
+/// inferred schemas, which only miss keys that first appear after the records they're inferred
+/// from). Table fields are `coerce`d, so numbers and booleans can be written from their text.
+fn json_to_avro(
+    value: &Value,
+    schema: &Schema,
+    names: &Names,
+    coerce: bool,
+    strict: bool,
+) -> Result<Avro, Mismatch> {
+    let fail = || mismatch(schema, value);
+
+    // numbers and booleans of table fields that weren't typed by `-p` / `--types`
+    let text = match value {
+        Value::String(s) if coerce => Some(s.as_str()),
+        _ => None,
+    };
+    let int = || value.as_i64().or_else(|| text?.parse().ok());
+    let float = || match value {
+        // integers beyond the range of longs would lose their precision as floats
+        Value::Number(n) if n.is_u64() && !n.is_i64() => None,
+        _ => value.as_f64().or_else(|| text?.parse().ok()),
+    };
+
+    Ok(match schema {
+        // empty table fields are nulls
+        Schema::Null => match (value, text) {
+            (Value::Null, _) | (_, Some("")) => Avro::Null,
+            _ => return Err(fail()),
+        },
+        Schema::Boolean => {
+            let b = value.as_bool().or_else(|| text?.parse().ok());
+            Avro::Boolean(b.ok_or_else(fail)?)
+        }
+        Schema::Int => Avro::Int(int().and_then(|i| i32::try_from(i).ok()).ok_or_else(fail)?),
+        Schema::Long => Avro::Long(int().ok_or_else(fail)?),
+        Schema::Float => Avro::Float(float().ok_or_else(fail)? as f32),
+        Schema::Double => Avro::Double(float().ok_or_else(fail)?),
+        Schema::String => match value {
+            Value::String(s) => Avro::String(s.clone()),
+            Value::Number(n) if n.is_u64() && !n.is_i64() => Avro::String(n.to_string()),
+            Value::Number(_) | Value::Bool(_) if coerce => Avro::String(value.to_string()),
+            _ => return Err(fail()),
+        },
+        Schema::Bytes => Avro::Bytes(bytes(value).ok_or_else(fail)?),
+        Schema::Fixed(fixed) => match bytes(value) {
+            Some(data) if data.len() == fixed.size => Avro::Fixed(fixed.size, data),
+            _ => return Err(fail()),
+        },
+        Schema::Enum(e) => match value.as_str().and_then(|s| e.symbols.iter().position(|x| x == s))
+        {
+            Some(i) => Avro::Enum(i as u32, e.symbols[i].clone()),
+            None => return Err(fail()),
+        },
+        Schema::Uuid(_) => {
+            Avro::Uuid(value.as_str().and_then(|s| Uuid::parse_str(s).ok()).ok_or_else(fail)?)
+        }
+        Schema::Decimal(decimal) => {
+            let number = match value {
+                Value::Number(n) => BigDecimal::from_str(&n.to_string()).ok(),
+                Value::String(s) => BigDecimal::from_str(s).ok(),
+                _ => None,
+            }
+            .ok_or_else(fail)?;
+
+            let scaled = number.with_scale(decimal.scale as i64);
+            let (unscaled, _) = scaled.as_bigint_and_exponent();
+            let digits = unscaled.to_string().trim_start_matches('-').len();
+
+            if scaled != number || digits > decimal.precision {
+                let reason = format!(
+                    "expected a decimal with a precision of {} and a scale of {}, found {}",
+                    decimal.precision,
+                    decimal.scale,
+                    found(value)
+                );
+                return Err((String::new(), reason));
+            }
+
+            let mut data = unscaled.to_signed_bytes_be();
+            if let InnerDecimalSchema::Fixed(fixed) = &decimal.inner {
+                // sign-extend to the size of the fixed
+                let fill = if data.first().is_some_and(|b| b & 0x80 != 0) { 0xff } else { 0 };
+                let mut extended = vec![fill; fixed.size.saturating_sub(data.len())];
+                extended.append(&mut data);
+                data = extended;
+            }
+
+            Avro::Decimal(Decimal::from(data))
+        }
+        Schema::BigDecimal => {
+            let number = match value {
+                Value::Number(n) => BigDecimal::from_str(&n.to_string()).ok(),
+                Value::String(s) => BigDecimal::from_str(s).ok(),
+                _ => None,
+            };
+            Avro::BigDecimal(number.ok_or_else(fail)?)
+        }
+        Schema::Date => {
+            let days = match value {
+                Value::String(s) => Datetime::from_str(s)
+                    .ok()
+                    .filter(|dt| dt.time.is_none())
+                    .and_then(|dt| i32::try_from(epoch_days(&dt.date?)).ok()),
+                _ => value.as_i64().and_then(|d| i32::try_from(d).ok()),
+            };
+            Avro::Date(days.ok_or_else(fail)?)
+        }
+        Schema::TimeMillis => Avro::TimeMillis(
+            ticks(value, 1000, true).and_then(|t| i32::try_from(t).ok()).ok_or_else(fail)?,
+        ),
+        Schema::TimeMicros => Avro::TimeMicros(ticks(value, 1_000_000, true).ok_or_else(fail)?),
+        Schema::TimestampMillis => {
+            Avro::TimestampMillis(ticks(value, 1000, false).ok_or_else(fail)?)
+        }
+        Schema::TimestampMicros => {
+            Avro::TimestampMicros(ticks(value, 1_000_000, false).ok_or_else(fail)?)
+        }
+        Schema::TimestampNanos => {
+            Avro::TimestampNanos(ticks(value, 1_000_000_000, false).ok_or_else(fail)?)
+        }
+        Schema::LocalTimestampMillis => {
+            Avro::LocalTimestampMillis(ticks(value, 1000, false).ok_or_else(fail)?)
+        }
+        Schema::LocalTimestampMicros => {
+            Avro::LocalTimestampMicros(ticks(value, 1_000_000, false).ok_or_else(fail)?)
+        }
+        Schema::LocalTimestampNanos => {
+            Avro::LocalTimestampNanos(ticks(value, 1_000_000_000, false).ok_or_else(fail)?)
+        }
+        Schema::Duration(_) => {
+            let part = |key| value.get(key)?.as_u64().and_then(|n| u32::try_from(n).ok());
+            let (months, days, millis) =
+                (|| Some((part("months")?, part("days")?, part("millis")?)))().ok_or_else(fail)?;
+
+            Avro::Duration(Duration::new(Months::new(months), Days::new(days), Millis::new(millis)))
+        }
+        Schema::Array(array) => {
+            let items = value.as_array().ok_or_else(fail)?;
+
+            let items = items.iter().enumerate().map(|(i, item)| {
+                json_to_avro(item, &array.items, names, coerce, strict)
+                    .map_err(|(path, reason)| (format!("/{}{}", i, path), reason))
+            });
+            Avro::Array(items.collect::<Result<_, _>>()?)
+        }
+        Schema::Map(map) => {
+            let obj = value.as_object().ok_or_else(fail)?;
+
+            let entries = obj.iter().map(|(key, value)| {
+                json_to_avro(value, &map.types, names, coerce, strict)
+                    .map(|value| (key.clone(), value))
+                    .map_err(|(path, reason)| (format!("/{}{}", key, path), reason))
+            });
+            Avro::Map(entries.collect::<Result<HashMap<_, _>, _>>()?)
+        }
+        Schema::Record(record) => {
+            let obj = value.as_object().ok_or_else(fail)?;
+            let mut fields = Vec::with_capacity(record.fields.len());
+
+            for field in &record.fields {
+                let value = match (obj.get(&field.name), &field.default) {
+                    (Some(value), _) => value,
+                    (None, Some(default)) => default,
+                    (None, None) => {
+                        return Err((format!("/{}", field.name), "missing field".to_string()));
+                    }
+                };
+
+                let value = json_to_avro(value, &field.schema, names, coerce, strict)
+                    .map_err(|(path, reason)| (format!("/{}{}", field.name, path), reason))?;
+                fields.push((field.name.clone(), value));
+            }
+
+            if strict
+                && let Some(key) = obj.keys().find(|k| record.fields.iter().all(|f| f.name != **k))
+            {
+                return Err((format!("/{}", key), "unknown field".to_string()));
+            }
+
+            Avro::Record(fields)
+        }
+        Schema::Union(union) => {
+            let variants = union.variants();
+
+            // coercing would turn text like `02134` that `-p` left untyped into the long `2134`
+            let string = variants
+                .iter()
+                .position(|schema| *schema == Schema::String)
+                .filter(|_| value.is_string());
+            let mut order =
+                string.into_iter().chain((0..variants.len()).filter(|i| Some(*i) != string));
+
+            let branch = order.find_map(|i| {
+                json_to_avro(value, &variants[i], names, coerce, strict)
+                    .ok()
+                    .map(|value| (i, value))
+            });
+
+            match branch {
+                Some((i, value)) => Avro::Union(i as u32, Box::new(value)),
+                None => return Err(fail()),
+            }
+        }
+        Schema::Ref { name } => {
+            let schema = names.get(name).ok_or_else(fail)?;
+            json_to_avro(value, schema, names, coerce, strict)?
+        }
+    })
+}
+
+/// Records are written into an Avro object container file with the schema at
+/// `Options::avro_schema`, or with a schema inferred from the first `Options::avro_infer`
+/// records. Records that don't match the schema are logged and skipped.
+pub(crate) fn avro_writer<W: Write>(
+    data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+    output: W,
+    options: &Options,
+    types: &[(String, ColumnType)],
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    match data_stream {
+        WriterStreams::Values { iter: values } | WriterStreams::Ndjson { values } => {
+            let values = values.enumerate().map(|(rec_no, rec)| {
+                rec.and_then(DataTypes::into_json)
+                    .map(|obj| (rec_no + 1, obj))
+                    .context("Failed to re-serialize object for writing")
+            });
+
+            write_values(values, output, options, false, conv)
+        }
+
+        WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, options.parse_numbers, types)?;
+
+            // rows that fail to be typed are skipped here so they keep their fields in the reject
+            // file, only the error that uses up the budget is passed on
+            let rows = iter.enumerate().filter_map(|(line_no, rec)| {
+                let line_no = line_no + 1;
+
+                let record = match into_byte_record(rec)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => return Some(Err(e)),
+                };
+                let cells = match inference
+                    .row(&record)
+                    .with_context(|| format!("Failed to infer the types of record: {}", line_no))
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        return conv
+                            .skip_rejected::<Error>(&e, line_no, Rejected::Row(&record))
+                            .err()
+                            .map(Err);
+                    }
+                };
+
+                // empty fields are nulls and integers beyond the range of longs are kept as text
+                let obj = headers.iter().zip(&cells).map(|(h, cell)| {
+                    let value = match cell {
+                        Cell::Str(b"") => Value::Null,
+                        Cell::BigInt(digits) => Value::String(digits.to_string()),
+                        cell => cell.to_json(),
+                    };
+                    (h.clone(), value)
+                });
+                Some(Ok((line_no, Value::Object(obj.collect()))))
+            });
+
+            write_values(rows, output, options, true, conv)
+        }
+    }
+}
+
+fn write_values<W: Write>(
+    mut values: impl Iterator<Item = CtxResult<(usize, Value), Error>>,
+    output: W,
+    options: &Options,
+    coerce: bool,
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let mut buffered = Vec::new();
+    let inferred = options.avro_schema.is_none();
+
+    let schema = match &options.avro_schema {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).with_context(|| {
+                format!("Failed to read Avro schema: {}", path.to_string_lossy())
+            })?;
+
+            Schema::parse_str(&text).map_err(avro_error).with_context(|| {
+                format!("Failed to parse Avro schema: {}", path.to_string_lossy())
+            })?
+        }
+        None => {
+            for rec in values.by_ref() {
+                match rec {
+                    Ok(value) => buffered.push(value),
+                    Err(e) => conv.log_skip::<Error>(&e)?,
+                }
+
+                if buffered.len() >= options.avro_infer.max(1) {
+                    break;
+                }
+            }
+
+            infer_schema(&buffered, coerce)?
+        }
+    };
+
+    let names = avro_names(&schema).context("Failed to resolve the Avro schema")?;
+
+    let codec = match options.avro_codec {
+        AvroCodec::Null => Codec::Null,
+        AvroCodec::Deflate => Codec::Deflate(DeflateSettings::default()),
+        AvroCodec::Snappy => Codec::Snappy,
+    };
+
+    let mut writer = apache_avro::Writer::with_codec(&schema, BufWriter::new(output), codec)
+        .map_err(avro_error)
+        .context("Failed to create Avro writer")?;
+
+    for rec in buffered.into_iter().map(Ok).chain(values) {
+        let (rec_no, value) = match rec {
+            Ok(ok) => ok,
+            Err(e) => {
+                conv.log_skip::<Error>(&e)?;
+                continue;
+            }
+        };
+
+        let avro = match json_to_avro(&value, &schema, &names, coerce, inferred) {
+            Ok(avro) => avro,
+            Err((path, reason)) => {
+                let at = if path.is_empty() { String::new() } else { format!(" at: {}", path) };
+                let e = ErrCtx::new(
+                    Error::new(EK::InvalidData, reason),
+                    format!("Record: {} doesn't match the Avro schema{}", rec_no, at).into_bytes(),
+                );
+                conv.skip_rejected(&e, rec_no, Rejected::Value(&value))?;
+                continue;
+            }
+        };
+
+        writer
+            .append_value_ref(&avro)
+            .map_err(avro_error)
+            .with_context(|| format!("Failed to write record: {} into output file", rec_no))?;
+    }
+
+    let mut output = writer
+        .into_inner()
+        .map_err(avro_error)
+        .context("Failed to flush final bytes into output file")?;
+    output.flush().context("Failed to flush final bytes into output file")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_avro_schema() {
+        let values = [
+            json!({"id": 1, "tags": ["a"], "addr": {"city": "NYC"}, "meta": {"a-b": 1}}),
+            json!({"id": 2.5, "tags": [], "addr": {"city": null, "zip": "1"}, "meta": {}}),
+        ];
+        let values: Vec<(usize, Value)> = values.into_iter().enumerate().collect();
+
+        let schema = infer_schema(&values, false).unwrap();
+        let canonical = schema.canonical_form();
+        assert!(canonical.contains(r#"{"name":"id","type":"double"}"#), "{}", canonical);
+        assert!(canonical.contains(r#"{"name":"zip","type":["null","string"]}"#), "{}", canonical);
+        assert!(canonical.contains(r#"{"type":"map","values":"long"}"#), "{}", canonical);
+
+        let names = avro_names(&schema).unwrap();
+        let (path, reason) = json_to_avro(
+            &json!({"id": 3, "tags": [1], "addr": {}, "meta": {}}),
+            &schema,
+            &names,
+            false,
+            true,
+        )
+        .unwrap_err();
+        assert_eq!((path.as_str(), reason.as_str()), ("/tags/0", "expected string, found 1"));
+
+        // table fields are written from their text
+        let record = json!({"id": "4", "tags": [], "addr": {"city": ""}, "meta": {}});
+        assert!(json_to_avro(&record, &schema, &names, true, true).is_ok());
+
+        // keys the inferred schema doesn't have are only dropped from given schemas
+        let record = json!({"id": 5, "tags": [], "addr": {}, "meta": {}, "new": 1});
+        let (path, _) = json_to_avro(&record, &schema, &names, false, true).unwrap_err();
+        assert_eq!(path, "/new");
+        assert!(json_to_avro(&record, &schema, &names, false, false).is_ok());
+
+        // table fields are nullable and integers beyond longs are strings
+        let rows = [(1, json!({"a": 1, "b": 12345678901234567890u64}))];
+        let canonical = infer_schema(&rows, true).unwrap().canonical_form();
+        assert!(canonical.contains(r#"{"name":"a","type":["null","long"]}"#), "{}", canonical);
+        assert!(canonical.contains(r#"{"name":"b","type":["null","string"]}"#), "{}", canonical);
+    }
+}
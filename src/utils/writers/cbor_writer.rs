@@ -0,0 +1,276 @@
+use std::io::{BufWriter, Error, Write};
+
+use resext::{CtxResult, ResExt};
+use serde_json::Value;
+
+use crate::utils::{
+    Cell, ColumnType, Conversion, DataTypes, Inference, Rejected, WriterStreams, into_byte_record,
+};
+
+/// Writes the head of a data item with the shortest encoding of `arg`.
+fn head<W: Write>(wr: &mut W, major: u8, arg: u64) -> Result<(), Error> {
+    let major = major << 5;
+
+    match arg {
+        0..=23 => wr.write_all(&[major | arg as u8]),
+        24..=0xff => wr.write_all(&[major | 24, arg as u8]),
+        0x100..=0xffff => {
+            wr.write_all(&[major | 25])?;
+            wr.write_all(&(arg as u16).to_be_bytes())
+        }
+        0x1_0000..=0xffff_ffff => {
+            wr.write_all(&[major | 26])?;
+            wr.write_all(&(arg as u32).to_be_bytes())
+        }
+        _ => {
+            wr.write_all(&[major | 27])?;
+            wr.write_all(&arg.to_be_bytes())
+        }
+    }
+}
+
+fn int<W: Write>(wr: &mut W, i: i64) -> Result<(), Error> {
+    if i < 0 { head(wr, 1, (-1 - i) as u64) } else { head(wr, 0, i as u64) }
+}
+
+/// Floats are written as single-precision floats when that's lossless.
+fn float<W: Write>(wr: &mut W, f: f64) -> Result<(), Error> {
+    if (f as f32) as f64 == f || f.is_nan() {
+        wr.write_all(&[0xfa])?;
+        wr.write_all(&(f as f32).to_be_bytes())
+    } else {
+        wr.write_all(&[0xfb])?;
+        wr.write_all(&f.to_be_bytes())
+    }
+}
+
+fn text<W: Write>(wr: &mut W, s: &str) -> Result<(), Error> {
+    head(wr, 3, s.len() as u64)?;
+    wr.write_all(s.as_bytes())
+}
+
+/// Writes a decimal integer that doesn't fit into 64 bits as a bignum (tags 2 and 3).
+fn bignum<W: Write>(wr: &mut W, s: &str) -> Result<(), Error> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+
+    // little-endian bytes of the magnitude
+    let mut bytes: Vec<u8> = vec![0];
+    for d in digits.bytes().map(|b| (b - b'0') as u16) {
+        let mut carry = d;
+        for byte in bytes.iter_mut() {
+            let v = *byte as u16 * 10 + carry;
+            *byte = v as u8;
+            carry = v >> 8;
+        }
+        if carry > 0 {
+            bytes.push(carry as u8);
+        }
+    }
+
+    // negative bignums hold `-1 - n`
+    if negative {
+        for byte in bytes.iter_mut() {
+            let (v, borrow) = byte.overflowing_sub(1);
+            *byte = v;
+            if !borrow {
+                break;
+            }
+        }
+    }
+
+    while bytes.len() > 1 && bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+    bytes.reverse();
+
+    head(wr, 6, if negative { 3 } else { 2 })?;
+    head(wr, 2, bytes.len() as u64)?;
+    wr.write_all(&bytes)
+}
+
+/// Tag and value of a `{"$tag": <tag>, "$value": <value>}` object.
+fn tag(obj: &serde_json::Map<String, Value>) -> Option<(u64, &Value)> {
+    if obj.len() != 2 {
+        return None;
+    }
+
+    Some((obj.get("$tag")?.as_u64()?, obj.get("$value")?))
+}
+
+/// Writes a JSON value as a CBOR item, `$tag` / `$simple` objects are written back as tags and
+/// simple values.
+fn write_value<W: Write>(wr: &mut W, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::Null => wr.write_all(&[0xf6]),
+        Value::Bool(b) => wr.write_all(&[if *b { 0xf5 } else { 0xf4 }]),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => head(wr, 0, u),
+            (None, Some(i)) => int(wr, i),
+            _ => float(wr, n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => text(wr, s),
+        Value::Array(items) => {
+            head(wr, 4, items.len() as u64)?;
+            items.iter().try_for_each(|item| write_value(wr, item))
+        }
+        Value::Object(obj) => {
+            if let Some((t, v)) = tag(obj) {
+                head(wr, 6, t)?;
+                return write_value(wr, v);
+            }
+
+            let simple = obj.get("$simple").and_then(Value::as_u64).filter(|_| obj.len() == 1);
+            match simple.and_then(|n| u8::try_from(n).ok()) {
+                Some(n @ (0..=19 | 32..)) => head(wr, 7, n as u64),
+                _ => {
+                    head(wr, 5, obj.len() as u64)?;
+                    obj.iter().try_for_each(|(k, v)| {
+                        text(wr, k)?;
+                        write_value(wr, v)
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Writes a typed table field as a CBOR item, dates and datetimes are tagged as date / time
+/// strings.
+fn write_cell<W: Write>(wr: &mut W, cell: &Cell) -> Result<(), Error> {
+    match cell {
+        Cell::Null => wr.write_all(&[0xf6]),
+        Cell::Bool(b) => wr.write_all(&[if *b { 0xf5 } else { 0xf4 }]),
+        Cell::Int(i) => int(wr, *i),
+        Cell::BigInt(s) => match s.parse::<u64>() {
+            Ok(u) => head(wr, 0, u),
+            Err(_) => bignum(wr, s),
+        },
+        Cell::Float(f) => float(wr, *f),
+        Cell::Datetime(dt) => {
+            match (dt.date, dt.time, dt.offset) {
+                (Some(_), Some(_), Some(_)) => head(wr, 6, 0)?,
+                (Some(_), None, None) => head(wr, 6, 1004)?,
+                _ => {}
+            }
+            text(wr, &dt.to_string())
+        }
+        Cell::Str(s) => text(wr, &String::from_utf8_lossy(s)),
+        Cell::List(l) => {
+            head(wr, 4, l.len() as u64)?;
+            l.iter().try_for_each(|s| text(wr, s))
+        }
+    }
+}
+
+/// Every record is written as its own top-level item, so the output is a CBOR sequence (RFC
+/// 8742). Table rows become maps keyed by their headers.
+#[inline]
+pub(crate) fn cbor_writer<W: Write>(
+    data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+    output: W,
+    parse_numbers: bool,
+    types: &[(String, ColumnType)],
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let mut buffered_writer = BufWriter::new(output);
+
+    match data_stream {
+        WriterStreams::Values { iter: values } | WriterStreams::Ndjson { values } => {
+            for (rec_no, rec) in values.enumerate() {
+                let rec_no = rec_no + 1;
+
+                let obj = match rec
+                    .and_then(DataTypes::into_json)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                write_value(&mut buffered_writer, &obj).with_context(|| {
+                    format!("Failed to write record: {} into output file", rec_no)
+                })?;
+            }
+        }
+
+        WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, parse_numbers, types)?;
+
+            for (line_no, rec) in iter.enumerate() {
+                let line_no = line_no + 1;
+
+                let record = match into_byte_record(rec)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let cells = match inference
+                    .row(&record)
+                    .with_context(|| format!("Failed to infer the types of record: {}", line_no))
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.skip_rejected::<Error>(&e, line_no, Rejected::Row(&record))?;
+                        continue;
+                    }
+                };
+
+                // rows are written straight from their fields without building a value first
+                let written: Result<(), Error> = (|| {
+                    head(&mut buffered_writer, 5, headers.len() as u64)?;
+                    for (h, cell) in headers.iter().zip(&cells) {
+                        text(&mut buffered_writer, h)?;
+                        write_cell(&mut buffered_writer, cell)?;
+                    }
+                    Ok(())
+                })();
+
+                written.with_context(|| {
+                    format!("Failed to write record: {} into output file", line_no)
+                })?;
+            }
+        }
+    }
+
+    buffered_writer.flush().context("Failed to flush final bytes into output file")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{Binary, CborTags, cbor_decoder::cbor_to_json, cbor_reader::cbor_reader};
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let value = serde_json::json!({
+            "n": [0, 23, 24, -1, -500, 4294967296u64, 1.5, 0.1],
+            "at": {"$tag": 1, "$value": 0},
+            "s": {"$simple": 99},
+            "t": "x"
+        });
+
+        let mut buf = Vec::new();
+        write_value(&mut buf, &value).unwrap();
+        assert_eq!(&buf[..5], b"\xa4\x61n\x88\x00");
+
+        let item = cbor_reader(buf.as_slice()).read_item().unwrap().unwrap();
+        assert_eq!(cbor_to_json(item, Binary::Base64, CborTags::Preserve), value);
+
+        let mut buf = Vec::new();
+        bignum(&mut buf, "-18446744073709551617").unwrap();
+        assert_eq!(buf, b"\xc3\x49\x01\x00\x00\x00\x00\x00\x00\x00\x00");
+    }
+}
@@ -1,58 +1,285 @@
-use resext::{CtxResult, ErrCtx, ResExt, throw_err_if};
+use resext::{CtxResult, ErrCtx, ResExt};
 
-use crate::utils::{DataTypes, WriterStreams, into_byte_record};
+use crate::utils::{
+    Conversion, CsvDialect, DataTypes, LateKeys, QuoteStyle, Rejected, Terminator, WriterStreams,
+    csv_reader::ascii, flatten_json, into_byte_record,
+};
 
-use std::io::{BufWriter, Error};
+use std::{
+    collections::HashMap,
+    io::{BufWriter, Error, ErrorKind as EK, Write},
+};
 
 #[inline]
-pub(crate) fn csv_writer(
+pub(crate) fn csv_writer<W: Write>(
     data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
-    file: std::fs::File,
+    output: W,
     delimiter: char,
+    dialect: &CsvDialect,
+    late_keys: LateKeys,
+    conv: &Conversion,
 ) -> CtxResult<(), Error> {
-    let buffered = BufWriter::new(file);
+    let buffered = BufWriter::new(output);
 
-    throw_err_if!(
-        !delimiter.is_ascii(),
-        || format!("Output delimiter: {} is not valid UTF-8", delimiter),
-        1
-    );
+    let mut builder = csv::WriterBuilder::new();
 
-    let d = delimiter as u8;
-    let mut wtr = csv::WriterBuilder::new().delimiter(d).from_writer(buffered);
+    builder
+        .delimiter(ascii(delimiter, "Output delimiter")?)
+        .quote(ascii(dialect.quote, "Output quote")?)
+        .double_quote(dialect.double_quote)
+        .terminator(match dialect.terminator {
+            None | Some(Terminator::Lf) => csv::Terminator::Any(b'\n'),
+            Some(Terminator::Crlf) => csv::Terminator::CRLF,
+            Some(Terminator::Cr) => csv::Terminator::Any(b'\r'),
+        })
+        .quote_style(match dialect.quote_style {
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+            QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+        });
+
+    if let Some(escape) = dialect.escape {
+        builder.escape(ascii(escape, "Output escape")?);
+    }
+
+    let mut wtr = builder.from_writer(buffered);
+    let has_headers = dialect.has_headers;
 
     match data_stream {
         WriterStreams::Table { headers, iter } => {
             // write headers
-            wtr.write_record(&headers)
-                .map_err(|_| Error::other("Failed to write headers"))
-                .context("Failed to write headers into output file")?;
+            if has_headers {
+                write_headers(&mut wtr, &headers)?;
+            }
 
             // write records
             for (line_no, line) in iter.enumerate() {
-                let b = into_byte_record(line)
+                let b = match into_byte_record(line)
                     .context("Failed to re-serialize object for writing")
-                    .unwrap_or_else(|e: ErrCtx<Error>| {
-                        crate::utils::log_err(&e)
-                            .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-                        csv::ByteRecord::from(vec![b""; headers.len()])
-                    });
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
                 wtr.write_record(&b)
                     .map_err(|_| Error::other("Failed to write CSV record"))
                     .with_context(|| format!("Failed to write CSV record at: {}", line_no + 1))?;
             }
+        }
 
-            // flush writer
-            wtr.flush()
-                .map_err(|_| Error::other("Failed to flush"))
-                .context("Failed to flush final bytes into output file")?;
+        WriterStreams::Values { iter } => {
+            // whole documents (e.g. a JSON array or a TOML file with `[[Rows]]`) get unwrapped into
+            // their rows first
+            let rows = iter.flat_map(|item| match item.and_then(DataTypes::into_json) {
+                Ok(data) => into_rows(data).into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            });
+
+            write_flattened(&mut wtr, rows, late_keys, has_headers, conv)?;
         }
-        _ => {
-            eprintln!("CSV only supports table-based formats with headers");
-            eprintln!("» Support for non-table formats will be added soon");
-            std::process::exit(1);
+
+        WriterStreams::Ndjson { values } => {
+            let rows = values.map(|item| item.and_then(DataTypes::into_json));
+
+            write_flattened(&mut wtr, rows, late_keys, has_headers, conv)?;
         }
     }
 
+    // flush writer
+    wtr.flush()
+        .map_err(|_| Error::other("Failed to flush"))
+        .context("Failed to flush final bytes into output file")?;
+
     Ok(())
 }
+
+/// Splits a whole document into the records it holds: arrays yield their elements and objects
+/// with a single array-of-objects key (e.g. `{"Rows": [...]}`) yield that array's elements.
+fn into_rows(value: serde_json::Value) -> Vec<serde_json::Value> {
+    match value {
+        serde_json::Value::Array(arr) => arr,
+        serde_json::Value::Object(mut map) if map.len() == 1 => {
+            let Some(serde_json::Value::Array(arr)) = map.values_mut().next() else {
+                return vec![serde_json::Value::Object(map)];
+            };
+
+            if !arr.iter().all(serde_json::Value::is_object) {
+                return vec![serde_json::Value::Object(map)];
+            }
+
+            std::mem::take(arr)
+        }
+        other => vec![other],
+    }
+}
+
+/// Writes records as CSV rows by flattening them into dotted columns, the header row is derived
+/// from the records' keys according to the `late_keys` policy.
+fn write_flattened<W: Write>(
+    wtr: &mut csv::Writer<W>,
+    rows: impl Iterator<Item = CtxResult<serde_json::Value, Error>>,
+    late_keys: LateKeys,
+    has_headers: bool,
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let mut headers: Vec<String> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut prefix = String::new();
+    let mut headers_written = false;
+
+    // only used with `LateKeys::Union` since the header row can't be written before every record
+    // has been seen, rows keep their input record number for error messages
+    let mut buffered: Vec<(usize, Vec<(usize, String)>)> = Vec::new();
+
+    for (rec_no, row) in rows.enumerate() {
+        let rec_no = rec_no + 1;
+
+        let row: CtxResult<_, Error> = row.context("Failed to re-serialize record for writing");
+
+        let row = match row {
+            Ok(ok) => ok,
+            Err(e) => {
+                conv.log_skip::<Error>(&e)?;
+                continue;
+            }
+        };
+
+        let mut flat = Vec::new();
+        flatten_json(&mut prefix, &row, &mut flat);
+
+        let first = !headers_written && late_keys != LateKeys::Union;
+        let mut fields = Vec::with_capacity(flat.len());
+        let mut rejected = false;
+
+        for (key, leaf) in flat {
+            let idx = match index.get(&key) {
+                Some(idx) => *idx,
+                None if first || late_keys == LateKeys::Union => {
+                    headers.push(key.clone());
+                    index.insert(key, headers.len() - 1);
+                    headers.len() - 1
+                }
+                None => {
+                    let e = ErrCtx::new(
+                        Error::new(EK::InvalidData, "Key is missing from headers"),
+                        format!(
+                            "Record: {} has key: {} which is not in the header row\n- Use `--late-keys union` to include keys that appear late",
+                            rec_no, key
+                        )
+                        .into_bytes(),
+                    );
+                    if late_keys == LateKeys::Error {
+                        conv.skip_rejected(&e, rec_no, Rejected::Value(&row))?;
+                        rejected = true;
+                        break;
+                    }
+
+                    crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
+
+                    // log every late key only once
+                    index.insert(key, usize::MAX);
+                    continue;
+                }
+            };
+
+            if idx != usize::MAX {
+                fields.push((idx, leaf_to_string(leaf)));
+            }
+        }
+
+        if first {
+            if has_headers {
+                write_headers(wtr, &headers)?;
+            }
+            headers_written = true;
+        }
+
+        if rejected {
+            continue;
+        }
+
+        if late_keys == LateKeys::Union {
+            buffered.push((rec_no, fields));
+        } else {
+            write_row(wtr, &fields, headers.len(), rec_no)?;
+        }
+    }
+
+    if late_keys == LateKeys::Union {
+        if has_headers {
+            write_headers(wtr, &headers)?;
+        }
+
+        for (rec_no, fields) in &buffered {
+            write_row(wtr, fields, headers.len(), *rec_no)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the header row, nothing is written without headers since an empty record would be
+/// written as a single empty field.
+#[inline]
+fn write_headers<W: Write>(wtr: &mut csv::Writer<W>, headers: &[String]) -> CtxResult<(), Error> {
+    if headers.is_empty() {
+        return Ok(());
+    }
+
+    wtr.write_record(headers)
+        .map_err(|_| Error::other("Failed to write headers"))
+        .context("Failed to write headers into output file")
+}
+
+#[inline]
+fn write_row<W: Write>(
+    wtr: &mut csv::Writer<W>,
+    fields: &[(usize, String)],
+    len: usize,
+    rec_no: usize,
+) -> CtxResult<(), Error> {
+    if len == 0 {
+        return Ok(());
+    }
+
+    let mut row = vec![""; len];
+    for (idx, v) in fields {
+        row[*idx] = v;
+    }
+
+    wtr.write_record(&row)
+        .map_err(|_| Error::other("Failed to write CSV record"))
+        .with_context(|| format!("Failed to write CSV record at: {}", rec_no))
+}
+
+#[inline]
+fn leaf_to_string(leaf: &serde_json::Value) -> String {
+    match leaf {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_into_rows() {
+        assert_eq!(into_rows(json!([{"a": 1}, 2])), [json!({"a": 1}), json!(2)]);
+        assert_eq!(
+            into_rows(json!({"Rows": [{"a": 1}, {"a": 2}]})),
+            [json!({"a": 1}), json!({"a": 2})]
+        );
+
+        // objects with other keys or arrays that aren't all objects are a single row
+        assert_eq!(into_rows(json!({"tags": ["a"]})), [json!({"tags": ["a"]})]);
+        assert_eq!(into_rows(json!({"Rows": [{"a": 1}], "b": 2})).len(), 1);
+    }
+}
@@ -1,4 +1,13 @@
+pub(crate) mod arrow_writer;
+pub(crate) mod avro_writer;
+pub(crate) mod cbor_writer;
 pub(crate) mod csv_writer;
+pub(crate) mod msgpack_writer;
 pub(crate) mod ndjson_writer;
+pub(crate) mod parquet_writer;
+pub(crate) mod record_batches;
+pub(crate) mod toml_emitter;
 pub(crate) mod toml_writer;
 pub(crate) mod write_json;
+pub(crate) mod xml_writer;
+pub(crate) mod yaml_writer;
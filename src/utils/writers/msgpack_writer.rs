@@ -0,0 +1,215 @@
+use std::io::{BufWriter, Error, ErrorKind as EK, Write};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use resext::{CtxResult, ResExt};
+use rmp::encode;
+use serde_json::Value;
+
+use crate::utils::{
+    Cell, ColumnType, Conversion, DataTypes, Inference, Rejected, WriterStreams, into_byte_record,
+};
+
+/// Bytes of a `{"$ext": {"type": <type>, "data": <bytes>}}` object, with its data as a base64
+/// string or an array of byte values.
+fn ext(value: &Value) -> Option<(i8, Vec<u8>)> {
+    let obj = value.as_object().filter(|obj| obj.len() == 1)?.get("$ext")?.as_object()?;
+
+    let ty = i8::try_from(obj.get("type")?.as_i64()?).ok()?;
+    let data = match obj.get("data")? {
+        Value::String(s) => STANDARD.decode(s).ok()?,
+        Value::Array(items) => items
+            .iter()
+            .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+
+    (obj.len() == 2).then_some((ty, data))
+}
+
+#[inline]
+fn len(n: usize) -> Result<u32, Error> {
+    u32::try_from(n)
+        .map_err(|_| Error::new(EK::InvalidData, "Collection is too large for MessagePack"))
+}
+
+/// Writes a JSON value as a MessagePack value, `$ext` objects are written back as ext values.
+fn write_value<W: Write>(wr: &mut W, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::Null => encode::write_nil(wr)?,
+        Value::Bool(b) => encode::write_bool(wr, *b)?,
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => {
+                encode::write_sint(wr, i)?;
+            }
+            (None, Some(u)) => {
+                encode::write_uint(wr, u)?;
+            }
+            _ => encode::write_f64(wr, n.as_f64().unwrap_or(f64::NAN))?,
+        },
+        Value::String(s) => encode::write_str(wr, s)?,
+        Value::Array(items) => {
+            encode::write_array_len(wr, len(items.len())?)?;
+            for item in items {
+                write_value(wr, item)?;
+            }
+        }
+        Value::Object(obj) => match ext(value) {
+            Some((ty, data)) => {
+                encode::write_ext_meta(wr, len(data.len())?, ty)?;
+                wr.write_all(&data)?;
+            }
+            None => {
+                encode::write_map_len(wr, len(obj.len())?)?;
+                for (k, v) in obj {
+                    encode::write_str(wr, k)?;
+                    write_value(wr, v)?;
+                }
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// Writes a typed table field as a MessagePack value.
+fn write_cell<W: Write>(wr: &mut W, cell: &Cell) -> Result<(), Error> {
+    match cell {
+        Cell::Null => encode::write_nil(wr)?,
+        Cell::Bool(b) => encode::write_bool(wr, *b)?,
+        Cell::Int(i) => {
+            encode::write_sint(wr, *i)?;
+        }
+        Cell::BigInt(s) => match s.parse::<u64>() {
+            Ok(u) => {
+                encode::write_uint(wr, u)?;
+            }
+            Err(_) => encode::write_str(wr, s)?,
+        },
+        Cell::Float(f) => encode::write_f64(wr, *f)?,
+        Cell::Datetime(dt) => encode::write_str(wr, &dt.to_string())?,
+        Cell::Str(s) => encode::write_str(wr, &String::from_utf8_lossy(s))?,
+        Cell::List(l) => {
+            encode::write_array_len(wr, l.len() as u32)?;
+            for s in l {
+                encode::write_str(wr, s)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Every record is written as its own top-level value, so the output is a stream of concatenated
+/// MessagePack values. Table rows become maps keyed by their headers.
+#[inline]
+pub(crate) fn msgpack_writer<W: Write>(
+    data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+    output: W,
+    parse_numbers: bool,
+    types: &[(String, ColumnType)],
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let mut buffered_writer = BufWriter::new(output);
+
+    match data_stream {
+        WriterStreams::Values { iter: values } | WriterStreams::Ndjson { values } => {
+            for (rec_no, rec) in values.enumerate() {
+                let rec_no = rec_no + 1;
+
+                let obj = match rec
+                    .and_then(DataTypes::into_json)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                write_value(&mut buffered_writer, &obj).with_context(|| {
+                    format!("Failed to write record: {} into output file", rec_no)
+                })?;
+            }
+        }
+
+        WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, parse_numbers, types)?;
+            let map_len = len(headers.len()).context("Failed to write header row")?;
+
+            for (line_no, rec) in iter.enumerate() {
+                let line_no = line_no + 1;
+
+                let record = match into_byte_record(rec)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let cells = match inference
+                    .row(&record)
+                    .with_context(|| format!("Failed to infer the types of record: {}", line_no))
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.skip_rejected::<Error>(&e, line_no, Rejected::Row(&record))?;
+                        continue;
+                    }
+                };
+
+                // rows are written straight from their fields without building a value first
+                let written: Result<(), Error> = (|| {
+                    encode::write_map_len(&mut buffered_writer, map_len)?;
+                    for (h, cell) in headers.iter().zip(&cells) {
+                        encode::write_str(&mut buffered_writer, h)?;
+                        write_cell(&mut buffered_writer, cell)?;
+                    }
+                    Ok(())
+                })();
+
+                written.with_context(|| {
+                    format!("Failed to write record: {} into output file", line_no)
+                })?;
+            }
+        }
+    }
+
+    buffered_writer.flush().context("Failed to flush final bytes into output file")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_ext_values() {
+        let value = serde_json::json!({
+            "ts": {"$ext": {"type": -1, "data": "AAAAAQ=="}},
+            "raw": {"$ext": {"type": 5, "data": [1, 2]}},
+            "not_ext": {"$ext": {"type": 500, "data": ""}}
+        });
+
+        let mut buf = Vec::new();
+        write_value(&mut buf, &value).unwrap();
+
+        let decoded = rmpv::decode::read_value(&mut buf.as_slice()).unwrap();
+        let map: Vec<(String, rmpv::Value)> = decoded
+            .as_map()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.as_str().unwrap().to_string(), v.clone()))
+            .collect();
+
+        assert_eq!(map[0], ("ts".to_string(), rmpv::Value::Ext(-1, vec![0, 0, 0, 1])));
+        assert_eq!(map[1], ("raw".to_string(), rmpv::Value::Ext(5, vec![1, 2])));
+        assert_eq!(map[2], ("not_ext".to_string(), map[2].1.clone()));
+        assert!(map[2].1.is_map());
+    }
+}
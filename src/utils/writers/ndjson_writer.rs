@@ -1,42 +1,45 @@
-use std::{
-    fs::File,
-    io::{BufWriter, Error, Write},
-};
+use std::io::{BufWriter, Error, Write};
 
-use resext::{CtxResult, ErrCtx, ResExt};
+use resext::{CtxResult, ResExt};
 use serde_json::Value;
 
-use crate::utils::{DataTypes, WriterStreams, escape, into_byte_record};
+use crate::utils::{
+    ColumnType, Conversion, DataTypes, Inference, Rejected, WriterStreams, into_byte_record,
+};
 
 #[inline]
-pub(crate) fn ndjson_writer(
+pub(crate) fn ndjson_writer<W: Write>(
     data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
-    file: File,
+    output: W,
     parse_numbers: bool,
+    types: &[(String, ColumnType)],
+    conv: &Conversion,
 ) -> CtxResult<(), Error> {
-    let mut writer = BufWriter::new(file);
+    let mut writer = BufWriter::new(output);
 
     match data_stream {
         WriterStreams::Values { iter } => {
             for (line_no, item) in iter.enumerate() {
                 let line_no = line_no + 1;
-                let json = serde_json::to_value(
-                    item.context("Failed to re-serialize record for writing").unwrap_or_else(
-                        |e: ErrCtx<Error>| {
-                            crate::utils::log_err(&e)
-                                .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-
-                            DataTypes::Json(serde_json::json!({}))
-                        },
-                    ),
-                )
-                .context("Failed to re-serialize record")
-                .context("Invalid NDJSON values in input file")
-                .unwrap_or_else(|e: ErrCtx<serde_json::Error>| {
-                    crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-
-                    serde_json::json!({})
-                });
+                let item = match item.context("Failed to re-serialize record for writing") {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        // invalid records are skipped instead of writing a placeholder
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let json = match serde_json::to_value(item)
+                    .context("Failed to re-serialize record")
+                    .context("Invalid NDJSON values in input file")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<serde_json::Error>(&e)?;
+                        continue;
+                    }
+                };
 
                 if let Value::Array(arr) = json {
                     for (idx, obj) in arr.iter().enumerate() {
@@ -51,7 +54,8 @@ pub(crate) fn ndjson_writer(
                             format!("Failed to write newline delimiter after object: {}", idx)
                         })?;
                     }
-                } else if let Value::Object(_) = json {
+                } else {
+                    // top-level scalars are valid NDJSON lines too
                     serde_json::to_writer(&mut writer, &json)
                         .map_err(|_| Error::new(std::io::ErrorKind::WriteZero, "Failed to write"))
                         .with_context(|| {
@@ -63,9 +67,12 @@ pub(crate) fn ndjson_writer(
                     })?;
                 }
             }
+
+            writer.flush().context("Failed to flush final bytes into output file")?;
         }
 
         WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, parse_numbers, types)?;
             let mut esc_buf: Vec<u8> = Vec::with_capacity(10);
 
             let headers: Vec<String> = headers
@@ -81,6 +88,28 @@ pub(crate) fn ndjson_writer(
 
             for (line_no, rec) in iter.enumerate() {
                 let line_no = line_no + 1;
+
+                let record = match into_byte_record(rec)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let cells = match inference
+                    .row(&record)
+                    .with_context(|| format!("Failed to infer the types of record: {}", line_no))
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.skip_rejected::<Error>(&e, line_no, Rejected::Row(&record))?;
+                        continue;
+                    }
+                };
+
                 writer.write(b"{").with_context(|| {
                     format!(
                         "Failed to write opening curly brace for object: {} into output file",
@@ -90,31 +119,9 @@ pub(crate) fn ndjson_writer(
 
                 let mut first_value = true;
 
-                let record = into_byte_record(rec)
-                    .context("Failed to re-serialize object for writing")
-                    .unwrap_or_else(|e: ErrCtx<Error>| {
-                        crate::utils::log_err(&e)
-                            .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-                        csv::ByteRecord::with_capacity(0, 0)
-                    });
-
-                for (idx, (h, v)) in headers.iter().zip(record.iter()).enumerate() {
+                for (idx, (h, v)) in headers.iter().zip(cells.iter()).enumerate() {
                     esc_buf.clear();
-                    if matches!(v, b"true" | b"false" | b"null")
-                        || (parse_numbers
-                            && v.first()
-                                .is_some_and(|b| *b == b'-' || *b == b'+' || b.is_ascii_digit())
-                            && v.last().is_some_and(|b| b.is_ascii_digit())
-                            && std::str::from_utf8(v).unwrap_or("").parse::<f64>().is_ok())
-                    {
-                        esc_buf.extend_from_slice(v);
-                    } else {
-                        esc_buf.push(b'"');
-                        v.iter().for_each(|byte| {
-                            escape(*byte, &mut esc_buf);
-                        });
-                        esc_buf.push(b'"');
-                    }
+                    v.write_json(&mut esc_buf);
 
                     if first_value {
                         writer.write_all(b"\"").with_context(|| format!("Failed to write opening quote for key in key-value pair: {} in object: {} into output file", idx, line_no))?;
@@ -148,18 +155,24 @@ pub(crate) fn ndjson_writer(
 
         WriterStreams::Ndjson { values } => {
             for (line_no, item) in values.enumerate() {
-                let json = item
-                    .context("Failed to re-serialize record for writing")
-                    .unwrap_or_else(|e: ErrCtx<Error>| {
-                        crate::utils::log_err(&e)
-                            .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-                        DataTypes::Json(serde_json::json!({}))
-                    });
+                let json = match item.context("Failed to re-serialize record for writing") {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
 
                 serde_json::to_writer(&mut writer, &json)
                     .map_err(|_| Error::new(std::io::ErrorKind::WriteZero, "Failed to write"))
                     .with_context(|| format!("Failed to write NDJSON object: {}", line_no + 1))?;
+
+                writeln!(writer).with_context(|| {
+                    format!("Failed to write newline delimiter after object: {}", line_no + 1)
+                })?;
             }
+
+            writer.flush().context("Failed to flush final bytes into output file")?;
         }
     }
 
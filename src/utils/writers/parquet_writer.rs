@@ -0,0 +1,91 @@
+use std::io::{Error, Write};
+
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use resext::CtxResult;
+
+use crate::utils::{
+    ColumnType, Conversion, DataTypes, Options, ParquetCompression, WriterStreams,
+    record_batches::{BATCH_SIZE, BatchSink, write_batches},
+};
+
+#[inline]
+fn parquet_error(e: impl std::fmt::Display) -> Error {
+    Error::other(format!("Failed to write Parquet: {}", e))
+}
+
+/// Parquet file that's written into `output` one row group at a time, row groups are encoded in
+/// memory and moved into `output` once they're flushed.
+struct Sink<W: Write> {
+    writer: Option<ArrowWriter<Vec<u8>>>,
+    output: W,
+    props: WriterProperties,
+}
+
+impl<W: Write> BatchSink for Sink<W> {
+    fn start(&mut self, schema: SchemaRef) -> Result<(), Error> {
+        let writer = ArrowWriter::try_new(Vec::new(), schema, Some(self.props.clone()))
+            .map_err(parquet_error)?;
+        self.writer = Some(writer);
+        Ok(())
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), Error> {
+        let Some(writer) = self.writer.as_mut() else { return Ok(()) };
+
+        writer.write(batch).map_err(parquet_error)?;
+
+        let bytes = std::mem::take(writer.inner_mut());
+        self.output.write_all(&bytes)
+    }
+
+    fn finish(mut self, schema: SchemaRef) -> Result<(), Error> {
+        if self.writer.is_none() {
+            self.start(schema)?;
+        }
+
+        if let Some(writer) = self.writer.take() {
+            let bytes = writer.into_inner().map_err(parquet_error)?;
+            self.output.write_all(&bytes)?;
+        }
+
+        self.output.flush()
+    }
+}
+
+/// Rows are buffered into record batches and written one row group at a time, the Arrow schema is
+/// inferred from the first batch (see `write_batches`).
+pub(crate) fn parquet_writer<W: Write>(
+    data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+    output: W,
+    options: &Options,
+    types: &[(String, ColumnType)],
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let compression = match options.parquet_compression {
+        ParquetCompression::Uncompressed => Compression::UNCOMPRESSED,
+        ParquetCompression::Snappy => Compression::SNAPPY,
+        ParquetCompression::Gzip => Compression::GZIP(Default::default()),
+        ParquetCompression::Zstd => Compression::ZSTD(Default::default()),
+    };
+
+    let row_group_size = options.row_group_size.max(1);
+
+    let props = WriterProperties::builder()
+        .set_max_row_group_row_count(Some(row_group_size))
+        .set_compression(compression)
+        .build();
+
+    let sink = Sink { writer: None, output, props };
+
+    write_batches(
+        data_stream,
+        sink,
+        options,
+        types,
+        row_group_size.min(BATCH_SIZE),
+        "Parquet",
+        conv,
+    )
+}
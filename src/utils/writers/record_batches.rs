@@ -0,0 +1,514 @@
+use std::{
+    io::{Error, ErrorKind as EK},
+    sync::Arc,
+};
+
+use arrow_array::{
+    ArrayRef, RecordBatch,
+    builder::{
+        BooleanBuilder, Date32Builder, Float64Builder, Int64Builder, StringBuilder,
+        TimestampMicrosecondBuilder,
+    },
+};
+use arrow_json::reader::{Decoder, ReaderBuilder, infer_json_schema_from_iterator};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use resext::{CtxResult, ErrCtx, ResExt};
+use serde_json::Value;
+use toml::value::Datetime;
+
+use crate::utils::{
+    Cell, ColumnType, Conversion, DataTypes, Inference, Options, Rejected, WriterStreams,
+    epoch_days, epoch_seconds, into_byte_record,
+};
+
+/// Maximum number of records in a record batch, the schema is inferred from the first one.
+pub(crate) const BATCH_SIZE: usize = 64 * 1024;
+
+#[inline]
+fn batch_error(format: &str, e: impl std::fmt::Display) -> Error {
+    Error::other(format!("Failed to write {}: {}", format, e))
+}
+
+/// Columnar output that record batches are written into, the schema is known once the first
+/// batch has been inferred.
+pub(crate) trait BatchSink {
+    fn start(&mut self, schema: SchemaRef) -> Result<(), Error>;
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), Error>;
+
+    /// Writes the last batch and the footer, `schema` is used if nothing was written.
+    fn finish(self, schema: SchemaRef) -> Result<(), Error>;
+}
+
+fn days(dt: &Datetime) -> Option<i32> {
+    i32::try_from(epoch_days(&dt.date?)).ok()
+}
+
+/// Microseconds since the Unix epoch, datetimes without an offset are read as UTC.
+fn micros(dt: &Datetime) -> Option<i64> {
+    let (secs, nanos) = epoch_seconds(dt)?;
+
+    secs.checked_mul(1_000_000)?.checked_add(i64::from(nanos / 1000))
+}
+
+/// Arrow column types inferred from table fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Null,
+    Bool,
+    Int,
+    Float,
+    Date,
+    Timestamp,
+    Str,
+}
+
+impl Kind {
+    /// Kind of a field, empty fields are nulls and integers that don't fit into 64 bits are
+    /// strings.
+    fn of(cell: &Cell) -> Kind {
+        match cell {
+            Cell::Null | Cell::Str(b"") => Kind::Null,
+            Cell::Bool(_) => Kind::Bool,
+            Cell::Int(_) => Kind::Int,
+            Cell::Float(_) => Kind::Float,
+            Cell::Datetime(dt) => match (dt.date, dt.time) {
+                (Some(_), None) => Kind::Date,
+                (Some(_), Some(_)) => Kind::Timestamp,
+                _ => Kind::Str,
+            },
+            // extra fields of ragged rows keep their JSON array text
+            Cell::BigInt(_) | Cell::Str(_) | Cell::List(_) => Kind::Str,
+        }
+    }
+
+    /// Narrowest kind that holds values of both kinds.
+    fn unify(self, other: Kind) -> Kind {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Kind::Null, k) | (k, Kind::Null) => k,
+            (Kind::Int, Kind::Float) | (Kind::Float, Kind::Int) => Kind::Float,
+            _ => Kind::Str,
+        }
+    }
+}
+
+/// Builder of an Arrow column from table fields.
+enum Column {
+    Bool(BooleanBuilder),
+    Int(Int64Builder),
+    Float(Float64Builder),
+    Date(Date32Builder),
+    Timestamp(TimestampMicrosecondBuilder),
+    Str(StringBuilder),
+}
+
+impl Column {
+    fn new(kind: Kind) -> Self {
+        match kind {
+            Kind::Bool => Column::Bool(BooleanBuilder::new()),
+            Kind::Int => Column::Int(Int64Builder::new()),
+            Kind::Float => Column::Float(Float64Builder::new()),
+            Kind::Date => Column::Date(Date32Builder::new()),
+            Kind::Timestamp => {
+                Column::Timestamp(TimestampMicrosecondBuilder::new().with_timezone("UTC"))
+            }
+            // columns without any values in the first batch are strings
+            Kind::Null | Kind::Str => Column::Str(StringBuilder::new()),
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            Column::Bool(_) => DataType::Boolean,
+            Column::Int(_) => DataType::Int64,
+            Column::Float(_) => DataType::Float64,
+            Column::Date(_) => DataType::Date32,
+            Column::Timestamp(_) => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            Column::Str(_) => DataType::Utf8,
+        }
+    }
+
+    /// Whether `cell` can be appended to the column, string columns take the raw text of any
+    /// field.
+    fn accepts(&self, cell: &Cell) -> bool {
+        matches!(
+            (self, Kind::of(cell)),
+            (Column::Str(_), _)
+                | (_, Kind::Null)
+                | (Column::Bool(_), Kind::Bool)
+                | (Column::Int(_), Kind::Int)
+                | (Column::Float(_), Kind::Int | Kind::Float)
+                | (Column::Date(_), Kind::Date)
+                | (Column::Timestamp(_), Kind::Timestamp)
+        )
+    }
+
+    fn append(&mut self, cell: &Cell, raw: &[u8]) {
+        match (self, cell) {
+            (Column::Str(b), Cell::Null) => b.append_null(),
+            (Column::Str(b), _) => b.append_value(String::from_utf8_lossy(raw)),
+            (Column::Bool(b), Cell::Bool(v)) => b.append_value(*v),
+            (Column::Int(b), Cell::Int(v)) => b.append_value(*v),
+            (Column::Float(b), Cell::Float(v)) => b.append_value(*v),
+            (Column::Float(b), Cell::Int(v)) => b.append_value(*v as f64),
+            (Column::Date(b), Cell::Datetime(dt)) => b.append_option(days(dt)),
+            (Column::Timestamp(b), Cell::Datetime(dt)) => b.append_option(micros(dt)),
+            (Column::Bool(b), _) => b.append_null(),
+            (Column::Int(b), _) => b.append_null(),
+            (Column::Float(b), _) => b.append_null(),
+            (Column::Date(b), _) => b.append_null(),
+            (Column::Timestamp(b), _) => b.append_null(),
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Column::Bool(b) => Arc::new(b.finish()),
+            Column::Int(b) => Arc::new(b.finish()),
+            Column::Float(b) => Arc::new(b.finish()),
+            Column::Date(b) => Arc::new(b.finish()),
+            Column::Timestamp(b) => Arc::new(b.finish()),
+            Column::Str(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+/// Encodes JSON records with the schema inferred from the first batch, records that don't match
+/// it are logged and skipped.
+fn encode_json(
+    decoder: &mut Option<(SchemaRef, Decoder)>,
+    pending: &mut Vec<(usize, Value)>,
+    format: &str,
+    conv: &Conversion,
+) -> CtxResult<Option<RecordBatch>, Error> {
+    if pending.is_empty() {
+        return Ok(None);
+    }
+
+    let (schema, decoder) = match decoder {
+        Some((schema, decoder)) => (schema.clone(), decoder),
+        None => {
+            let schema = infer_json_schema_from_iterator(pending.iter().map(|(_, v)| Ok(v)))
+                .map_err(|e| batch_error(format, e))
+                .with_context(|| {
+                    format!(
+                        "Failed to infer a {} schema from the first {} records",
+                        format,
+                        pending.len()
+                    )
+                })?;
+            let schema = Arc::new(schema);
+
+            let (_, decoder) = decoder.insert((schema.clone(), json_decoder(&schema, format)?));
+            (schema, decoder)
+        }
+    };
+
+    let values: Vec<&Value> = pending.iter().map(|(_, v)| v).collect();
+
+    // values are only checked against the schema once the batch is flushed
+    if let Ok(batch) = decoder.serialize(&values).and_then(|_| decoder.flush()) {
+        pending.clear();
+        return Ok(batch);
+    }
+
+    // a record didn't match the schema, so find the ones that do one at a time
+    *decoder = json_decoder(&schema, format)?;
+
+    for (rec_no, value) in pending.iter() {
+        let mut probe = json_decoder(&schema, format)?;
+
+        let checked = probe
+            .serialize(&[value])
+            .and_then(|_| probe.flush())
+            .map_err(|e| Error::new(EK::InvalidData, e.to_string()))
+            .with_context(|| format!("Record: {} doesn't match the {} schema", rec_no, format));
+
+        match checked {
+            Ok(_) => decoder.serialize(&[value]).map_err(|e| batch_error(format, e))?,
+            Err(e) => conv.skip_rejected::<Error>(&e, *rec_no, Rejected::Value(value))?,
+        }
+    }
+
+    pending.clear();
+
+    Ok(decoder.flush().map_err(|e| batch_error(format, e))?)
+}
+
+/// JSON decoder for `schema`, numbers and booleans in string columns are written as their text.
+/// Keys that aren't in the schema are errors so their records are skipped instead of losing them.
+fn json_decoder(schema: &SchemaRef, format: &str) -> Result<Decoder, Error> {
+    ReaderBuilder::new(schema.clone())
+        .with_batch_size(BATCH_SIZE)
+        .with_coerce_primitive(true)
+        .with_strict_mode(true)
+        .build_decoder()
+        .map_err(|e| batch_error(format, e))
+}
+
+/// Buffers rows into record batches of `batch_size` rows and writes them into `sink`, the Arrow
+/// schema is inferred from the first batch: table fields are typed like `parse_numbers` / `types`
+/// type them and JSON objects by their values (nested objects become structs and arrays lists).
+/// Records that don't match the schema are logged and skipped, `format` names the output in
+/// messages.
+pub(crate) fn write_batches(
+    data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+    mut sink: impl BatchSink,
+    options: &Options,
+    types: &[(String, ColumnType)],
+    batch_size: usize,
+    format: &str,
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    match data_stream {
+        WriterStreams::Values { iter: values } | WriterStreams::Ndjson { values } => {
+            let mut decoder = None;
+            let mut pending = Vec::with_capacity(batch_size);
+
+            for (rec_no, rec) in values.enumerate() {
+                let rec_no = rec_no + 1;
+
+                let obj = match rec
+                    .and_then(DataTypes::into_json)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let value = obj;
+
+                if !value.is_object() {
+                    let e = ErrCtx::new(
+                        Error::new(EK::InvalidData, format!("{} rows have to be objects", format)),
+                        format!("Record: {} isn't an object", rec_no).into_bytes(),
+                    );
+                    conv.skip_rejected(&e, rec_no, Rejected::Value(&value))?;
+                    continue;
+                }
+
+                pending.push((rec_no, value));
+
+                if pending.len() == batch_size {
+                    let started = decoder.is_some();
+                    let batch = encode_json(&mut decoder, &mut pending, format, conv)?;
+
+                    if let (false, Some((schema, _))) = (started, &decoder) {
+                        sink.start(schema.clone())?;
+                    }
+                    if let Some(batch) = batch {
+                        sink.write(&batch).context("Failed to write record batch")?;
+                    }
+                }
+            }
+
+            let started = decoder.is_some();
+            let batch = encode_json(&mut decoder, &mut pending, format, conv)?;
+
+            if let (false, Some((schema, _))) = (started, &decoder) {
+                sink.start(schema.clone())?;
+            }
+            if let Some(batch) = batch {
+                sink.write(&batch).context("Failed to write record batch")?;
+            }
+
+            let schema = match decoder {
+                Some((schema, _)) => schema,
+                None => Arc::new(Schema::empty()),
+            };
+            sink.finish(schema).with_context(|| format!("Failed to finish {} output", format))?;
+        }
+
+        WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, options.parse_numbers, types)?;
+
+            let mut first = Vec::with_capacity(batch_size);
+            let mut columns: Option<(SchemaRef, Vec<Column>)> = None;
+            let mut rows = 0usize;
+
+            for (line_no, rec) in iter.enumerate() {
+                let line_no = line_no + 1;
+
+                let record = match into_byte_record(rec)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let cells = match inference
+                    .row(&record)
+                    .with_context(|| format!("Failed to infer the types of record: {}", line_no))
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.skip_rejected::<Error>(&e, line_no, Rejected::Row(&record))?;
+                        continue;
+                    }
+                };
+
+                let Some((schema, cols)) = columns.as_mut() else {
+                    drop(cells);
+                    first.push(record);
+
+                    if first.len() == batch_size {
+                        let (schema, cols) =
+                            columns.insert(table_columns(&headers, &inference, &first));
+                        sink.start(schema.clone())?;
+
+                        let batch = table_batch(schema, cols, &inference, &first, format)?;
+                        sink.write(&batch).context("Failed to write record batch")?;
+                        first.clear();
+                    }
+                    continue;
+                };
+
+                let mismatch = cols
+                    .iter()
+                    .zip(cells.iter().zip(record.iter()))
+                    .zip(&headers)
+                    .find(|((col, (cell, _)), _)| !col.accepts(cell));
+
+                if let Some(((col, (_, raw)), h)) = mismatch {
+                    let e = ErrCtx::new(
+                        Error::new(EK::InvalidData, format!("Field doesn't match its {} column", format)),
+                        format!(
+                            "Record: {} has field: {} = `{}` which doesn't match its {} column type {}",
+                            line_no,
+                            h,
+                            String::from_utf8_lossy(raw),
+                            format,
+                            col.data_type()
+                        )
+                        .into_bytes(),
+                    );
+                    conv.skip_rejected(&e, line_no, Rejected::Row(&record))?;
+                    continue;
+                }
+
+                for (col, (cell, raw)) in cols.iter_mut().zip(cells.iter().zip(record.iter())) {
+                    col.append(cell, raw);
+                }
+                rows += 1;
+
+                if rows == batch_size {
+                    let batch = finish_batch(schema, cols, format)?;
+                    sink.write(&batch).context("Failed to write record batch")?;
+                    rows = 0;
+                }
+            }
+
+            let schema = match columns.as_mut() {
+                Some((schema, cols)) => {
+                    let batch = finish_batch(schema, cols, format)?;
+                    sink.write(&batch).context("Failed to write record batch")?;
+                    schema.clone()
+                }
+                None => {
+                    let (schema, cols) =
+                        columns.insert(table_columns(&headers, &inference, &first));
+                    sink.start(schema.clone())?;
+
+                    let batch = table_batch(schema, cols, &inference, &first, format)?;
+                    if batch.num_rows() > 0 {
+                        sink.write(&batch).context("Failed to write record batch")?;
+                    }
+                    schema.clone()
+                }
+            };
+
+            sink.finish(schema).with_context(|| format!("Failed to finish {} output", format))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Infers the columns of a table from its first batch of rows.
+fn table_columns(
+    headers: &[String],
+    inference: &Inference,
+    rows: &[csv::ByteRecord],
+) -> (SchemaRef, Vec<Column>) {
+    let mut kinds = vec![Kind::Null; headers.len()];
+
+    for row in rows {
+        let Ok(cells) = inference.row(row) else { continue };
+
+        for (kind, cell) in kinds.iter_mut().zip(&cells) {
+            *kind = kind.unify(Kind::of(cell));
+        }
+    }
+
+    let columns: Vec<Column> = kinds.into_iter().map(Column::new).collect();
+    let fields: Vec<Field> =
+        headers.iter().zip(&columns).map(|(h, c)| Field::new(h, c.data_type(), true)).collect();
+
+    (Arc::new(Schema::new(fields)), columns)
+}
+
+/// Builds a record batch from the first batch of rows, they always match the inferred columns.
+fn table_batch(
+    schema: &SchemaRef,
+    columns: &mut [Column],
+    inference: &Inference,
+    rows: &[csv::ByteRecord],
+    format: &str,
+) -> CtxResult<RecordBatch, Error> {
+    for row in rows {
+        let Ok(cells) = inference.row(row) else { continue };
+
+        for (col, (cell, raw)) in columns.iter_mut().zip(cells.iter().zip(row.iter())) {
+            col.append(cell, raw);
+        }
+    }
+
+    finish_batch(schema, columns, format)
+}
+
+fn finish_batch(
+    schema: &SchemaRef,
+    columns: &mut [Column],
+    format: &str,
+) -> CtxResult<RecordBatch, Error> {
+    let arrays = columns.iter_mut().map(Column::finish).collect();
+
+    RecordBatch::try_new(schema.clone(), arrays)
+        .map_err(|e| batch_error(format, e))
+        .context("Failed to build record batch")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Array;
+
+    #[test]
+    fn test_table_kinds() {
+        let headers = ["id", "price", "on", "at", "note"].map(String::from);
+        let inference = Inference::new(&headers, true, &[]).unwrap();
+        let rows = [
+            csv::ByteRecord::from(vec!["1", "2", "2024-05-01", "2024-05-01T00:00:01+01:00", ""]),
+            csv::ByteRecord::from(vec!["2", "2.5", "", "1970-01-01T00:00:00Z", "x"]),
+        ];
+
+        let (schema, mut columns) = table_columns(&headers, &inference, &rows);
+        let types: Vec<String> =
+            schema.fields().iter().map(|f| f.data_type().to_string()).collect();
+        assert_eq!(types, ["Int64", "Float64", "Date32", "Timestamp(µs, \"UTC\")", "Utf8"]);
+
+        let batch = table_batch(&schema, &mut columns, &inference, &rows, "Arrow").unwrap();
+        let at = batch.column(3).as_any().downcast_ref::<arrow_array::TimestampMicrosecondArray>();
+        assert_eq!(at.unwrap().values(), &[1_714_518_001_000_000, 0]);
+
+        let on = batch.column(2).as_any().downcast_ref::<arrow_array::Date32Array>().unwrap();
+        assert_eq!((on.value(0), on.is_null(1)), (19_844, true));
+    }
+}
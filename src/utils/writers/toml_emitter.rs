@@ -0,0 +1,305 @@
+use std::io::{Error, ErrorKind as EK, Write};
+
+/// Borrowed JSON or TOML value, so both record types are written without converting them into
+/// each other first.
+#[derive(Clone, Copy)]
+pub(crate) enum Node<'a> {
+    Json(&'a serde_json::Value),
+    Toml(&'a toml::Value),
+}
+
+impl<'a> Node<'a> {
+    fn is_table(&self) -> bool {
+        matches!(self, Node::Json(serde_json::Value::Object(_)) | Node::Toml(toml::Value::Table(_)))
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(self, Node::Json(serde_json::Value::Null))
+    }
+
+    /// Entries of a table, empty for other values.
+    fn entries(&self) -> Vec<(&'a str, Node<'a>)> {
+        match *self {
+            Node::Json(serde_json::Value::Object(map)) => {
+                map.iter().map(|(k, v)| (k.as_str(), Node::Json(v))).collect()
+            }
+            Node::Toml(toml::Value::Table(map)) => {
+                map.iter().map(|(k, v)| (k.as_str(), Node::Toml(v))).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Elements of an array, `None` for other values.
+    fn elements(&self) -> Option<Vec<Node<'a>>> {
+        match *self {
+            Node::Json(serde_json::Value::Array(arr)) => Some(arr.iter().map(Node::Json).collect()),
+            Node::Toml(toml::Value::Array(arr)) => Some(arr.iter().map(Node::Toml).collect()),
+            _ => None,
+        }
+    }
+
+    /// Whether the value is written as `[[key]]` sections instead of an inline array.
+    fn is_array_of_tables(&self) -> bool {
+        self.elements().is_some_and(|els| !els.is_empty() && els.iter().all(Node::is_table))
+    }
+
+    /// Whether the value is written as a `[key]` section instead of an inline value.
+    fn is_section(&self) -> bool {
+        self.is_table() || self.is_array_of_tables()
+    }
+}
+
+/// Checks that a value can be written as TOML before anything is written, so invalid records
+/// never leave half a table in the output.
+pub(crate) fn check(node: Node, in_array: bool) -> Result<(), Error> {
+    match node {
+        Node::Json(serde_json::Value::Null) if in_array => {
+            Err(Error::new(EK::InvalidData, "TOML has no null values, found null in an array"))
+        }
+        Node::Json(serde_json::Value::Number(n))
+            if n.as_i64().is_none() && n.as_u64().is_some() =>
+        {
+            Err(Error::new(
+                EK::InvalidData,
+                format!("Integer: {} is out of range for TOML (64-bit signed integers)", n),
+            ))
+        }
+        _ => {
+            if let Some(els) = node.elements() {
+                els.into_iter().try_for_each(|el| check(el, true))
+            } else {
+                node.entries().into_iter().try_for_each(|(_, v)| check(v, false))
+            }
+        }
+    }
+}
+
+/// Writes a key, quoting it unless it's a bare key.
+fn write_key<W: Write>(out: &mut W, key: &str) -> Result<(), Error> {
+    if !key.is_empty() && key.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-') {
+        out.write_all(key.as_bytes())
+    } else {
+        write_str(out, key)
+    }
+}
+
+/// Writes a basic string with TOML escapes.
+fn write_str<W: Write>(out: &mut W, s: &str) -> Result<(), Error> {
+    let mut buf = Vec::with_capacity(s.len() + 2);
+    buf.push(b'"');
+
+    for c in s.chars() {
+        match c {
+            '"' => buf.extend_from_slice(b"\\\""),
+            '\\' => buf.extend_from_slice(b"\\\\"),
+            '\n' => buf.extend_from_slice(b"\\n"),
+            '\r' => buf.extend_from_slice(b"\\r"),
+            '\t' => buf.extend_from_slice(b"\\t"),
+            c if c.is_control() => buf.extend_from_slice(format!("\\u{:04X}", c as u32).as_bytes()),
+            c => buf.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+
+    buf.push(b'"');
+    out.write_all(&buf)
+}
+
+fn write_float<W: Write>(out: &mut W, f: f64) -> Result<(), Error> {
+    if f.is_nan() {
+        out.write_all(b"nan")
+    } else if f.is_infinite() {
+        out.write_all(if f > 0.0 { b"inf" } else { b"-inf" })
+    } else {
+        // `Debug` keeps the fraction of whole floats (`1.0` instead of `1`)
+        write!(out, "{:?}", f)
+    }
+}
+
+/// Writes a value inline (e.g. `1`, `"a"`, `[1, 2]` or `{ a = 1 }`).
+fn write_inline<W: Write>(out: &mut W, node: Node) -> Result<(), Error> {
+    match node {
+        Node::Json(serde_json::Value::String(s)) | Node::Toml(toml::Value::String(s)) => {
+            write_str(out, s)
+        }
+        Node::Json(serde_json::Value::Bool(b)) | Node::Toml(toml::Value::Boolean(b)) => {
+            write!(out, "{}", b)
+        }
+        Node::Json(serde_json::Value::Number(n)) => match n.as_i64() {
+            Some(i) => write!(out, "{}", i),
+            None => write_float(out, n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Node::Toml(toml::Value::Integer(i)) => write!(out, "{}", i),
+        Node::Toml(toml::Value::Float(f)) => write_float(out, *f),
+        Node::Toml(toml::Value::Datetime(d)) => write!(out, "{}", d),
+        // nulls in arrays are rejected by `check` and nulls in tables are left out
+        Node::Json(serde_json::Value::Null) => Ok(()),
+        Node::Json(serde_json::Value::Array(_)) | Node::Toml(toml::Value::Array(_)) => {
+            out.write_all(b"[")?;
+
+            for (idx, el) in node.elements().unwrap_or_default().into_iter().enumerate() {
+                if idx > 0 {
+                    out.write_all(b", ")?;
+                }
+                write_inline(out, el)?;
+            }
+
+            out.write_all(b"]")
+        }
+        Node::Json(serde_json::Value::Object(_)) | Node::Toml(toml::Value::Table(_)) => {
+            let entries: Vec<_> =
+                node.entries().into_iter().filter(|(_, v)| !v.is_null()).collect();
+
+            if entries.is_empty() {
+                return out.write_all(b"{}");
+            }
+
+            out.write_all(b"{ ")?;
+
+            for (idx, (k, v)) in entries.into_iter().enumerate() {
+                if idx > 0 {
+                    out.write_all(b", ")?;
+                }
+                write_key(out, k)?;
+                out.write_all(b" = ")?;
+                write_inline(out, v)?;
+            }
+
+            out.write_all(b" }")
+        }
+    }
+}
+
+/// Writes tables straight into `out` without building the document first, `path` holds the
+/// quoted keys of the section that is being written.
+pub(crate) struct TomlEmitter<'w, W: Write> {
+    out: &'w mut W,
+    path: Vec<Vec<u8>>,
+    empty: bool,
+}
+
+impl<'w, W: Write> TomlEmitter<'w, W> {
+    /// Creates an emitter whose tables are written into the section at `path` (e.g. `Array` for
+    /// the elements of `[[Array]]`), an empty path writes top-level tables.
+    pub(crate) fn new(out: &'w mut W, path: &[&str]) -> Result<Self, Error> {
+        let mut emitter = TomlEmitter { out, path: Vec::new(), empty: true };
+
+        for key in path {
+            let mut quoted = Vec::new();
+            write_key(&mut quoted, key)?;
+            emitter.path.push(quoted);
+        }
+
+        Ok(emitter)
+    }
+
+    /// Writes an empty line unless nothing was written yet.
+    pub(crate) fn separate(&mut self) -> Result<(), Error> {
+        if !self.empty {
+            self.out.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the body of a table (its `key = value` pairs followed by its sub-tables and arrays
+    /// of tables).
+    pub(crate) fn write_table(&mut self, table: Node) -> Result<(), Error> {
+        let entries = table.entries();
+
+        for (k, v) in entries.iter().filter(|(_, v)| !v.is_null() && !v.is_section()) {
+            write_key(self.out, k)?;
+            self.out.write_all(b" = ")?;
+            write_inline(self.out, *v)?;
+            self.out.write_all(b"\n")?;
+            self.empty = false;
+        }
+
+        for (k, v) in entries.iter().filter(|(_, v)| v.is_section()) {
+            let mut key = Vec::new();
+            write_key(&mut key, k)?;
+            self.path.push(key);
+
+            if v.is_table() {
+                // tables holding only sections are defined by their sub-tables' headers
+                let entries = v.entries();
+                let implicit = !entries.is_empty() && entries.iter().all(|(_, v)| v.is_section());
+
+                if !implicit {
+                    self.write_header(false)?;
+                }
+                self.write_table(*v)?;
+            } else {
+                for el in v.elements().unwrap_or_default() {
+                    self.write_header(true)?;
+                    self.write_table(el)?;
+                }
+            }
+
+            self.path.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Writes the `[a.b]` or `[[a.b]]` header of the current section, preceded by an empty line.
+    pub(crate) fn write_header(&mut self, array: bool) -> Result<(), Error> {
+        self.separate()?;
+        self.out.write_all(if array { b"[[" } else { b"[" })?;
+
+        for (idx, key) in self.path.iter().enumerate() {
+            if idx > 0 {
+                self.out.write_all(b".")?;
+            }
+            self.out.write_all(key)?;
+        }
+
+        self.out.write_all(if array { b"]]\n" } else { b"]\n" })?;
+        self.empty = false;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emit(value: serde_json::Value) -> String {
+        let mut out = Vec::new();
+        TomlEmitter::new(&mut out, &[]).unwrap().write_table(Node::Json(&value)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_emit_nested_tables() {
+        let value = serde_json::json!({
+            "name": "fiux",
+            "a key": 1.0,
+            "skip": null,
+            "tags": ["a", {"b": 2}],
+            "owner": {"info": {"age": 3}},
+            "rows": [{"x": 1, "sub": {"y": "\"q\"\n"}}, {"x": 2}],
+        });
+
+        let toml = emit(value.clone());
+
+        assert_eq!(
+            toml,
+            "name = \"fiux\"\n\"a key\" = 1.0\ntags = [\"a\", { b = 2 }]\n\n[owner.info]\nage = 3\n\n[[rows]]\nx = 1\n\n[rows.sub]\ny = \"\\\"q\\\"\\n\"\n\n[[rows]]\nx = 2\n"
+        );
+
+        // the output parses back into the same value (without nulls)
+        let parsed: toml::Value = toml::from_str(&toml).unwrap();
+        let mut expected = value;
+        expected.as_object_mut().unwrap().remove("skip");
+        assert_eq!(crate::utils::toml_to_json(parsed), expected);
+    }
+
+    #[test]
+    fn test_check_rejects_unsupported_values() {
+        assert!(check(Node::Json(&serde_json::json!({"a": [1, null]})), false).is_err());
+        assert!(check(Node::Json(&serde_json::json!({"a": u64::MAX})), false).is_err());
+        assert!(check(Node::Json(&serde_json::json!({"a": null})), false).is_ok());
+    }
+}
@@ -1,62 +1,88 @@
 use std::io::{BufWriter, Error, Write};
 
-use resext::{CtxResult, ErrCtx, ResExt};
-use toml::{Value, map::Map};
+use resext::{CtxResult, ResExt};
+use toml::Value;
 
-use crate::utils::{DataTypes, WriterStreams, escape, into_byte_record};
+use crate::utils::{
+    Cell, ColumnType, Conversion, DataTypes, Inference, Rejected, TABLE_ROW, WriterStreams, escape,
+    into_byte_record,
+    toml_emitter::{self, Node, TomlEmitter},
+};
 
 #[inline]
-pub(crate) fn toml_writer(
+pub(crate) fn toml_writer<W: Write>(
     data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
-    file: std::fs::File,
+    output: W,
     parse_numbers: bool,
+    types: &[(String, ColumnType)],
+    conv: &Conversion,
 ) -> CtxResult<(), Error> {
-    let mut buffered_writer = BufWriter::new(file);
+    let mut buffered_writer = BufWriter::new(output);
 
     match data_stream {
         WriterStreams::Values { iter } => {
-            for item in iter {
-                let obj = Value::try_from(
-                    item.context("Failed to re-serialize object for writing").unwrap_or_else(
-                        |e: ErrCtx<Error>| {
-                            crate::utils::log_err(&e)
-                                .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-                            DataTypes::Toml(Value::Table(Map::new()))
-                        },
-                    ),
-                )
-                .context("Failed to re-serialize object for writing")
-                .context("Invalid TOML values in input file")
-                .unwrap_or_else(|e: ErrCtx<toml::ser::Error>| {
-                    crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-
-                    Value::Table(Map::new())
-                });
-
-                if let Value::Array(_) = obj {
-                    let mut map = Map::with_capacity(1);
-                    map.insert("Array".to_string(), obj);
-
-                    buffered_writer.write_all(toml::to_string_pretty(&Value::Table(map))
-                        .map_err(|e| Error::new(std::io::ErrorKind::OutOfMemory, format!("Failed to allocate String of valid TOML values for writing\nCaused by: {}", e)))
-                        .context("Failed to serialize valid TOML table")
-                        .context("This error was caused by an internal error")?
-                        .as_bytes()
-                    )
-                        .context("Failed to write TOML table into output file")?;
+            let mut emitter = TomlEmitter::new(&mut buffered_writer, &[])
+                .context("Failed to create TOML emitter")?;
+
+            for (doc_no, item) in iter.enumerate() {
+                let item = match item.context("Failed to re-serialize object for writing") {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let node = match node_of(&item) {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                // documents have to be tables so arrays are written under an `Array` key
+                let wrapped;
+                let node = match node {
+                    Node::Json(v @ serde_json::Value::Array(_)) => {
+                        wrapped = serde_json::json!({ "Array": v });
+                        Node::Json(&wrapped)
+                    }
+                    Node::Toml(v @ Value::Array(_)) => {
+                        wrapped =
+                            serde_json::json!({ "Array": crate::utils::toml_to_json(v.clone()) });
+                        Node::Json(&wrapped)
+                    }
+                    node => node,
+                };
+
+                let checked = if matches!(
+                    node,
+                    Node::Json(serde_json::Value::Object(_)) | Node::Toml(Value::Table(_))
+                ) {
+                    toml_emitter::check(node, false)
                 } else {
-                    buffered_writer.write_all(toml::to_string_pretty(&obj)
-                        .map_err(|e| Error::new(std::io::ErrorKind::OutOfMemory, format!("Failed to allocate String of valid TOML values for writing\nCaused by: {}", e)))
-                        .context("Failed to serialize valid TOML table")
-                        .context("This error was caused by an internal error")?
-                        .as_bytes()
-                    )
-                        .context("Failed to write TOML table into output file")?;
+                    Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "TOML documents have to be tables",
+                    ))
+                };
+
+                if let Err(e) = checked
+                    .context("Failed to re-serialize object for writing")
+                    .context("Invalid TOML values in input file")
+                {
+                    conv.skip_rejected::<Error>(&e, doc_no + 1, Rejected::Record(&item))?;
+                    continue;
                 }
+
+                emitter.separate().context("Failed to write a newline into output file")?;
+                emitter.write_table(node).context("Failed to write TOML table into output file")?;
             }
             buffered_writer.flush().context("Failed to flush final bytes into output file")?;
         }
         WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, parse_numbers, types)?;
             let mut esc_buf: Vec<u8> = Vec::with_capacity(10);
 
             let headers: Vec<String> = headers
@@ -83,6 +109,28 @@ pub(crate) fn toml_writer(
 
             for (line_no, rec) in iter.enumerate() {
                 let line_no = line_no + 1;
+
+                let record = match into_byte_record(rec)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let cells = match inference
+                    .row(&record)
+                    .with_context(|| format!("Failed to infer the types of record: {}", line_no))
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.skip_rejected::<Error>(&e, line_no, Rejected::Row(&record))?;
+                        continue;
+                    }
+                };
+
                 if !first_row {
                     buffered_writer.write_all(b"\n[[Rows]]\n").with_context(|| {
                         format!("Failed to write array key for row: {}", line_no)
@@ -94,35 +142,29 @@ pub(crate) fn toml_writer(
                     first_row = false;
                 }
 
-                let record = into_byte_record(rec)
-                    .context("Failed to re-serialize object for writing")
-                    .unwrap_or_else(|e: ErrCtx<Error>| {
-                        crate::utils::log_err(&e)
-                            .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-
-                        csv::ByteRecord::with_capacity(0, 0)
-                    });
-
-                for (idx, (h, v)) in headers.iter().zip(record.iter()).enumerate() {
+                for (idx, (h, v)) in headers.iter().zip(cells.iter()).enumerate() {
                     let idx = idx + 1;
 
                     esc_buf.clear();
 
-                    if matches!(v, b"true" | b"false")
-                        || (parse_numbers
-                            && v.first()
-                                .is_some_and(|b| *b == b'-' || *b == b'+' || b.is_ascii_digit())
-                            && v.last().is_some_and(|b| b.is_ascii_digit())
-                            && std::str::from_utf8(v).unwrap_or("").parse::<f64>().is_ok())
-                    {
-                        esc_buf.extend_from_slice(v);
-                    } else {
-                        esc_buf.push(b'"');
-                        v.iter().for_each(|byte| {
-                            escape(*byte, &mut esc_buf);
-                        });
-                        esc_buf.push(b'"');
+                    match v {
+                        // TOML has no null values so null fields are left out
+                        Cell::Null => continue,
+                        // TOML integers are 64-bit so bigger ones are kept as strings
+                        Cell::BigInt(s) => write!(esc_buf, "\"{}\"", s),
+                        Cell::Datetime(dt) => write!(esc_buf, "{}", dt),
+                        Cell::Str(s) => {
+                            esc_buf.push(b'"');
+                            s.iter().for_each(|byte| escape(*byte, &mut esc_buf));
+                            esc_buf.push(b'"');
+                            Ok(())
+                        }
+                        v => {
+                            v.write_json(&mut esc_buf);
+                            Ok(())
+                        }
                     }
+                    .context("Failed to write a value into buffer")?;
                     buffered_writer.write_all(h.as_bytes()).with_context(|| {
                         format!(
                             "Failed to write key for key-value pair: {} in record: {}",
@@ -154,65 +196,77 @@ pub(crate) fn toml_writer(
         }
 
         WriterStreams::Ndjson { values } => {
-            let mut first = true;
+            let mut emitter = TomlEmitter::new(&mut buffered_writer, &["Array"])
+                .context("Failed to create TOML emitter")?;
+
             for (rec_no, rec) in values.enumerate() {
                 let rec_no = rec_no + 1;
 
-                let obj = Value::try_from(
-                    rec.context("Failed to re-serialize object for writing").unwrap_or_else(
-                        |e: ErrCtx<Error>| {
-                            crate::utils::log_err(&e)
-                                .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-
-                            DataTypes::Toml(Value::Table(Map::new()))
-                        },
-                    ),
-                )
-                .context("Failed to re-serialize object for writing")
-                .context("Invalid TOML values in input file")
-                .unwrap_or_else(|e: ErrCtx<toml::ser::Error>| {
-                    crate::utils::log_err(&e).unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-
-                    Value::Table(Map::new())
-                });
-
-                if first {
-                    buffered_writer
-                        .write_all(b"[[Array]]\n")
-                        .with_context(|| format!("Failed to write array key: {}", rec_no))?;
+                let rec = match rec.context("Failed to re-serialize object for writing") {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
 
-                    first = false;
-                } else {
-                    buffered_writer
-                        .write_all(b"\n[[Array]]\n")
-                        .with_context(|| format!("Failed to write array key: {}", rec_no))?;
-                }
+                let node = match node_of(&rec) {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
 
-                if let Value::Array(_) = obj {
-                    let mut map = Map::with_capacity(1);
-                    map.insert("Array".to_string(), obj);
-
-                    buffered_writer.write_all(toml::to_string_pretty(&Value::Table(map))
-                        .map_err(|e| Error::new(std::io::ErrorKind::OutOfMemory, format!("Failed to allocate String of valid TOML values for writing\nCaused by: {}", e)))
-                        .context("Failed to serialize valid TOML table")
-                        .context("This error was caused by an internal error")?
-                        .as_bytes()
-                    )
-                        .context("Failed to write TOML table into output file")?;
-                } else {
-                    buffered_writer.write_all(toml::to_string_pretty(&obj)
-                        .map_err(|e| Error::new(std::io::ErrorKind::OutOfMemory, format!("Failed to allocate String of valid TOML values for writing\nCaused by: {}", e)))
-                        .context("Failed to serialize valid TOML table")
-                        .context("This error was caused by an internal error")?
-                        .as_bytes()
-                    )
-                        .context("Failed to write TOML table into output file")?;
+                // every record is an element of the `Array` array of tables, records that aren't
+                // tables are written under an `Array` key in their element
+                let wrapped;
+                let node = match node {
+                    node @ (Node::Json(serde_json::Value::Object(_))
+                    | Node::Toml(Value::Table(_))) => node,
+                    Node::Json(v) => {
+                        wrapped = serde_json::json!({ "Array": v });
+                        Node::Json(&wrapped)
+                    }
+                    Node::Toml(v) => {
+                        wrapped =
+                            serde_json::json!({ "Array": crate::utils::toml_to_json(v.clone()) });
+                        Node::Json(&wrapped)
+                    }
+                };
+
+                if let Err(e) = toml_emitter::check(node, false)
+                    .with_context(|| {
+                        format!("Failed to re-serialize record: {} for writing", rec_no)
+                    })
+                    .context("Invalid TOML values in input file")
+                {
+                    conv.skip_rejected::<Error>(&e, rec_no, Rejected::Record(&rec))?;
+                    continue;
                 }
 
-                buffered_writer.flush().context("Failed to flush final bytes into output file")?;
+                emitter
+                    .write_header(true)
+                    .with_context(|| format!("Failed to write array key: {}", rec_no))?;
+
+                emitter.write_table(node).with_context(|| {
+                    format!("Failed to write record: {} into output file", rec_no)
+                })?;
             }
+
+            buffered_writer.flush().context("Failed to flush final bytes into output file")?;
         }
     }
 
     Ok(())
 }
+
+/// Borrows a JSON / TOML record for the emitter.
+fn node_of(data: &DataTypes) -> CtxResult<Node<'_>, Error> {
+    match data {
+        DataTypes::Json(j) => Ok(Node::Json(j)),
+        DataTypes::Toml(t) => Ok(Node::Toml(t)),
+        DataTypes::Csv(_) => Err(Error::new(std::io::ErrorKind::InvalidInput, TABLE_ROW))
+            .context("Failed to re-serialize object for writing"),
+    }
+}
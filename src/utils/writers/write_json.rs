@@ -1,27 +1,32 @@
 use std::io::{BufWriter, Error, Write};
 
-use resext::{CtxResult, ErrCtx, ResExt};
+use resext::{CtxResult, ResExt};
 
-use crate::utils::{DataTypes, WriterStreams, into_byte_record};
+use crate::utils::{
+    ColumnType, Conversion, DataTypes, Inference, Rejected, WriterStreams, into_byte_record,
+};
 
 #[inline]
-pub(crate) fn write_json(
+pub(crate) fn write_json<W: Write>(
     data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
-    file: std::fs::File,
+    output: W,
     parse_numbers: bool,
+    types: &[(String, ColumnType)],
+    conv: &Conversion,
 ) -> CtxResult<(), Error> {
-    let mut buffered_writer = BufWriter::new(&file);
+    let mut buffered_writer = BufWriter::new(output);
 
     match data_stream {
         WriterStreams::Values { iter } => {
             for obj in iter {
-                let valid_obj = obj
-                    .context("Failed to re-serialize object for writing")
-                    .unwrap_or_else(|e: ErrCtx<Error>| {
-                        crate::utils::log_err(&e)
-                            .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-                        DataTypes::Json(serde_json::json!({}))
-                    });
+                let valid_obj = match obj.context("Failed to re-serialize object for writing") {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        // invalid objects are skipped instead of writing a placeholder
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
 
                 serde_json::to_writer_pretty(&mut buffered_writer, &valid_obj)
                     .map_err(|_| Error::other("Failed to write into file"))
@@ -35,6 +40,7 @@ pub(crate) fn write_json(
         }
 
         WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, parse_numbers, types)?;
             // buffer for escapijg values which will get cleared after each value and
             // reused instead of allocating a new `Vec<u8>` for every value
             let mut esc_buf: Vec<u8> = Vec::with_capacity(10);
@@ -58,6 +64,28 @@ pub(crate) fn write_json(
 
             for (line_no, rec) in iter.enumerate() {
                 let line = line_no + 1;
+
+                let record = match into_byte_record(rec)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let cells = match inference
+                    .row(&record)
+                    .with_context(|| format!("Failed to infer the types of record: {}", line))
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.skip_rejected::<Error>(&e, line, Rejected::Row(&record))?;
+                        continue;
+                    }
+                };
+
                 if first_obj {
                     buffered_writer.write_all(b"  {\n").with_context(|| {
                         format!(
@@ -77,33 +105,10 @@ pub(crate) fn write_json(
 
                 let mut first_value = true;
 
-                let record = into_byte_record(rec)
-                    .context("Failed to re-serialize object for writing")
-                    .unwrap_or_else(|e: ErrCtx<Error>| {
-                        crate::utils::log_err(&e)
-                            .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-                        csv::ByteRecord::with_capacity(0, 0)
-                    });
-
-                for (idx, (h, v)) in headers.iter().zip(record.iter()).enumerate() {
+                for (idx, (h, v)) in headers.iter().zip(cells.iter()).enumerate() {
                     esc_buf.clear();
                     let idx = idx + 1;
-                    if matches!(v, b"true" | b"false" | b"null")
-                        || (parse_numbers
-                            && v.first()
-                                .is_some_and(|b| *b == b'-' || *b == b'+' || b.is_ascii_digit())
-                            && v.last().is_some_and(|b| b.is_ascii_digit())
-                            && std::str::from_utf8(v).unwrap_or("").parse::<f64>().is_ok())
-                    {
-                        esc_buf.extend_from_slice(v);
-                    } else {
-                        esc_buf.push(b'"');
-                        v.iter().for_each(|byte| {
-                            crate::utils::escape(*byte, &mut esc_buf);
-                        });
-
-                        esc_buf.push(b'"');
-                    }
+                    v.write_json(&mut esc_buf);
                     if first_value {
                         buffered_writer
                             .write(b"    \"")
@@ -157,38 +162,41 @@ pub(crate) fn write_json(
                 .context("Failed to write opening bracket into output file")?;
 
             let mut first = true;
+            let mut pretty: Vec<u8> = Vec::new();
 
             for (idx, obj) in values.enumerate() {
                 let idx = idx + 1;
 
-                let obj = obj.context("Failed to re-serialize object for writing").unwrap_or_else(
-                    |e: ErrCtx<Error>| {
-                        crate::utils::log_err(&e)
-                            .unwrap_or_else(|err| eprintln!("{}\n{}", err, &e));
-                        DataTypes::Json(serde_json::json!({}))
-                    },
-                );
-
-                if first {
-                    serde_json::to_writer_pretty(&mut buffered_writer, &obj)
-                        .map_err(|_| Error::other("Failed to write into file"))
-                        .with_context(|| {
-                            format!("Failed to write record: {} into output JSON file", idx)
-                        })
-                        .context("Error might be caused by invalid NDJSON values in input file")?;
-
-                    first = false;
-                } else {
-                    buffered_writer.write_all(b",\n").with_context(|| {
-                        format!("Failed to write comma after record: {} into output file", idx)
-                    })?;
+                let obj = match obj.context("Failed to re-serialize object for writing") {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                // records are indented to sit inside the array
+                pretty.clear();
+                serde_json::to_writer_pretty(&mut pretty, &obj)
+                    .map_err(|_| Error::other("Failed to write into buffer"))
+                    .with_context(|| format!("Failed to serialize record: {}", idx))
+                    .context("Error might be caused by invalid values in input file")?;
+
+                buffered_writer.write_all(if first { b"  " } else { b",\n  " }).with_context(
+                    || format!("Failed to write separator before record: {} into output file", idx),
+                )?;
+                first = false;
+
+                for (line_no, line) in pretty.split(|b| *b == b'\n').enumerate() {
+                    if line_no > 0 {
+                        buffered_writer.write_all(b"\n  ").with_context(|| {
+                            format!("Failed to write record: {} into output file", idx)
+                        })?;
+                    }
 
-                    serde_json::to_writer_pretty(&mut buffered_writer, &obj)
-                        .map_err(|_| Error::other("Failed to write into file"))
-                        .with_context(|| {
-                            format!("Failed to write record: {} into output JSON file", idx)
-                        })
-                        .context("Error might be caused by invalid NDJSON values in input file")?;
+                    buffered_writer.write_all(line).with_context(|| {
+                        format!("Failed to write record: {} into output file", idx)
+                    })?;
                 }
             }
 
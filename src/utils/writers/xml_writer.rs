@@ -0,0 +1,278 @@
+use std::io::{BufWriter, Error, ErrorKind as EK, Write};
+
+use resext::{CtxResult, ResExt};
+use serde_json::Value;
+
+use crate::utils::{
+    Conversion, DataTypes, Rejected, WriterStreams, into_byte_record, xml_reader::is_restricted,
+};
+
+/// Turns a key into a valid element / attribute name, invalid characters are replaced with `_`.
+/// Colons are replaced too since the namespace declarations of prefixes aren't kept.
+fn xml_name(key: &str) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') { c } else { '_' })
+        .collect();
+
+    if !name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+
+    name
+}
+
+/// Escapes `text` for element content, or attribute values if `attr` is set. Control characters
+/// that XML doesn't allow fail the text.
+fn write_escaped<W: Write>(out: &mut W, text: &str, attr: bool) -> Result<(), Error> {
+    let mut start = 0;
+
+    for (idx, b) in text.bytes().enumerate() {
+        let esc: &[u8] = match b {
+            b'&' => b"&amp;",
+            b'<' => b"&lt;",
+            b'>' => b"&gt;",
+            b'"' if attr => b"&quot;",
+            b if is_restricted(b as char) => {
+                return Err(Error::new(
+                    EK::InvalidData,
+                    format!("Control character U+{:04X} isn't allowed in XML", b),
+                ));
+            }
+            _ => continue,
+        };
+
+        out.write_all(&text.as_bytes()[start..idx])?;
+        out.write_all(esc)?;
+        start = idx + 1;
+    }
+
+    out.write_all(&text.as_bytes()[start..])
+}
+
+/// Text of a scalar, nested values in attributes are written as JSON.
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn indent<W: Write>(out: &mut W, depth: usize) -> Result<(), Error> {
+    for _ in 0..depth {
+        out.write_all(b"  ")?;
+    }
+
+    Ok(())
+}
+
+/// Writes `value` as a `name` element, `@key` entries become attributes, `#text` becomes the
+/// element's text and arrays become repeated elements.
+fn write_element<W: Write>(
+    out: &mut W,
+    name: &str,
+    value: &Value,
+    depth: usize,
+) -> Result<(), Error> {
+    if let Value::Array(arr) = value {
+        return arr.iter().try_for_each(|el| write_element(out, name, el, depth));
+    }
+
+    indent(out, depth)?;
+    write!(out, "<{}", name)?;
+
+    let Value::Object(map) = value else {
+        return match value {
+            Value::Null => out.write_all(b"/>\n"),
+            scalar => {
+                out.write_all(b">")?;
+                write_escaped(out, &scalar_text(scalar), false)?;
+                writeln!(out, "</{}>", name)
+            }
+        };
+    };
+
+    let mut attrs: Vec<(String, &str)> = Vec::new();
+
+    for (k, v) in map.iter().filter(|(k, _)| k.starts_with('@')) {
+        let attr = xml_name(&k[1..]);
+
+        // attributes have to be unique, but different keys can have the same name
+        if let Some((_, other)) = attrs.iter().find(|(a, _)| *a == attr) {
+            return Err(Error::new(
+                EK::InvalidData,
+                format!("Keys: {} and {} are both written as the attribute: {}", other, k, attr),
+            ));
+        }
+
+        write!(out, " {}=\"", attr)?;
+        write_escaped(out, &scalar_text(v), true)?;
+        out.write_all(b"\"")?;
+        attrs.push((attr, k));
+    }
+
+    let text = map.get("#text").map(scalar_text).unwrap_or_default();
+    let mut children = map.iter().filter(|(k, _)| !k.starts_with('@') && *k != "#text").peekable();
+
+    if children.peek().is_none() {
+        if text.is_empty() {
+            return out.write_all(b"/>\n");
+        }
+
+        out.write_all(b">")?;
+        write_escaped(out, &text, false)?;
+        return writeln!(out, "</{}>", name);
+    }
+
+    out.write_all(b">\n")?;
+
+    if !text.is_empty() {
+        indent(out, depth + 1)?;
+        write_escaped(out, &text, false)?;
+        out.write_all(b"\n")?;
+    }
+
+    for (k, v) in children {
+        write_element(out, &xml_name(k), v, depth + 1)?;
+    }
+
+    indent(out, depth)?;
+    writeln!(out, "</{}>", name)
+}
+
+/// Writes every record as a `row` element inside a single `root` element, records are written into
+/// `buf` first so records that can't be written as XML are skipped without partial output.
+#[inline]
+pub(crate) fn xml_writer<W: Write>(
+    data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+    output: W,
+    root: &str,
+    row: &str,
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let mut buffered_writer = BufWriter::new(output);
+    let root = xml_name(root);
+    let row = xml_name(row);
+    let mut buf = Vec::new();
+
+    write!(buffered_writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<{}>\n", root)
+        .context("Failed to write XML declaration into output file")?;
+
+    match data_stream {
+        WriterStreams::Values { iter } => {
+            for (idx, obj) in iter.enumerate() {
+                let obj = match obj
+                    .and_then(DataTypes::into_json)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                // arrays are split into one row per element
+                buf.clear();
+                if let Err(e) = write_element(&mut buf, &row, &obj, 1)
+                    .with_context(|| format!("Failed to write value: {} as XML", idx + 1))
+                {
+                    conv.skip_rejected::<Error>(&e, idx + 1, Rejected::Value(&obj))?;
+                    continue;
+                }
+
+                buffered_writer.write_all(&buf).with_context(|| {
+                    format!("Failed to write value: {} into output file", idx + 1)
+                })?;
+            }
+        }
+
+        WriterStreams::Table { headers, iter } => {
+            let headers: Vec<String> = headers.iter().map(|h| xml_name(h)).collect();
+
+            for (line_no, rec) in iter.enumerate() {
+                let line_no = line_no + 1;
+
+                let record = match into_byte_record(rec)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let write_row = |out: &mut Vec<u8>| -> Result<(), Error> {
+                    writeln!(out, "  <{}>", row)?;
+
+                    for (h, v) in headers.iter().zip(record.iter()) {
+                        write!(out, "    <{}>", h)?;
+                        write_escaped(out, &String::from_utf8_lossy(v), false)?;
+                        writeln!(out, "</{}>", h)?;
+                    }
+
+                    writeln!(out, "  </{}>", row)
+                };
+
+                buf.clear();
+                if let Err(e) = write_row(&mut buf)
+                    .with_context(|| format!("Failed to write record: {} as XML", line_no))
+                {
+                    conv.skip_rejected::<Error>(&e, line_no, Rejected::Row(&record))?;
+                    continue;
+                }
+
+                buffered_writer.write_all(&buf).with_context(|| {
+                    format!("Failed to write record: {} into output file", line_no)
+                })?;
+            }
+        }
+
+        WriterStreams::Ndjson { values } => {
+            for (rec_no, rec) in values.enumerate() {
+                let obj = match rec
+                    .and_then(DataTypes::into_json)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                buf.clear();
+                if let Err(e) = write_element(&mut buf, &row, &obj, 1)
+                    .with_context(|| format!("Failed to write record: {} as XML", rec_no + 1))
+                {
+                    conv.skip_rejected::<Error>(&e, rec_no + 1, Rejected::Value(&obj))?;
+                    continue;
+                }
+
+                buffered_writer.write_all(&buf).with_context(|| {
+                    format!("Failed to write record: {} into output file", rec_no + 1)
+                })?;
+            }
+        }
+    }
+
+    writeln!(buffered_writer, "</{}>", root).context("Failed to write closing root element")?;
+
+    buffered_writer.flush().context("Failed to flush final bytes into output file")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_name() {
+        assert_eq!(xml_name("first name"), "first_name");
+        assert_eq!(xml_name("1st"), "_1st");
+        assert_eq!(xml_name("dc:title"), "dc_title");
+    }
+}
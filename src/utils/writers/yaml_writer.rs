@@ -0,0 +1,158 @@
+use std::io::{BufWriter, Error, Write};
+
+use resext::{CtxResult, ResExt};
+use serde_norway::{Mapping, Value};
+
+use crate::utils::{
+    Cell, ColumnType, Conversion, DataTypes, Inference, Rejected, WriterStreams, into_byte_record,
+};
+
+#[inline]
+pub(crate) fn yaml_writer<W: Write>(
+    data_stream: WriterStreams<impl Iterator<Item = CtxResult<DataTypes, Error>>>,
+    output: W,
+    parse_numbers: bool,
+    types: &[(String, ColumnType)],
+    conv: &Conversion,
+) -> CtxResult<(), Error> {
+    let mut buffered_writer = BufWriter::new(output);
+
+    match data_stream {
+        WriterStreams::Values { iter } => {
+            // every value is written as its own document in a multi-document stream
+            let mut first = true;
+
+            for (doc_no, item) in iter.enumerate() {
+                let doc_no = doc_no + 1;
+
+                let obj = match item
+                    .and_then(DataTypes::into_json)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                if !first {
+                    buffered_writer.write_all(b"---\n").with_context(|| {
+                        format!("Failed to write document separator before document: {}", doc_no)
+                    })?;
+                }
+                first = false;
+
+                // values are converted to JSON since TOML datetimes would be written as serde's
+                // private datetime wrapper
+                serde_norway::to_writer(&mut buffered_writer, &obj)
+                    .map_err(|e| Error::other(format!("Failed to write into file: {}", e)))
+                    .with_context(|| {
+                        format!("Failed to write document: {} into output file", doc_no)
+                    })?;
+            }
+        }
+
+        WriterStreams::Table { headers, iter } => {
+            let inference = Inference::new(&headers, parse_numbers, types)?;
+            let headers: Vec<Value> = headers.into_iter().map(Value::String).collect();
+
+            let mut empty = true;
+
+            for (line_no, rec) in iter.enumerate() {
+                let line_no = line_no + 1;
+
+                let record = match into_byte_record(rec)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                let cells = match inference
+                    .row(&record)
+                    .with_context(|| format!("Failed to infer the types of record: {}", line_no))
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.skip_rejected::<Error>(&e, line_no, Rejected::Row(&record))?;
+                        continue;
+                    }
+                };
+
+                let mut row = Mapping::with_capacity(headers.len());
+
+                for (h, v) in headers.iter().zip(cells) {
+                    let value = match v {
+                        Cell::Null => Value::Null,
+                        Cell::Bool(b) => Value::Bool(b),
+                        Cell::Int(i) => Value::Number(i.into()),
+                        Cell::BigInt(s) => s
+                            .parse::<u64>()
+                            .map_or(Value::String(s.to_string()), |u| Value::Number(u.into())),
+                        Cell::Float(f) => Value::Number(f.into()),
+                        Cell::Datetime(dt) => Value::String(dt.to_string()),
+                        Cell::Str(s) => Value::String(String::from_utf8_lossy(s).into_owned()),
+                        Cell::List(l) => {
+                            Value::Sequence(l.into_iter().map(Value::String).collect())
+                        }
+                    };
+
+                    row.insert(h.clone(), value);
+                }
+
+                // one-element sequences are written for every row so the output stays a single
+                // sequence without buffering it
+                serde_norway::to_writer(&mut buffered_writer, &[Value::Mapping(row)])
+                    .map_err(|e| Error::other(format!("Failed to write into file: {}", e)))
+                    .with_context(|| {
+                        format!("Failed to write record: {} into output file", line_no)
+                    })?;
+
+                empty = false;
+            }
+
+            if empty {
+                buffered_writer.write_all(b"[]\n").context("Failed to write empty sequence")?;
+            }
+        }
+
+        WriterStreams::Ndjson { values } => {
+            let mut empty = true;
+
+            for (rec_no, rec) in values.enumerate() {
+                let rec_no = rec_no + 1;
+
+                let obj = match rec
+                    .and_then(DataTypes::into_json)
+                    .context("Failed to re-serialize object for writing")
+                {
+                    Ok(ok) => ok,
+                    Err(e) => {
+                        conv.log_skip::<Error>(&e)?;
+                        continue;
+                    }
+                };
+
+                serde_norway::to_writer(&mut buffered_writer, &[obj])
+                    .map_err(|e| Error::other(format!("Failed to write into file: {}", e)))
+                    .with_context(|| {
+                        format!("Failed to write record: {} into output file", rec_no)
+                    })?;
+
+                empty = false;
+            }
+
+            if empty {
+                buffered_writer.write_all(b"[]\n").context("Failed to write empty sequence")?;
+            }
+        }
+    }
+
+    buffered_writer.flush().context("Failed to flush final bytes into output file")?;
+
+    Ok(())
+}
@@ -80,7 +80,7 @@ fn test_error_logging_to_file() -> CtxResult<(), Error> {
         .arg("-o")
         .arg(output.path())
         .assert()
-        .success(); // Should still succeed (graceful error handling)
+        .code(2); // Should still finish (graceful error handling), skipped records exit with 2
 
     // Check log file has error content
     let log_content = fs::read_to_string(log.path()).context("Failed to read output file")?;
@@ -260,6 +260,30 @@ tempfile = "3.24.0"
     Ok(())
 }
 
+#[test]
+fn test_toml_to_yaml() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".toml").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".yaml").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "released = 1979-05-27T07:32:00Z\n")
+        .context("Failed to write input TempFile contents")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let result = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(result, "released: 1979-05-27T07:32:00Z\n");
+
+    Ok(())
+}
+
 #[test]
 fn test_ndjson_to_toml() -> resext::CtxResult<(), Error> {
     let input =
@@ -286,7 +310,1649 @@ fn test_ndjson_to_toml() -> resext::CtxResult<(), Error> {
         .assert()
         .success();
 
-    fs::read_to_string(output.path()).context("Failed to read output file")?;
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+
+    // nested tables are written under their record's `[[Array]]` element
+    let parsed: toml::Value = toml::from_str(&out).expect("output should be valid TOML");
+    assert_eq!(parsed["Array"][0]["c"]["sum"].as_integer(), Some(6));
+    assert_eq!(parsed["Array"][2]["nums and squares"][4]["5"].as_integer(), Some(25));
+    assert!(out.contains("[Array.c]\narr = [1, 2, 3]\nsum = 6\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_ndjson_to_csv_flattening() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to crate input TempFile")?;
+    let output =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(
+        input.path(),
+        r#"{"name":"Alice","address":{"city":"NYC"},"tags":["a","b"]}
+{"name":"Bob","age":25,"address":{"city":"LA"}}
+"#,
+    )
+    .context("Failed to write input TempFile contents")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let result = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    let mut lines = result.lines();
+
+    // late `age` key is part of the header row with the default `union` policy
+    assert_eq!(lines.next(), Some("name,address.city,tags.0,tags.1,age"));
+    assert_eq!(lines.next(), Some("Alice,NYC,a,b,"));
+    assert_eq!(lines.next(), Some("Bob,LA,,,25"));
+
+    Ok(())
+}
+
+#[test]
+fn test_empty_records_to_csv() -> CtxResult<(), Error> {
+    for (from, input) in [("json", "[]"), ("ndjson", ""), ("ndjson", "{}\n{\"a\":1}\n")] {
+        let assert = assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+            .arg("convert")
+            .arg("-")
+            .arg("--from")
+            .arg(from)
+            .arg("-o")
+            .arg("-")
+            .arg("--to")
+            .arg("csv")
+            .write_stdin(input)
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+        let expected = if input.is_empty() || input == "[]" { "" } else { "a\n\"\"\n1\n" };
+        assert_eq!(stdout, expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_json_to_csv_late_keys_error() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".json").tempfile().context("Failed to crate input TempFile")?;
+    let output =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), r#"[{"a": 1}, {"a": 2, "b": 3}, {"a": 4}]"#)
+        .context("Failed to write input TempFile contents")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--late-keys")
+        .arg("error")
+        .assert()
+        .code(2);
+
+    let result = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(result, "a\n1\n4\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_stdin_to_stdout() -> CtxResult<(), Error> {
+    let assert = assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg("-")
+        .arg("--from")
+        .arg("csv")
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("ndjson")
+        .write_stdin("name,age\nAlice,30\nBob,25\n")
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert_eq!(
+        stdout,
+        "{\"name\": \"Alice\", \"age\": \"30\"}\n{\"name\": \"Bob\", \"age\": \"25\"}\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_stdin_undetectable_format() -> CtxResult<(), Error> {
+    assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg("-")
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("json")
+        .write_stdin("just some words")
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_unsupported_format_keeps_output() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".docx").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "a\n1\n").context("Failed to write input TempFile")?;
+    fs::write(output.path(), "precious").context("Failed to write output TempFile")?;
+
+    for to in [None, Some("jsn")] {
+        let mut cmd = Command::new(cargo::cargo_bin!("fiux"));
+        cmd.arg("convert").arg(input.path()).arg("-o").arg(output.path());
+        if let Some(to) = to {
+            cmd.arg("--to").arg(to);
+        }
+        cmd.assert().failure();
+
+        let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+        assert_eq!(out, "precious");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_format_sniffing_without_extension() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".txt").tempfile().context("Failed to crate input TempFile")?;
+    let output =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "{\"a\": 1}\n{\"a\": 2}\n")
+        .context("Failed to write input TempFile contents")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("Detected input format"));
+    assert!(stderr.contains("ndjson"));
+
+    let result = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(result, "a\n1\n2\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_format_sniffing_tsv() -> CtxResult<(), Error> {
+    let input = Builder::new().tempfile().context("Failed to crate input TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "name\tcity\nAlice\tNYC\n")
+        .context("Failed to write input TempFile contents")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let result = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(result, "{\"name\": \"Alice\", \"city\": \"NYC\"}\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_yaml_multi_document_to_ndjson() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".yaml").tempfile().context("Failed to crate input TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "name: Alice\ntags: [a, b]\n---\nname: Bob\nage: 25\n")
+        .context("Failed to write input TempFile contents")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let result = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(
+        result,
+        "{\"name\":\"Alice\",\"tags\":[\"a\",\"b\"]}\n{\"name\":\"Bob\",\"age\":25}\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_csv_to_yaml() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to crate input TempFile")?;
+    let output =
+        Builder::new().suffix(".yml").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "name,age,active\nAlice,30,true\n")
+        .context("Failed to write input TempFile contents")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let result = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(result, "- name: Alice\n  age: '30'\n  active: true\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_yaml_validation_fail() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".yaml").tempfile().context("Failed to crate input TempFile")?;
+
+    fs::write(input.path(), "a: [1, 2\nb: 3\n")
+        .context("Failed to write input TempFile contents")?;
+
+    Command::new(cargo::cargo_bin!("fiux")).arg("validate").arg(input.path()).assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_library_decoder_encoder() -> CtxResult<(), Error> {
+    use fiux::{Decoder, Encoder, Options, formats};
+
+    let options = Options { parse_numbers: true, ..Options::default() };
+    let mut output = Vec::new();
+
+    let data = formats::Csv.decode("a,b\n1,x\n".as_bytes(), &options)?;
+    formats::Ndjson.encode(data, &mut output, &options)?;
+
+    assert_eq!(String::from_utf8_lossy(&output), "{\"a\": 1, \"b\": \"x\"}\n");
+
+    // encoders used on their own still stop once the error budget is used up
+    let options = Options {
+        types: vec![("a".to_string(), fiux::ColumnType::Int)],
+        max_errors: Some(1),
+        ..Options::default()
+    };
+
+    let data = formats::Csv.decode("a\nx\n1\n".as_bytes(), &options)?;
+    assert!(formats::Ndjson.encode(data, &mut Vec::new(), &options).is_err());
+
+    // records that don't match their stream's variant are skipped instead of panicking
+    let row = fiux::DataTypes::Csv(csv::ByteRecord::from(vec!["1"]));
+    let value = fiux::DataTypes::Json(serde_json::json!({"a": 2}));
+
+    let mut output = Vec::new();
+    let data = fiux::WriterStreams::Ndjson { values: vec![Ok(row), Ok(value)].into_iter() };
+    formats::Yaml.encode(data, &mut output, &Options::default())?;
+    assert_eq!(String::from_utf8_lossy(&output), "- a: 2\n");
+
+    let data = fiux::WriterStreams::Table {
+        headers: vec!["a".to_string()],
+        iter: vec![Ok(fiux::DataTypes::Json(serde_json::json!({"a": 1})))].into_iter(),
+    };
+    formats::Toml.encode(data, &mut Vec::new(), &Options::default())?;
+
+    Ok(())
+}
+
+#[test]
+fn test_compressed_round_trip() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let compressed = Builder::new()
+        .suffix(".ndjson.gz")
+        .tempfile()
+        .context("Failed to create compressed TempFile")?;
+    let output =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "name,age\nAlice,30\nBob,25\n")
+        .context("Failed to write input file")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(compressed.path())
+        .assert()
+        .success();
+
+    let bytes = fs::read(compressed.path()).context("Failed to read compressed file")?;
+    assert_eq!(&bytes[..2], &[0x1F, 0x8B]);
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(compressed.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "name,age\nAlice,30\nBob,25\n");
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("validate")
+        .arg(compressed.path())
+        .assert()
+        .success();
+
+    Ok(())
+}
+
+#[test]
+fn test_truncated_compressed_input() -> CtxResult<(), Error> {
+    use std::io::Write;
+
+    let input =
+        Builder::new().suffix(".csv.gz").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    let rows: String = (0..20_000).map(|i| format!("{},name{}\n", i, i * 7919 % 10_007)).collect();
+
+    let mut gz = fiux::compress(Vec::new(), fiux::Codec::Gzip, None)?;
+    gz.write_all(format!("id,name\n{}", rows).as_bytes()).context("Failed to compress input")?;
+    let gz = gz.finish().context("Failed to finish compressed input")?;
+
+    // a truncated stream is a read failure, not an invalid record
+    fs::write(input.path(), &gz[..gz.len() / 2]).context("Failed to write input TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .failure();
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Invalid CSV data in input file"), "{}", stderr);
+    assert!(!stderr.contains("skipped"), "{}", stderr);
+
+    let mut gz = fiux::compress(Vec::new(), fiux::Codec::Gzip, None)?;
+    for line in rows.lines() {
+        let (id, name) = line.split_once(',').unwrap_or_default();
+        writeln!(gz, "{{\"id\": {}, \"name\": \"{}\"}}", id, name)
+            .context("Failed to compress input")?;
+    }
+    let gz = gz.finish().context("Failed to finish compressed input")?;
+
+    fs::write(input.path(), &gz[..gz.len() / 2]).context("Failed to write input TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("--from")
+        .arg("ndjson")
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .failure();
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Failed to read line"), "{}", stderr);
+    assert!(!stderr.contains("skipped"), "{}", stderr);
+
+    Ok(())
+}
+
+#[test]
+fn test_compress_flag_to_stdout() -> CtxResult<(), Error> {
+    let assert = assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg("-")
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("csv")
+        .arg("--compress")
+        .arg("zstd")
+        .arg("--level")
+        .arg("19")
+        .write_stdin("{\"a\": 1, \"b\": 2}\n{\"a\": 3, \"b\": 4}\n")
+        .assert()
+        .success();
+
+    let compressed = assert.get_output().stdout.clone();
+    assert_eq!(&compressed[..4], &[0x28, 0xB5, 0x2F, 0xFD]);
+
+    let assert = assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg("-")
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("ndjson")
+        .write_stdin(compressed)
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert_eq!(stdout, "{\"a\": \"1\", \"b\": \"2\"}\n{\"a\": \"3\", \"b\": \"4\"}\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_compression_level() -> CtxResult<(), Error> {
+    assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg("-")
+        .arg("--from")
+        .arg("csv")
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("csv")
+        .arg("--compress")
+        .arg("gz")
+        .arg("--level")
+        .arg("12")
+        .write_stdin("a\n1\n")
+        .assert()
+        .failure();
+
+    // negative levels are zstd's faster modes
+    for (level, ok) in [("-5", true), ("-131073", false)] {
+        let assert = assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+            .arg("convert")
+            .arg("-")
+            .arg("--from")
+            .arg("csv")
+            .arg("-o")
+            .arg("-")
+            .arg("--to")
+            .arg("csv")
+            .arg("--compress")
+            .arg("zstd")
+            .arg("--level")
+            .arg(level)
+            .write_stdin("a\n1\n")
+            .assert();
+
+        if ok {
+            assert.success()
+        } else {
+            assert.failure()
+        };
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reject_file() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create output TempFile")?;
+    let rejects =
+        Builder::new().suffix(".txt").tempfile().context("Failed to create reject TempFile")?;
+
+    fs::write(input.path(), "{\"a\": 1}\n{\"a\": 2,\n\n{\"a\": 3}\n")
+        .context("Failed to write input file")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--reject-file")
+        .arg(rejects.path())
+        .assert()
+        .code(2);
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "a\n1\n3\n");
+
+    let rejected = fs::read_to_string(rejects.path()).context("Failed to read reject file")?;
+    assert_eq!(rejected, "2\t{\"a\": 2,\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_reject_file_csv_rows() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+    let rejects =
+        Builder::new().suffix(".txt").tempfile().context("Failed to create reject TempFile")?;
+
+    fs::write(input.path(), "name,age\nAlice,30\nBob\nCarol,  \"41\" ,extra\nDan,25\nEve,old\n")
+        .context("Failed to write input file")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--reject-file")
+        .arg(rejects.path())
+        .arg("--types")
+        .arg("age=string")
+        .assert()
+        .code(2);
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(
+        out,
+        "{\"name\": \"Alice\", \"age\": \"30\"}\n{\"name\": \"Dan\", \"age\": \"25\"}\n{\"name\": \"Eve\", \"age\": \"old\"}\n"
+    );
+
+    let rejected = fs::read_to_string(rejects.path()).context("Failed to read reject file")?;
+    // rows that fail to decode keep their original bytes
+    assert_eq!(rejected, "3\tBob\n4\tCarol,  \"41\" ,extra\n");
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--reject-file")
+        .arg(rejects.path())
+        .arg("--types")
+        .arg("age=int")
+        .assert()
+        .code(2);
+
+    // rows that fail to encode are written into the reject file too
+    let rejected = fs::read_to_string(rejects.path()).context("Failed to read reject file")?;
+    assert_eq!(rejected, "3\tBob\n4\tCarol,  \"41\" ,extra\n6\tEve,old\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_max_errors_aborts() -> CtxResult<(), Error> {
+    assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg("-")
+        .arg("--from")
+        .arg("ndjson")
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("ndjson")
+        .arg("--max-errors")
+        .arg("2")
+        .write_stdin("{\"a\": 1}\n{bad\n{\"a\": 2}\n{bad\n{\"a\": 3}\n")
+        .assert()
+        .code(1);
+
+    let assert = assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg("-")
+        .arg("--from")
+        .arg("ndjson")
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("ndjson")
+        .arg("--max-errors")
+        .arg("3")
+        .write_stdin("{\"a\": 1}\n{bad\n{\"a\": 2}\n{bad\n{\"a\": 3}\n")
+        .assert()
+        .code(2);
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert_eq!(stdout, "{\"a\":1}\n{\"a\":2}\n{\"a\":3}\n");
+
+    // input that can't be read to its end is a failure even when records may be skipped
+    let mut gz = fiux::compress(Vec::new(), fiux::Codec::Gzip, None)?;
+    let lines: String =
+        (0..20_000).map(|i| format!("{{\"a\": {}}}\n", i * 7919 % 10_007)).collect();
+    std::io::Write::write_all(&mut gz, lines.as_bytes()).context("Failed to compress input")?;
+    let gz = gz.finish().context("Failed to finish compressed input")?;
+
+    assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg("-")
+        .arg("--from")
+        .arg("ndjson")
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("ndjson")
+        .arg("--max-errors")
+        .arg("3")
+        .write_stdin(gz[..gz.len() / 2].to_vec())
+        .assert()
+        .code(1);
+
+    Ok(())
+}
+
+#[test]
+fn test_strict_leaves_no_partial_output() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".json").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(output.path(), "previous output").context("Failed to write output file")?;
+    fs::write(input.path(), "{\"a\": 1}\n{\"a\": 2}\n{bad\n")
+        .context("Failed to write input file")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--strict")
+        .assert()
+        .code(1);
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "previous output");
+
+    fs::write(input.path(), "{\"a\": 1}\n{\"a\": 2}\n").context("Failed to write input file")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--strict")
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert!(out.contains("\"a\": 2"));
+
+    Ok(())
+}
+
+#[test]
+fn test_ndjson_log_format() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create input TempFile")?;
+
+    fs::write(input.path(), "{\"a\": 1}\n{a: 2}\n").context("Failed to write input TempFile")?;
+
+    let out = Command::new(cargo::cargo_bin!("fiux"))
+        .args(["--log-format", "ndjson", "validate"])
+        .arg(input.path())
+        .assert()
+        .code(1)
+        .get_output()
+        .stderr
+        .clone();
+
+    let events: Vec<serde_json::Value> = String::from_utf8_lossy(&out)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("log lines should be JSON"))
+        .collect();
+
+    let error = events.iter().find(|e| e["event"] == "error").expect("missing error event");
+    assert_eq!(error["line"], 2);
+    assert_eq!(error["column"], 2);
+    assert_eq!(error["offset"], 9);
+    assert!(error["context"].as_array().is_some_and(|ctx| !ctx.is_empty()));
+
+    let summary = events.last().expect("missing summary event");
+    assert_eq!(summary["event"], "summary");
+    assert_eq!(summary["success"], false);
+    assert_eq!(summary["errors"], 1);
+
+    let out = Command::new(cargo::cargo_bin!("fiux"))
+        .args(["--log-format", "ndjson", "convert", "missing.csv", "-o", "out.json"])
+        .assert()
+        .code(1)
+        .get_output()
+        .stderr
+        .clone();
+
+    let fatal: serde_json::Value = String::from_utf8_lossy(&out)
+        .lines()
+        .next()
+        .and_then(|line| serde_json::from_str(line).ok())
+        .expect("missing fatal event");
+    assert_eq!(fatal["event"], "fatal");
+    assert_eq!(fatal["message"], "Input file: missing.csv doesn't exist");
+
+    Ok(())
+}
+
+#[test]
+fn test_code_frames() -> CtxResult<(), Error> {
+    let json =
+        Builder::new().suffix(".json").tempfile().context("Failed to create input TempFile")?;
+    let toml =
+        Builder::new().suffix(".toml").tempfile().context("Failed to create input TempFile")?;
+
+    fs::write(json.path(), "[\n{\"a\": 1},\n{\"a\": }\n]\n").context("Failed to write JSON")?;
+    fs::write(toml.path(), "a = 1\nb = = 2\n").context("Failed to write TOML")?;
+
+    let assert =
+        Command::new(cargo::cargo_bin!("fiux")).arg("validate").arg(json.path()).assert().code(1);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("3 | {\"a\": }\n  |       ^"));
+
+    let out = Command::new(cargo::cargo_bin!("fiux"))
+        .args(["--log-format", "ndjson", "validate"])
+        .arg(toml.path())
+        .assert()
+        .code(1)
+        .get_output()
+        .stderr
+        .clone();
+
+    let error: serde_json::Value = String::from_utf8_lossy(&out)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("log lines should be JSON"))
+        .find(|e: &serde_json::Value| e["event"] == "error")
+        .expect("missing error event");
+
+    assert_eq!(error["kind"], "Syntax");
+    assert_eq!(error["line"], 2);
+    assert_eq!(error["column"], 5);
+    assert_eq!(error["offset"], 10);
+
+    Ok(())
+}
+
+#[test]
+fn test_json_array_streams_elements() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".json").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+    let rejects = tempfile::NamedTempFile::new().context("Failed to create reject TempFile")?;
+
+    fs::write(input.path(), "[\n  {\"a\": 1, \"s\": \"x,]\"},\n  {\"a\": },\n  {\"a\": 3}\n]\n")
+        .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--reject-file")
+        .arg(rejects.path())
+        .assert()
+        .code(2);
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "{\"a\":1,\"s\":\"x,]\"}\n{\"a\":3}\n");
+
+    let rejected = fs::read_to_string(rejects.path()).context("Failed to read reject file")?;
+    assert_eq!(rejected, "3\t{\"a\": }\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_xml_round_trip() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".xml").tempfile().context("Failed to create input TempFile")?;
+    let ndjson =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+    let xml =
+        Builder::new().suffix(".xml").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(
+        input.path(),
+        "<?xml version=\"1.0\"?>\n<feed>\n  <meta>x</meta>\n  <item id=\"1\"><tag>a &amp; b</tag><tag>c</tag><price cur=\"EUR\">9.5</price></item>\n  <item id=\"2\"/>\n</feed>\n",
+    )
+    .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(ndjson.path())
+        .arg("--xml-record")
+        .arg("item")
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(ndjson.path()).context("Failed to read output file")?;
+    assert_eq!(
+        out,
+        "{\"@id\":\"1\",\"tag\":[\"a & b\",\"c\"],\"price\":{\"@cur\":\"EUR\",\"#text\":\"9.5\"}}\n{\"@id\":\"2\"}\n"
+    );
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(ndjson.path())
+        .arg("-o")
+        .arg(xml.path())
+        .arg("--xml-root")
+        .arg("feed")
+        .arg("--xml-row")
+        .arg("item")
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(xml.path()).context("Failed to read output file")?;
+    assert_eq!(
+        out,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed>\n  <item id=\"1\">\n    <tag>a &amp; b</tag>\n    <tag>c</tag>\n    <price cur=\"EUR\">9.5</price>\n  </item>\n  <item id=\"2\"/>\n</feed>\n"
+    );
+
+    // records with control characters or keys that collide as attributes are skipped
+    fs::write(ndjson.path(), "{\"a\":\"x\\u0001\"}\n{\"@a b\":1,\"@a_b\":2}\n{\"a\":\"ok\"}\n")
+        .context("Failed to write input TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(ndjson.path())
+        .arg("-o")
+        .arg(xml.path())
+        .assert()
+        .code(2);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Control character U+0001 isn't allowed in XML"));
+    assert!(stderr.contains("Keys: @a b and @a_b are both written as the attribute: a_b"));
+
+    let out = fs::read_to_string(xml.path()).context("Failed to read output file")?;
+    assert!(out.contains("<a>ok</a>") && !out.contains('\u{1}') && !out.contains("a_b"));
+
+    Command::new(cargo::cargo_bin!("fiux")).arg("validate").arg(xml.path()).assert().success();
+
+    fs::write(xml.path(), "<feed><a>x\u{1}</a></feed>").context("Failed to write TempFile")?;
+    Command::new(cargo::cargo_bin!("fiux")).arg("validate").arg(xml.path()).assert().code(1);
+
+    Ok(())
+}
+
+#[test]
+fn test_select_exclude_rename() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "id,name,city,zip\n1,Alice,NYC,10001\n2,Bob,LA,90001\n")
+        .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--select")
+        .arg("city,id,zip")
+        .arg("--exclude")
+        .arg("zip")
+        .arg("--rename")
+        .arg("city=town")
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "town,id\nNYC,1\nLA,2\n");
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--select")
+        .arg("town")
+        .assert()
+        .code(1);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Column: town doesn't exist in the header row"));
+
+    Ok(())
+}
+
+#[test]
+fn test_where_filters_records() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(
+        input.path(),
+        "{\"name\":\"Alice\",\"age\":30,\"address\":{\"city\":\"NYC\"}}\n{\"name\":\"Bob\",\"age\":17,\"address\":{\"city\":\"NYC\"}}\n{\"name\":\"Cy\",\"age\":45,\"address\":null}\n",
+    )
+    .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--where")
+        .arg("age >= 18 and (address.city == 'NYC' or address is null) and not name =~ '^C'")
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "{\"name\":\"Alice\",\"age\":30,\"address\":{\"city\":\"NYC\"}}\n");
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--where")
+        .arg("age >")
+        .assert()
+        .code(1);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("unexpected end of expression at column 6"));
+
+    Ok(())
+}
+
+#[test]
+fn test_csv_dialect_options() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "# exported nightly\n1 ,'a,b','it\\'s'\n2,'x', y\n")
+        .context("Failed to write input TempFile")?;
+
+    let dialect =
+        ["--input-quote", "'", "--input-escape", "\\", "--comment", "#", "--trim", "--no-header"];
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .args(dialect)
+        .arg("--header-names")
+        .arg("id,name,note")
+        .arg("--quote-style")
+        .arg("non-numeric")
+        .arg("--output-terminator")
+        .arg("crlf")
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "\"id\",\"name\",\"note\"\r\n1,\"a,b\",\"it's\"\r\n2,\"x\",\"y\"\r\n");
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("validate")
+        .arg(input.path())
+        .args(dialect)
+        .assert()
+        .success();
+
+    Ok(())
+}
+
+#[test]
+fn test_ragged_csv_policies() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "a,b,c\n1,2\n3,4,5,6,7\n8,9,10\n")
+        .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--ragged")
+        .arg("extra-column")
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "a,b,c,_extra\n1,2,,[]\n3,4,5,\"[\"\"6\"\",\"\"7\"\"]\"\n8,9,10,[]\n");
+
+    let json =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(json.path())
+        .arg("--ragged")
+        .arg("extra-column")
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(json.path()).context("Failed to read output file")?;
+    let mut lines = out.lines();
+    assert_eq!(lines.next(), Some(r#"{"a": "1", "b": "2", "c": "", "_extra": []}"#));
+    assert_eq!(lines.next(), Some(r#"{"a": "3", "b": "4", "c": "5", "_extra": ["6","7"]}"#));
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--ragged")
+        .arg("pad")
+        .assert()
+        .code(2);
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "a,b,c\n1,2,\n8,9,10\n");
+
+    let assert =
+        Command::new(cargo::cargo_bin!("fiux")).arg("validate").arg(input.path()).assert().code(1);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Record has 2 fields, but the header row has 3"));
+    assert!(stderr.contains("Record has 5 fields, but the header row has 3"));
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_csv_types() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let output =
+        Builder::new().suffix(".toml").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(
+        input.path(),
+        "id,zip,big,price,since,ok\n1,02134,12345678901234567890,9.5,2024-05-01,true\n2,10001,x,1e3,2024-05-01T12:30:00Z,null\n",
+    )
+    .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("-p")
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(
+        out,
+        "[[Rows]]\nid = 1\nzip = \"02134\"\nbig = \"12345678901234567890\"\nprice = 9.5\nsince = 2024-05-01\nok = true\n\n[[Rows]]\nid = 2\nzip = 10001\nbig = \"x\"\nprice = 1000.0\nsince = 2024-05-01T12:30:00Z\n"
+    );
+
+    // forced types override inference and records that don't match them are skipped
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("-p")
+        .arg("--types")
+        .arg("zip=string,big=int")
+        .assert()
+        .code(2);
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(
+        out,
+        "{\"id\": 1, \"zip\": \"02134\", \"big\": 12345678901234567890, \"price\": 9.5, \"since\": \"2024-05-01\", \"ok\": true}\n"
+    );
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Field: big = `x` is not a valid int"));
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--types")
+        .arg("missing=int")
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_schema_profiles_fields() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+
+    fs::write(input.path(), "id,zip,name,since\n1,02134,Alice,2024-05-01\n2,10001,,2023-01-02\n")
+        .context("Failed to write input TempFile")?;
+
+    let assert =
+        Command::new(cargo::cargo_bin!("fiux")).arg("schema").arg(input.path()).assert().success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(stdout.starts_with("Records: 2\n"));
+    let id: Vec<&str> = stdout
+        .lines()
+        .find(|l| l.starts_with("id "))
+        .map(|l| l.split_whitespace().collect())
+        .unwrap_or_default();
+    assert_eq!(id, ["id", "integer", "2/2", "0", "1", "2", "2", "1,", "2"]);
+    assert!(stdout.lines().any(|l| l.starts_with("since") && l.contains(" date ")));
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("schema")
+        .arg(input.path())
+        .arg("--format")
+        .arg("json-schema")
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(stdout.starts_with("{\n  \"$schema\": "));
+
+    let schema: serde_json::Value =
+        serde_json::from_slice(&assert.get_output().stdout).expect("schema output should be JSON");
+
+    assert_eq!(schema["required"], serde_json::json!(["id", "zip", "name", "since"]));
+    assert_eq!(schema["properties"]["name"]["type"], serde_json::json!(["string", "null"]));
+    assert_eq!(schema["properties"]["since"]["format"], "date");
+    assert_eq!(schema["properties"]["zip"]["type"], serde_json::json!(["integer", "string"]));
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_json_schema() -> CtxResult<(), Error> {
+    let schema =
+        Builder::new().suffix(".json").tempfile().context("Failed to create schema TempFile")?;
+    let input =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create input TempFile")?;
+
+    fs::write(
+        schema.path(),
+        r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "integer", "minimum": 1}, "zip": {"type": "string", "pattern": "^\\d{5}$"}, "tags": {"items": {"enum": ["a", "b"]}}}, "additionalProperties": false}"#,
+    )
+    .context("Failed to write schema TempFile")?;
+
+    fs::write(
+        input.path(),
+        "{\"id\": 1, \"zip\": \"02134\", \"tags\": [\"a\"]}\n{\"id\": 0, \"tags\": [\"a\", \"c\"]}\n{\"zip\": \"1\", \"x\": 1}\n",
+    )
+    .context("Failed to write input TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("validate")
+        .arg(input.path())
+        .arg("--schema")
+        .arg(schema.path())
+        .assert()
+        .code(1);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Record: 2 doesn't match the JSON Schema"));
+    assert!(stderr.contains("Schema violation at `/id`: 0 is less than the minimum 1"));
+    assert!(
+        stderr.contains("Schema violation at `/tags/1`: \"c\" isn't one of the allowed values")
+    );
+    assert!(stderr.contains("Schema violation at the root: missing required property `id`"));
+    assert!(stderr.contains("Schema violation at the root: additional property `x` isn't allowed"));
+    assert!(stderr.contains("Schema violation at `/zip`: \"1\" doesn't match the pattern"));
+
+    // CSV rows are checked as objects, fields that are strings in the schema stay strings
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+
+    fs::write(input.path(), "id,zip\n1,02134\n2,10001\n")
+        .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("validate")
+        .arg(input.path())
+        .arg("--schema")
+        .arg(schema.path())
+        .assert()
+        .success();
+
+    Ok(())
+}
+
+#[test]
+fn test_msgpack_round_trip() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let packed =
+        Builder::new().suffix(".mpk").tempfile().context("Failed to create output TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "id,name,score\n1,Alice,9.5\n2,Bob,\n")
+        .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(packed.path())
+        .arg("-p")
+        .assert()
+        .success();
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(
+        out,
+        "{\"id\":1,\"name\":\"Alice\",\"score\":9.5}\n{\"id\":2,\"name\":\"Bob\",\"score\":\"\"}\n"
+    );
+
+    // binary blobs and ext values, followed by a truncated value
+    fs::write(packed.path(), b"\x81\xa1a\xc4\x02\x01\x02\xd4\x05\x07\x82\xa1b")
+        .context("Failed to write input TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--binary")
+        .arg("array")
+        .assert()
+        .code(2);
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "{\"a\":[1,2]}\n{\"$ext\":{\"type\":5,\"data\":[7]}}\n");
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Invalid MessagePack"));
+
+    Command::new(cargo::cargo_bin!("fiux")).arg("validate").arg(packed.path()).assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_cbor_round_trip() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let packed =
+        Builder::new().suffix(".cbor").tempfile().context("Failed to create output TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "id,big,since\n1,123456789012345678901234567890,2024-05-01\n")
+        .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(packed.path())
+        .arg("-p")
+        .assert()
+        .success();
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(
+        out,
+        "{\"id\":1,\"big\":\"123456789012345678901234567890\",\"since\":\"2024-05-01\"}\n"
+    );
+
+    // an epoch datetime followed by an item with a reserved additional info
+    fs::write(packed.path(), b"\xa1\x61t\xc1\x1a\x65\x53\xf1\x00\x1c")
+        .context("Failed to write input TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .code(2);
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "{\"t\":\"2023-11-14T22:13:20Z\"}\n");
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("reserved additional info 28 in the item starting at byte 9"));
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg("--cbor-tags")
+        .arg("preserve")
+        .assert()
+        .code(2);
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "{\"t\":{\"$tag\":1,\"$value\":1700000000}}\n");
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("validate")
+        .arg(packed.path())
+        .assert()
+        .failure();
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Invalid CBOR data in input file"));
+    assert!(stderr.contains("starting at byte 9"));
+
+    // top-level scalars are written as their own lines
+    fs::write(packed.path(), b"\x01\x61x").context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(out, "1\n\"x\"\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_parquet_round_trip() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let packed =
+        Builder::new().suffix(".parquet").tempfile().context("Failed to create output TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "id,price,since\n1,2.5,2024-05-01\n2,3,\n3,x,2024-05-03\n")
+        .context("Failed to write input TempFile")?;
+
+    // the third row doesn't fit the float column inferred from the first row group
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(packed.path())
+        .arg("-p")
+        .arg("--row-group-size")
+        .arg("2")
+        .arg("--parquet-compression")
+        .arg("zstd")
+        .assert()
+        .code(2);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(
+        stderr.contains("field: price = `x` which doesn't match its Parquet column type Float64")
+    );
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(
+        out,
+        "{\"id\":1,\"price\":2.5,\"since\":\"2024-05-01\"}\n{\"id\":2,\"price\":3.0,\"since\":null}\n"
+    );
+
+    Command::new(cargo::cargo_bin!("fiux")).arg("validate").arg(packed.path()).assert().success();
+
+    fs::write(packed.path(), b"PAR1 not really parquet").context("Failed to write TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("validate")
+        .arg(packed.path())
+        .assert()
+        .failure();
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Invalid Parquet data in input file"));
+
+    // keys that first appear after the first row group aren't silently dropped
+    fs::write(output.path(), "{\"a\":1}\n{\"a\":2}\n{\"a\":3,\"new\":5}\n")
+        .context("Failed to write input TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(output.path())
+        .arg("-o")
+        .arg(packed.path())
+        .arg("--row-group-size")
+        .arg("2")
+        .assert()
+        .code(2);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Record: 3 doesn't match the Parquet schema"), "{}", stderr);
+
+    Ok(())
+}
+
+#[test]
+fn test_avro_round_trip() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let schema =
+        Builder::new().suffix(".avsc").tempfile().context("Failed to create schema TempFile")?;
+    let packed =
+        Builder::new().suffix(".avro").tempfile().context("Failed to create output TempFile")?;
+    let output =
+        Builder::new().suffix(".ndjson").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "id,price,day\n1,12.5,2024-05-01\nx,1,2024-05-02\n2,,2024-05-03\n")
+        .context("Failed to write input TempFile")?;
+    fs::write(
+        schema.path(),
+        r#"{"type": "record", "name": "Sale", "fields": [
+            {"name": "id", "type": "long"},
+            {"name": "price", "type": ["null", {"type": "bytes", "logicalType": "decimal", "precision": 6, "scale": 2}]},
+            {"name": "day", "type": {"type": "int", "logicalType": "date"}}
+        ]}"#,
+    )
+    .context("Failed to write schema TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(packed.path())
+        .arg("--avro-schema")
+        .arg(schema.path())
+        .arg("--avro-codec")
+        .arg("deflate")
+        .assert()
+        .code(2);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Record: 2 doesn't match the Avro schema at: /id"));
+    assert!(stderr.contains("expected long, found \"x\""));
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg(output.path())
+        .assert()
+        .success();
+
+    let out = fs::read_to_string(output.path()).context("Failed to read output file")?;
+    assert_eq!(
+        out,
+        "{\"id\":1,\"price\":\"12.50\",\"day\":\"2024-05-01\"}\n{\"id\":2,\"price\":null,\"day\":\"2024-05-03\"}\n"
+    );
+
+    // without a schema it's inferred from the records
+    fs::write(output.path(), "{\"id\":1,\"tags\":[\"a\"]}\n{\"id\":2,\"tags\":[]}\n")
+        .context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(output.path())
+        .arg("-o")
+        .arg(packed.path())
+        .assert()
+        .success();
+
+    Command::new(cargo::cargo_bin!("fiux")).arg("validate").arg(packed.path()).assert().success();
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("ndjson")
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert_eq!(stdout, "{\"id\":1,\"tags\":[\"a\"]}\n{\"id\":2,\"tags\":[]}\n");
+
+    // leading zeros that `-p` keeps as text aren't coerced into the column's long branch
+    fs::write(input.path(), "zip\n02134\n10001\n").context("Failed to write input TempFile")?;
+
+    Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(packed.path())
+        .arg("-p")
+        .assert()
+        .success();
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("ndjson")
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert_eq!(stdout, "{\"zip\":\"02134\"}\n{\"zip\":10001}\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_arrow_round_trip() -> CtxResult<(), Error> {
+    let input =
+        Builder::new().suffix(".csv").tempfile().context("Failed to create input TempFile")?;
+    let packed =
+        Builder::new().suffix(".feather").tempfile().context("Failed to create output TempFile")?;
+
+    fs::write(input.path(), "id,at,note\n1,2024-05-01T00:00:01Z,a\n2,,b\n3,x,c\n")
+        .context("Failed to write input TempFile")?;
+
+    // the third row doesn't fit the timestamp column inferred from the first batch
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(input.path())
+        .arg("-o")
+        .arg(packed.path())
+        .arg("-p")
+        .arg("--batch-size")
+        .arg("2")
+        .arg("--arrow-compression")
+        .arg("lz4")
+        .assert()
+        .code(2);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("field: at = `x` which doesn't match its Arrow column type Timestamp"));
+
+    Command::new(cargo::cargo_bin!("fiux")).arg("validate").arg(packed.path()).assert().success();
+
+    // the file is converted into a stream and read back from stdin
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg(packed.path())
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("arrows")
+        .assert()
+        .success();
+
+    let stream = assert.get_output().stdout.clone();
+    assert!(stream.starts_with(b"\xff\xff\xff\xff"));
+
+    let assert = assert_cmd::Command::new(cargo::cargo_bin!("fiux"))
+        .arg("convert")
+        .arg("-")
+        .arg("-o")
+        .arg("-")
+        .arg("--to")
+        .arg("ndjson")
+        .write_stdin(stream)
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert_eq!(
+        stdout,
+        "{\"id\":1,\"at\":\"2024-05-01T00:00:01Z\",\"note\":\"a\"}\n{\"id\":2,\"at\":null,\"note\":\"b\"}\n"
+    );
+
+    fs::write(packed.path(), b"ARROW1 not really arrow").context("Failed to write TempFile")?;
+
+    let assert = Command::new(cargo::cargo_bin!("fiux"))
+        .arg("validate")
+        .arg(packed.path())
+        .assert()
+        .failure();
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Invalid Arrow data in input file"));
 
     Ok(())
 }